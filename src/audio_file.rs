@@ -121,6 +121,27 @@ impl AudioFile {
 
         Ok(result)
     }
+
+    /// Creates a new `AudioFile` from a local reader, bypassing downloads.
+    ///
+    /// The reader is wrapped in a 32 KiB buffer like downloads are. Local
+    /// data is never encrypted and always seekable.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The local audio data, like a file or an in-memory buffer
+    /// * `byte_len` - The total size of the audio data in bytes
+    #[must_use]
+    pub fn from_reader<R>(reader: R, byte_len: u64) -> Self
+    where
+        R: ReadSeek + 'static,
+    {
+        Self {
+            inner: Box::new(BufReader::with_capacity(BUFFER_LEN, reader)),
+            is_seekable: true,
+            byte_len: Some(byte_len),
+        }
+    }
 }
 
 /// Implements reading from the audio stream.
@@ -14,7 +14,7 @@
 //! use pleezer::config::{Config, Credentials};
 //! use pleezer::arl::Arl;
 //! use pleezer::protocol::connect::Percentage;
-//! use std::net::IpAddr;
+//! use std::{collections::BTreeSet, net::IpAddr, time::Duration};
 //!
 //! // Configure with ARL authentication, initial volume, and specific network binding
 //! let config = Config {
@@ -36,7 +36,12 @@
 //! };
 //! ```
 
-use std::net::IpAddr;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    net::IpAddr,
+    path::PathBuf,
+    time::Duration,
+};
 
 use regex_lite::Regex;
 use uuid::Uuid;
@@ -47,7 +52,11 @@ use crate::{
     decrypt::{KEY_LENGTH, Key},
     error::{Error, Result},
     http,
-    protocol::connect::{DeviceType, Percentage},
+    protocol::{
+        Codec,
+        connect::{AudioQuality, DeviceType, Percentage, Status},
+    },
+    track::{TrackId, TrackType},
 };
 
 /// Authentication methods for Deezer.
@@ -81,6 +90,26 @@ pub enum Credentials {
     Arl(Arl),
 }
 
+/// Placeholder metadata for hook scripts.
+///
+/// Applied when track metadata from the gateway is missing or empty, so
+/// hook scripts always receive well-formed values. Fields that are `None`
+/// leave the metadata as is.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MetadataFallbacks {
+    /// Title to use when a track has no title.
+    pub title: Option<String>,
+
+    /// Artist to use when a track has no artist.
+    pub artist: Option<String>,
+
+    /// Album title to use when a song has no album title.
+    pub album_title: Option<String>,
+
+    /// Cover ID to use when a track has no artwork.
+    pub cover_id: Option<String>,
+}
+
 /// Complete configuration for pleezer.
 ///
 /// Contains all settings needed to:
@@ -138,6 +167,13 @@ pub struct Config {
     /// string.
     pub app_version: String,
 
+    /// Override for the Deezer Connect protocol version.
+    ///
+    /// By default the version is derived from `app_version` in the form of
+    /// `Mmmppp`. Useful for compatibility testing when Deezer changes its
+    /// protocol expectations.
+    pub protocol_version: Option<String>,
+
     /// The language of the application in ISO 639-1 format.
     ///
     /// By default this is "en" for English, used in the `User-Agent` string,
@@ -160,20 +196,82 @@ pub struct Config {
     /// could not be retrieved.
     pub device_id: Uuid,
 
+    /// Audio quality per content type.
+    ///
+    /// Content types in this map are requested at this quality instead of
+    /// the quality from the account settings. Lower qualities are still
+    /// tried when the requested quality is unavailable.
+    pub quality_overrides: BTreeMap<TrackType, AudioQuality>,
+
     /// Whether to normalize the audio.
     ///
     /// By default this is `false`.
     pub normalization: bool,
 
+    /// Track types to normalize when `normalization` is enabled.
+    ///
+    /// By default this contains all track types.
+    pub normalization_types: BTreeSet<TrackType>,
+
     /// Whether to apply equal-loudness compensation.
     pub loudness: bool,
 
+    /// Reference level for equal-loudness compensation in LUFS.
+    ///
+    /// `None` to use the normalization target.
+    pub loudness_target: Option<i8>,
+
+    /// Duration of the fade-in at the start of each track.
+    ///
+    /// Not applied when resuming a track halfway. `None` to start tracks at
+    /// full volume.
+    pub fade_in: Option<Duration>,
+
+    /// Duration to fade through silence when skipping manually.
+    ///
+    /// The current track fades out to silence, and then the next track fades
+    /// in from silence; the tracks do not overlap. Tracks that end naturally
+    /// stay gapless. `None` to skip directly.
+    pub skip_fade: Option<Duration>,
+
+    /// Preferred codec for livestreams that offer both AAC and MP3.
+    ///
+    /// Either [`Codec::ADTS`] for AAC or [`Codec::MP3`]. Only decides between
+    /// sources of the same bitrate. By default AAC is preferred.
+    pub livestream_codec: Codec,
+
+    /// Codecs to trim encoder delay and padding of, for gapless playback.
+    ///
+    /// Tracks with an unknown codec are always trimmed. By default this
+    /// contains all codecs.
+    pub gapless_codecs: BTreeSet<Codec>,
+
+    /// Maximum bitrate of livestreams in kbps.
+    ///
+    /// Caps radio bitrates independently of the audio quality for songs.
+    /// `None` to only limit by the audio quality.
+    pub max_livestream_kbps: Option<usize>,
+
+    /// Directory with `ReplayGain` sidecar files.
+    ///
+    /// Consulted for tracks without Deezer gain or embedded `ReplayGain`
+    /// metadata, like user uploads. Each file is named after the track ID
+    /// with a `.txt` extension, and contains the track gain in dB.
+    pub replay_gain_dir: Option<PathBuf>,
+
     /// Initial volume level.
     ///
     /// Used when no volume is reported by Deezer client or when reported as maximum.
     /// None means no volume override.
     pub initial_volume: Option<Percentage>,
 
+    /// Minimum volume for any non-zero volume.
+    ///
+    /// Non-zero volumes are rescaled between this floor and full volume, so
+    /// that low volumes stay above the noise floor of some amplifiers. Zero
+    /// still mutes. `None` means no floor.
+    pub min_volume: Option<Percentage>,
+
     /// Dither bit depth based on DAC linearity (ENOB - Effective Number of Bits)
     ///
     /// This setting enables dithering to improve audio quality when reducing bit depth.
@@ -191,6 +289,12 @@ pub struct Config {
     /// * No dithering for floating point
     pub dither_bits: Option<f32>,
 
+    /// Bit depth above which dithering is skipped.
+    ///
+    /// Applies to the effective bit depth from `dither_bits` or its default.
+    /// `None` to always dither.
+    pub dither_threshold: Option<f32>,
+
     /// Noise shaping level for the dithering process.
     ///
     /// Uses psychoacoustic-optimized Shibata filters to shape quantization/dither noise
@@ -210,17 +314,246 @@ pub struct Config {
     /// The actual filter characteristics depend on the sample rate (44.1kHz or 48kHz).
     pub noise_shaping: u8,
 
+    /// Whether to reopen the output device when the sample rate changes.
+    ///
+    /// When a track starts with a different sample rate than the open output
+    /// device, the device is reopened at the rate of the track. Preloaded
+    /// tracks are still resampled to keep playback gapless. When `false`,
+    /// tracks are always resampled to the rate of the output device.
+    pub reopen_on_rate_change: bool,
+
+    /// Whether to count samples that clip after volume and dithering.
+    ///
+    /// Tracks that clipped are logged with their number of clipped samples
+    /// when they finish, to help tune normalization and noise shaping.
+    pub clip_detection: bool,
+
+    /// Time after which a paused player releases the output device.
+    ///
+    /// The Deezer Connect session is kept, and the device is opened again on
+    /// the next playback. `None` keeps the device open while paused.
+    pub idle_timeout: Option<Duration>,
+
+    /// Time to wait for a queue update when the position is beyond the queue.
+    ///
+    /// Afterwards, the position is clamped to the last track and an error
+    /// event is emitted. `None` to wait indefinitely.
+    pub position_timeout: Option<Duration>,
+
+    /// Whether to resample unsupported sample rates.
+    ///
+    /// Tracks with sample rates that noise shaping is not available for are
+    /// resampled to the nearest of 44.1 or 48 kHz. When `false`, such tracks
+    /// play at their own rate without noise shaping.
+    pub resample_unsupported: bool,
+
     /// Maximum amount of RAM in bytes that can be used for storing audio files.
     /// `None` means use temporary files instead of RAM.
     pub max_ram: Option<u64>,
 
+    /// Largest download in bytes to store in RAM instead of temporary files.
+    ///
+    /// Independent of the prefetch size. When `max_ram` is also set, the
+    /// lower of the two applies. `None` means only `max_ram` decides.
+    pub ram_threshold: Option<u64>,
+
+    /// Maximum number of tracks to preload ahead of the current track.
+    ///
+    /// Preloading more than one track smooths transitions between very
+    /// short tracks on fast connections, at the cost of memory or disk space.
+    pub preload_depth: usize,
+
+    /// Maximum number of sources queued for output, including the current track.
+    ///
+    /// Bounds preloading regardless of `preload_depth`, to keep memory
+    /// predictable. `None` means only `preload_depth` applies.
+    pub max_queued_sources: Option<usize>,
+
+    /// Real-time priority of the thread that feeds the output device, from 1 to 99.
+    ///
+    /// Reduces underruns on constrained hardware. Without privileges for
+    /// real-time scheduling, the thread is made less nice instead. `None`
+    /// keeps the default priority.
+    pub audio_priority: Option<u8>,
+
+    /// How long to wait for the audio output device to appear.
+    ///
+    /// Useful when the device is not yet available at boot, like a USB DAC
+    /// that enumerates late. `Duration::ZERO` means fail immediately.
+    pub device_timeout: Duration,
+
+    /// Maximum number of times to reconnect an interrupted livestream.
+    ///
+    /// Set to 0 to advance to the next track immediately.
+    pub livestream_retries: u32,
+
+    /// Delay before reconnecting an interrupted livestream.
+    ///
+    /// Doubles with every consecutive attempt.
+    pub livestream_retry_delay: Duration,
+
+    /// Whether to resume livestreams from the live edge after pausing.
+    ///
+    /// The stream is reopened on resume instead of playing the audio that
+    /// was buffered when pausing, which lags behind real time.
+    pub livestream_live_edge: bool,
+
+    /// Number of times to request a source again that responded with 0 bytes.
+    ///
+    /// Some CDN edges transiently respond with an empty body. Set to 0 to
+    /// mark such tracks as unavailable immediately.
+    pub zero_byte_retries: u8,
+
+    /// Number of times a track may fail to load before it is marked unavailable.
+    ///
+    /// Failures are counted per track for the whole session, so a single
+    /// transient timeout does not skip a track for good. Must be at least 1.
+    pub load_failures: u8,
+
+    /// Maximum download rate in kbps, or `None` for no limit.
+    ///
+    /// Downloads are never limited below twice the bitrate of the track,
+    /// so playback keeps up. The prefetch buffer is filled at full speed.
+    pub max_download_kbps: Option<usize>,
+
+    /// Whether to verify the integrity of completed downloads.
+    ///
+    /// Downloads are checked against the length announced by the server, and
+    /// downloaded again once when truncated.
+    pub verify_downloads: bool,
+
+    /// Whether to check that the output device is still current on resume.
+    ///
+    /// The device is reopened when it changed while paused, like when a
+    /// Bluetooth speaker reconnects as the new default device.
+    pub verify_device: bool,
+
+    /// Delay before connecting to Deezer Connect the first time.
+    ///
+    /// Gives dependent services like the network time to come up at boot.
+    /// `Duration::ZERO` means no delay.
+    pub startup_delay: Duration,
+
+    /// Timeout of each network request while starting up.
+    ///
+    /// Applies to fetching the `bf_secret`, logging in and fetching the
+    /// user token the first time. More generous than the timeout during a
+    /// session, as a slow device on a cold network may need longer.
+    pub startup_timeout: Duration,
+
+    /// Number of times to retry a network request while starting up.
+    ///
+    /// Only timeouts and unavailable services are retried.
+    pub startup_retries: u32,
+
+    /// Whether to restart the client when the user token expires.
+    ///
+    /// By default the user token is refreshed and the websocket reconnected
+    /// in place, keeping the session and playback going. Restarting the
+    /// client falls back to the full connection sequence instead.
+    pub restart_on_token_expiry: bool,
+
+    /// Seed for the shuffle order.
+    ///
+    /// When set, shuffling a queue of the same length always yields the same
+    /// order, which helps to reproduce issues in tests. By default the order
+    /// is random.
+    pub shuffle_seed: Option<u64>,
+
     /// Whether other clients may take over an existing connection.
     ///
     /// By default this is `true`.
     pub interruptions: bool,
 
-    /// Script to execute when events occur
-    pub hook: Option<String>,
+    /// Whether to appear in the device list without accepting connections.
+    ///
+    /// The device still responds to discovery, but declines every
+    /// connection attempt. Unlike eavesdropping, the device is visible.
+    pub announce_only: bool,
+
+    /// Whether to keep plays from being reported to Deezer.
+    ///
+    /// Progress is still reported to the controller. This may affect
+    /// recommendations and the recently played history.
+    pub private_listening: bool,
+
+    /// Whether a stop from the controller clears the queue and releases the
+    /// output device.
+    ///
+    /// By default, stopping pauses playback, keeping the queue and the
+    /// output device. Either way, the controller stays connected.
+    pub full_stop: bool,
+
+    /// Minimum time between reporting plays of the same track to Deezer.
+    ///
+    /// Keeps repeating a track, or pausing and resuming it, from reporting
+    /// a play every time. `Duration::ZERO` reports every play.
+    pub min_report_interval: Duration,
+
+    /// Whether to reject queues from container types that are not known.
+    ///
+    /// By default, unknown containers are resolved on a best-effort basis
+    /// from their track list. Rejecting them reports an error to the
+    /// controller instead of playing a possibly incomplete queue.
+    pub reject_unknown_containers: bool,
+
+    /// Status to respond with to the skip received during the handshake.
+    ///
+    /// The protocol expects [`Status::Error`], as no queue is published yet.
+    /// Some controllers only start after [`Status::OK`].
+    pub handshake_skip_status: Status,
+
+    /// Scripts to execute when events occur
+    ///
+    /// Each hook is a program, optionally followed by whitespace-separated
+    /// arguments. Arguments may contain `{VARIABLE}` placeholders that are
+    /// substituted with event variables.
+    pub hooks: Vec<String>,
+
+    /// Maximum time a hook script may run.
+    ///
+    /// Hook scripts still running after this duration are killed.
+    pub hook_timeout: Duration,
+
+    /// Placeholder metadata passed to hook scripts when metadata is missing.
+    pub metadata_fallbacks: MetadataFallbacks,
+
+    /// Webhook to POST scrobbles of played tracks to, if any.
+    pub scrobble_url: Option<url::Url>,
+
+    /// Percentage of a track that must play before it is scrobbled.
+    pub scrobble_percent: u8,
+
+    /// Time after which a track is scrobbled, even if it played for less
+    /// than `scrobble_percent`.
+    pub scrobble_after: Duration,
+
+    /// Track IDs that should never be marked as unavailable.
+    ///
+    /// Tracks in this set are retried instead of skipped when they fail to
+    /// load. Useful for debugging specific problem tracks.
+    pub allow_tracks: BTreeSet<TrackId>,
+
+    /// Track IDs that should always be skipped.
+    ///
+    /// Tracks in this set are treated as unavailable without attempting to
+    /// load them. Takes precedence over `allow_tracks`.
+    pub deny_tracks: BTreeSet<TrackId>,
+
+    /// Whether to skip songs with explicit lyrics.
+    pub skip_explicit: bool,
+
+    /// Hosts that media may be downloaded from.
+    ///
+    /// Each entry also trusts its subdomains. Sources on other hosts are
+    /// skipped. Empty to trust all hosts.
+    pub trusted_hosts: BTreeSet<String>,
+
+    /// Additional headers to send with gateway and media requests.
+    ///
+    /// Override the default headers with the same name. Only sent to Deezer
+    /// hosts, and not to third-party hosts like podcast or webhook servers.
+    pub http_headers: BTreeMap<String, String>,
 
     /// The client ID used in API requests.
     ///
@@ -236,12 +569,36 @@ pub struct Config {
     /// The credentials used to authenticate with Deezer.
     pub credentials: Credentials,
 
+    /// ARLs to fail over to, in order.
+    ///
+    /// Tried when authenticating with an ARL and the active ARL is invalid
+    /// or expired.
+    pub fallback_arls: Vec<Arl>,
+
     /// Secret for computing the track decryption key.
     pub bf_secret: Option<Key>,
 
+    /// File to cache the `bf_secret` from the web player in.
+    ///
+    /// Updated whenever a secret is fetched, and read when fetching fails.
+    /// `None` to disable.
+    pub bf_secret_cache: Option<PathBuf>,
+
     /// Whether to eavesdrop on the network traffic.
     pub eavesdrop: bool,
 
+    /// File to write all protocol messages to.
+    ///
+    /// Written independently of the log level, and rotated when it grows
+    /// too large. `None` to disable.
+    pub protocol_log: Option<PathBuf>,
+
+    /// Whether to log the sources of podcasts and livestreams.
+    ///
+    /// Logs the hosts, codecs and bitrates that were offered, and which was
+    /// selected, without the full URLs.
+    pub log_sources: bool,
+
     /// The address to bind for outgoing connections.
     pub bind_address: IpAddr,
 }
@@ -31,7 +31,14 @@
 //! * Fast initialization through codec-specific handlers
 //! * Minimal buffer reallocations during format changes
 
-use std::{io, time::Duration};
+use std::{
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
 
 use rodio::{ChannelCount, SampleRate, source::SeekError};
 use symphonia::{
@@ -89,7 +96,7 @@ use crate::{
 ///
 /// let track = /* ... */;
 /// let file = /* AudioFile instance ... */;
-/// let mut decoder = Decoder::new(&track, file)?;
+/// let mut decoder = Decoder::new(&track, file, true)?;
 ///
 /// // Seek to 1 minute
 /// decoder.try_seek(std::time::Duration::from_secs(60))?;
@@ -130,6 +137,9 @@ pub struct Decoder {
 
     /// Maximum number of samples per frame for the current codec
     max_frame_length: Option<usize>,
+
+    /// Decoder position in microseconds, shared with the track
+    decoded: Arc<AtomicU64>,
 }
 
 /// Maximum number of consecutive corrupted packets to skip before giving up.
@@ -148,9 +158,14 @@ impl Decoder {
     /// * Bits per sample: From codec if available
     /// * Channels: From codec, falling back to content type default
     ///
+    /// With `gapless`, the encoder delay and padding are trimmed from the
+    /// start and end of the track, so consecutive tracks join seamlessly.
+    /// For MP3, these are read from the LAME or Xing header, if present.
+    ///
     /// # Arguments
     /// * `track` - Track metadata including codec information
     /// * `file` - Unified audio file interface handling encryption transparently
+    /// * `gapless` - Whether to trim encoder delay and padding
     ///
     /// # Errors
     ///
@@ -159,7 +174,7 @@ impl Decoder {
     /// * Codec initialization fails
     /// * Required track is not found
     /// * Stream parameters are invalid
-    pub fn new(track: &Track, file: AudioFile) -> Result<Self> {
+    pub fn new(track: &Track, file: AudioFile, gapless: bool) -> Result<Self> {
         // Twice the buffer length to allow for Symphonia's read-ahead behavior,
         // and 64 kB minimum that Symphonia asserts for its ring buffer.
         let buffer_len = usize::max(64 * 1024, BUFFER_LEN * 2);
@@ -212,7 +227,7 @@ impl Decoder {
                 &hint,
                 stream,
                 &FormatOptions {
-                    enable_gapless: true,
+                    enable_gapless: gapless,
                     ..Default::default()
                 },
                 &MetadataOptions::default(),
@@ -229,6 +244,14 @@ impl Decoder {
         // Update the codec parameters with the actual decoder parameters.
         // This may yield information not available before decoder initialization.
         let codec_params = decoder.codec_params();
+        if gapless && (codec_params.delay.is_some() || codec_params.padding.is_some()) {
+            debug!(
+                "{} {track}: trimming {} samples of encoder delay and {} samples of padding",
+                track.typ(),
+                codec_params.delay.unwrap_or_default(),
+                codec_params.padding.unwrap_or_default()
+            );
+        }
         let total_duration = Self::calc_total_duration(codec_params);
         let channels = Self::calc_channels(codec_params).unwrap_or(track.typ().default_channels());
         let sample_rate = Self::calc_sample_rate(codec_params);
@@ -237,6 +260,9 @@ impl Decoder {
             .map(|codec| codec.max_frame_length(sample_rate, channels));
         let total_samples = Self::calc_total_samples(codec_params, max_frame_length);
 
+        let decoded = track.decoded_handle();
+        decoded.store(0, Ordering::Relaxed);
+
         Ok(Self {
             demuxer,
             decoder,
@@ -250,9 +276,54 @@ impl Decoder {
             total_duration,
             total_samples,
             max_frame_length,
+
+            decoded,
         })
     }
 
+    /// Returns the audio codecs that this build can decode.
+    ///
+    /// Reports the codecs registered with Symphonia, which depend on the
+    /// features it was built with. Each codec is described by its short
+    /// and long name, like "flac (Free Lossless Audio Codec)".
+    #[must_use]
+    pub fn supported_codecs() -> Vec<String> {
+        use symphonia::core::codecs::{
+            CODEC_TYPE_AAC, CODEC_TYPE_ALAC, CODEC_TYPE_FLAC, CODEC_TYPE_MP1, CODEC_TYPE_MP2,
+            CODEC_TYPE_MP3, CODEC_TYPE_OPUS, CODEC_TYPE_PCM_F32LE, CODEC_TYPE_PCM_S16LE,
+            CODEC_TYPE_PCM_S24LE, CODEC_TYPE_PCM_S32LE, CODEC_TYPE_VORBIS,
+        };
+
+        let registry = symphonia::default::get_codecs();
+        [
+            CODEC_TYPE_AAC,
+            CODEC_TYPE_ALAC,
+            CODEC_TYPE_FLAC,
+            CODEC_TYPE_MP1,
+            CODEC_TYPE_MP2,
+            CODEC_TYPE_MP3,
+            CODEC_TYPE_OPUS,
+            CODEC_TYPE_PCM_F32LE,
+            CODEC_TYPE_PCM_S16LE,
+            CODEC_TYPE_PCM_S24LE,
+            CODEC_TYPE_PCM_S32LE,
+            CODEC_TYPE_VORBIS,
+        ]
+        .into_iter()
+        .filter_map(|codec| registry.get_codec(codec))
+        .map(|descriptor| format!("{} ({})", descriptor.short_name, descriptor.long_name))
+        .collect()
+    }
+
+    /// Returns the containers that this build can read.
+    ///
+    /// All containers that Deezer serves are built in, as [`Decoder::new`]
+    /// registers a specific reader for each of them.
+    #[must_use]
+    pub fn supported_containers() -> Vec<Codec> {
+        vec![Codec::ADTS, Codec::FLAC, Codec::MP3, Codec::MP4, Codec::WAV]
+    }
+
     /// Returns the track's `ReplayGain` value in dB, if available.
     ///
     /// While Deezer normally provides gain information through its API for proper
@@ -450,6 +521,36 @@ impl Decoder {
                         }
                     };
 
+                    // Some streams, like ADTS livestreams, change their sample rate or channel
+                    // count without a reset. Follow the decoded audio, so that the rate changes
+                    // at the span boundary instead of playing at the wrong pitch.
+                    let spec = decoded.spec();
+                    let channels = ChannelCount::try_from(spec.channels.count()).ok();
+                    if spec.rate != self.sample_rate
+                        || channels.is_some_and(|channels| channels != self.channels)
+                    {
+                        let message = format!(
+                            "stream changed to {} kHz, {} channels; was {} kHz, {} channels",
+                            spec.rate.to_f32_lossy() / 1000.0,
+                            spec.channels.count(),
+                            self.sample_rate.to_f32_lossy() / 1000.0,
+                            self.channels,
+                        );
+
+                        // The codec parameters may not be accurate before the first packet.
+                        if self.buffer.is_some() {
+                            warn!("{message}");
+                        } else {
+                            debug!("{message}");
+                        }
+
+                        self.sample_rate = spec.rate;
+                        if let Some(channels) = channels {
+                            self.channels = channels;
+                        }
+                        self.buffer = None;
+                    }
+
                     let buffer = match self.buffer.as_mut() {
                         Some(buffer) => buffer,
                         None => {
@@ -465,6 +566,12 @@ impl Decoder {
                     };
                     buffer.copy_interleaved_ref(decoded);
                     self.position = 0;
+
+                    // Track the end of this packet as the decoder position.
+                    if let Some(time_base) = self.decoder.codec_params().time_base {
+                        let end = time_base.calc_time(packet.ts().saturating_add(packet.dur()));
+                        self.set_decoded(end.into());
+                    }
                     break Ok(packet.dur());
                 }
 
@@ -489,6 +596,14 @@ impl Decoder {
         }
     }
 
+    /// Updates the decoder position shared with the track.
+    fn set_decoded(&self, position: Duration) {
+        self.decoded.store(
+            position.as_micros().try_into().unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+    }
+
     /// Converts a timestamp in time base units to the number of samples.
     ///
     /// Returns `None` if the decoder is not initialized or if the time base is not available.
@@ -594,6 +709,7 @@ impl rodio::Source for Decoder {
         for _ in 0..(samples_to_skip + active_channel) {
             self.next();
         }
+        self.set_decoded(target);
 
         Ok(())
     }
@@ -47,12 +47,15 @@
 //! * Efficient buffered reading via `BufRead` trait
 //! * Proper seeking support with block alignment
 //! * Automatic buffer management
+//! * Process-wide decryption statistics via [`statistics`]
 
 use std::{
     cell::OnceCell,
+    fmt,
     io::{self, BufRead, Read, Seek, SeekFrom},
     ops::Deref,
     str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
 };
 
 use blowfish::{Blowfish, cipher::BlockDecryptMut, cipher::KeyIvInit};
@@ -210,6 +213,28 @@ impl Deref for Key {
     }
 }
 
+/// Where the decryption key in use came from.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum KeySource {
+    /// Set in the secrets file or environment
+    #[default]
+    Configured,
+    /// Fetched from the web player
+    WebPlayer,
+    /// Read from the cache file, after fetching from the web player failed
+    Cached,
+}
+
+impl fmt::Display for KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Configured => write!(f, "configuration"),
+            Self::WebPlayer => write!(f, "web player"),
+            Self::Cached => write!(f, "cache"),
+        }
+    }
+}
+
 /// Fixed IV for CBC decryption.
 const CBC_BF_IV: &[u8; 8] = b"\x00\x01\x02\x03\x04\x05\x06\x07";
 
@@ -230,6 +255,48 @@ thread_local! {
     static BF_SECRET: OnceCell<Key> = const { OnceCell::new() };
 }
 
+/// Total number of bytes decrypted.
+static BYTES_DECRYPTED: AtomicU64 = AtomicU64::new(0);
+
+/// Total number of blocks decrypted.
+static BLOCKS_DECRYPTED: AtomicU64 = AtomicU64::new(0);
+
+/// Total number of blocks passed through without decryption.
+static BLOCKS_SKIPPED: AtomicU64 = AtomicU64::new(0);
+
+/// Decryption statistics since the process started.
+///
+/// Counts blocks as they are read from the encrypted stream. Blocks
+/// that are read again after seeking are counted again. With the
+/// `BF_CBC_STRIPE` cipher, about one in three blocks should be decrypted.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Statistics {
+    /// Number of bytes decrypted.
+    pub bytes_decrypted: u64,
+
+    /// Number of blocks decrypted.
+    pub blocks_decrypted: u64,
+
+    /// Number of blocks passed through without decryption.
+    ///
+    /// Includes unencrypted stripes and partial blocks at the end of
+    /// the stream, which are never encrypted.
+    pub blocks_skipped: u64,
+}
+
+/// Returns the decryption statistics since the process started.
+///
+/// Useful to observe the decryption cost, for example on constrained
+/// hardware.
+#[must_use]
+pub fn statistics() -> Statistics {
+    Statistics {
+        bytes_decrypted: BYTES_DECRYPTED.load(Ordering::Relaxed),
+        blocks_decrypted: BLOCKS_DECRYPTED.load(Ordering::Relaxed),
+        blocks_skipped: BLOCKS_SKIPPED.load(Ordering::Relaxed),
+    }
+}
+
 /// Sets the global decryption key.
 ///
 /// Must be called before any decryption operations.
@@ -455,6 +522,11 @@ where
                 cipher
                     .decrypt_padded_mut::<NoPadding>(&mut self.buffer[..CBC_BLOCK_SIZE])
                     .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+                BLOCKS_DECRYPTED.fetch_add(1, Ordering::Relaxed);
+                BYTES_DECRYPTED.fetch_add(CBC_BLOCK_SIZE as u64, Ordering::Relaxed);
+            } else {
+                BLOCKS_SKIPPED.fetch_add(1, Ordering::Relaxed);
             }
         }
 
@@ -560,3 +632,70 @@ where
         Ok(amt)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Arbitrary salt for testing.
+    const SALT: &str = "pleezer-test-key";
+
+    /// Track to derive the key for.
+    const TRACK_ID: i64 = 3_135_556;
+
+    /// First bytes of the first stripe after decryption.
+    const DECRYPTED_PREFIX: [u8; 16] = [
+        0x24, 0xfb, 0x80, 0xd4, 0x81, 0x76, 0x67, 0x7c, 0x21, 0x9e, 0x84, 0x84, 0x76, 0x63, 0x95,
+        0x6f,
+    ];
+
+    /// MD5 of the first stripe after decryption.
+    const DECRYPTED_MD5: &str = "0d58e53db556d485195df89f063ef584";
+
+    #[test]
+    fn decrypts_stripe_and_counts_blocks() {
+        // Three full blocks and a partial one, so that the first block is
+        // decrypted and the others are passed through.
+        let encrypted: Vec<u8> = (0..3 * CBC_BLOCK_SIZE + 100)
+            .map(|i| u8::try_from(i % 256).unwrap())
+            .collect();
+
+        let salt: Key = SALT.parse().unwrap();
+        let key =
+            Decrypt::<Cursor<Vec<u8>>>::key_for_track_id(TrackId::new(TRACK_ID).unwrap(), &salt);
+        let mut decryptor = Decrypt {
+            file: Cursor::new(encrypted.clone()),
+            file_size: None,
+            key,
+            buffer: [0; CBC_BLOCK_SIZE],
+            buffer_len: 0,
+            pos: 0,
+            block: None,
+        };
+
+        let before = statistics();
+        let mut decrypted = Vec::new();
+        decryptor.read_to_end(&mut decrypted).unwrap();
+        let after = statistics();
+
+        assert_eq!(decrypted.len(), encrypted.len());
+        assert_eq!(decrypted[..16], DECRYPTED_PREFIX);
+        assert_eq!(
+            format!("{:x}", Md5::digest(&decrypted[..CBC_BLOCK_SIZE])),
+            DECRYPTED_MD5
+        );
+        assert_eq!(
+            decrypted[CBC_BLOCK_SIZE..],
+            encrypted[CBC_BLOCK_SIZE..],
+            "unencrypted blocks should pass through"
+        );
+
+        // The counters are process-wide, so other tests decrypting in
+        // parallel may add to them.
+        assert!(after.blocks_decrypted - before.blocks_decrypted >= 1);
+        assert!(after.bytes_decrypted - before.bytes_decrypted >= CBC_BLOCK_SIZE as u64);
+        assert!(after.blocks_skipped - before.blocks_skipped >= 3);
+    }
+}
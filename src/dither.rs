@@ -70,6 +70,13 @@ use rodio::{
 
 use crate::{loudness::EqualLoudnessFilter, ringbuf::RingBuffer, volume::Volume};
 
+/// Sample rates in Hz that noise shaping is available for.
+///
+/// Profiles 3-7 are only available for 44.1 and 48 kHz.
+pub const NOISE_SHAPING_SAMPLE_RATES: [u32; 8] = [
+    8_000, 11_025, 22_050, 44_100, 48_000, 88_200, 96_000, 192_000,
+];
+
 /// Creates a new audio source with dithered volume control and optional noise shaping.
 ///
 /// This function integrates professional-grade audio processing:
@@ -137,11 +144,7 @@ where
 
     let sample_rate = input.sample_rate();
     if noise_shaping_profile > 0 {
-        if ![
-            8_000, 11_025, 22_050, 44_100, 48_000, 88_200, 96_000, 192_000,
-        ]
-        .contains(&sample_rate)
-        {
+        if !NOISE_SHAPING_SAMPLE_RATES.contains(&sample_rate) {
             warn!("noise shaping not available for {sample_rate} Hz");
         } else if noise_shaping_profile > 2 && ![44_100, 48_000].contains(&sample_rate) {
             warn!("limiting noise shaping profile to 2 (highest available for {sample_rate} Hz)");
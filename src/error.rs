@@ -81,6 +81,19 @@ impl Error {
     {
         self.error.downcast_ref::<E>()
     }
+
+    /// Returns whether the error is transient, so that retrying the
+    /// operation may succeed.
+    ///
+    /// Timeouts and unavailable services are transient. Other errors, like
+    /// invalid credentials, fail again when retried.
+    #[must_use]
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self.kind,
+            ErrorKind::DeadlineExceeded | ErrorKind::Unavailable
+        )
+    }
 }
 
 /// Standard result type for pleezer operations.
@@ -938,6 +951,7 @@ impl From<cpal::StreamError> for Error {
         use cpal::StreamError::*;
         match e {
             DeviceNotAvailable => Self::unavailable(e),
+            BufferUnderrun => Self::data_loss(e),
             BackendSpecific { err } => Self::unknown(err),
         }
     }
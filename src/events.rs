@@ -5,6 +5,8 @@
 //! * Monitor playback state changes
 //! * Track remote control connections
 //! * React to track changes
+//! * Monitor authentication
+//! * Surface non-fatal errors to the user
 //!
 //! # Example
 //!
@@ -21,6 +23,8 @@
 //! }
 //! ```
 
+use crate::error::{Error, ErrorKind};
+
 /// Events that can be emitted by the Deezer Connect player or remote.
 ///
 /// These events represent significant state changes in playback
@@ -28,23 +32,35 @@
 ///
 /// # Events
 ///
-/// Events fall into two categories:
+/// Events fall into four categories:
 ///
 /// Playback Events:
 /// * [`Play`](Self::Play) - Playback starts
 /// * [`Pause`](Self::Pause) - Playback pauses
 /// * [`TrackChanged`](Self::TrackChanged) - Current track changes
+/// * [`NextTrackPreloaded`](Self::NextTrackPreloaded) - Next track starts preloading
+/// * [`NextTrackBuffered`](Self::NextTrackBuffered) - Next track is fully buffered
+/// * [`PlaybackStarted`](Self::PlaybackStarted) - Queue from a new context is published
 ///
 /// Connection Events:
 /// * [`Connected`](Self::Connected) - Remote connects
 /// * [`Disconnected`](Self::Disconnected) - Remote disconnects
 ///
+/// Authentication Events:
+/// * [`LoggedIn`](Self::LoggedIn) - Client logs in
+/// * [`TokenRefreshed`](Self::TokenRefreshed) - User token is refreshed
+/// * [`TokenRefreshFailed`](Self::TokenRefreshFailed) - User token cannot be obtained
+///
+/// Error Events:
+/// * [`LicenseForbidden`](Self::LicenseForbidden) - A download was forbidden
+/// * [`Error`](Self::Error) - A non-fatal error occurred
+///
 /// # Example
 ///
 /// ```rust
 /// use pleezer::events::Event;
 ///
-/// // Events can be copied and compared
+/// // Events can be cloned and compared
 /// let event = Event::Play;
 /// assert_eq!(event, Event::Play);
 /// assert_ne!(event, Event::Pause);
@@ -56,7 +72,7 @@
 ///     _ => "Other event",
 /// };
 /// ```
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Event {
     /// Playback has started.
     ///
@@ -76,6 +92,25 @@ pub enum Event {
     /// manual selection, automatic progression, or remote control.
     TrackChanged,
 
+    /// Next track has started preloading.
+    ///
+    /// Emitted when the next track is queued up for gapless playback
+    /// while the current track is still playing.
+    NextTrackPreloaded,
+
+    /// Next track is fully buffered.
+    ///
+    /// Emitted when the download of the preloaded next track has
+    /// completed, so the transition to it will be gapless.
+    NextTrackBuffered,
+
+    /// Playback from a new context has started.
+    ///
+    /// Emitted when a controller publishes a queue from a different
+    /// playlist, album or other context than before, including the
+    /// first queue after connecting.
+    PlaybackStarted,
+
     /// Remote control has connected.
     ///
     /// Emitted when a Deezer client establishes a remote control
@@ -87,4 +122,57 @@ pub enum Event {
     /// Emitted when a connected Deezer client ends its remote
     /// control session with this player.
     Disconnected,
+
+    /// Client has logged in.
+    ///
+    /// Emitted when the client has authenticated with Deezer and
+    /// obtained its first user token.
+    LoggedIn,
+
+    /// User token has been refreshed.
+    ///
+    /// Emitted when an expiring user token is replaced by a fresh one.
+    TokenRefreshed,
+
+    /// User token could not be obtained.
+    ///
+    /// Emitted when logging in or refreshing the user token fails, for
+    /// example because the ARL is invalid or expired. The client restarts
+    /// or stops afterwards.
+    TokenRefreshFailed {
+        /// Human-readable description of the error.
+        message: String,
+    },
+
+    /// A download was forbidden.
+    ///
+    /// Emitted when a media source responds with 403 Forbidden, which
+    /// usually means that the license token is stale. The player waits
+    /// until a renewed token is set with
+    /// [`Player::set_license_token`](crate::player::Player::set_license_token)
+    /// or the renewal is cancelled, and then retries the download once.
+    LicenseForbidden,
+
+    /// A non-fatal error has occurred.
+    ///
+    /// Emitted when an operation fails without interrupting the
+    /// session, for example when a track is unavailable or a network
+    /// request times out. Errors are also logged as before.
+    Error {
+        /// Category of the error.
+        kind: ErrorKind,
+
+        /// Human-readable description of the error.
+        message: String,
+    },
+}
+
+impl From<&Error> for Event {
+    /// Creates an [`Error`](Self::Error) event from an error.
+    fn from(e: &Error) -> Self {
+        Self::Error {
+            kind: e.kind,
+            message: e.error.to_string(),
+        }
+    }
 }
@@ -53,10 +53,11 @@
 //! // Make authenticated requests
 //! let songs = gateway.list_to_queue(&track_list).await?;
 //! let recommendations = gateway.user_radio(user_id).await?;
+//! let similar = gateway.track_radio(track_id).await?;
 //! let user_data = gateway.refresh().await?;
 //! ```
 
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 
 use cookie_store::RawCookie;
 use futures_util::TryFutureExt;
@@ -87,10 +88,12 @@ use crate::{
                 livestream::{self, LivestreamData},
                 songs::{self, SongData},
             },
+            mix::{self, ArtistRadio, TrackRadio},
             user_radio::{self, UserRadio},
         },
     },
     tokens::UserToken,
+    track::TrackId,
 };
 
 /// Gateway client for Deezer API access.
@@ -110,8 +113,17 @@ pub struct Gateway {
     //       a token object
     user_data: Option<UserData>,
 
+    /// When the cached user data expires on the monotonic clock.
+    expires_at: Option<Instant>,
+
     /// Client identifier for API requests.
     client_id: usize,
+
+    /// Configured ARLs in order of preference, if authenticating with an ARL.
+    arls: Vec<Arl>,
+
+    /// Index of the active ARL in `arls`.
+    active_arl: usize,
 }
 
 impl Gateway {
@@ -156,6 +168,9 @@ impl Gateway {
     /// Type 3 represents the standard gateway request format.
     const GATEWAY_INPUT: usize = 3;
 
+    /// Lifetime of user data whose expiration is too far ahead to represent.
+    const USER_DATA_FALLBACK_LIFETIME: Duration = Duration::from_secs(3600);
+
     /// OAuth client ID for authentication.
     ///
     /// Application identifier used during OAuth authentication flow.
@@ -234,23 +249,27 @@ impl Gateway {
             error!("unable to insert language cookie: {e}");
         }
 
-        if let Credentials::Arl(ref arl) = config.credentials {
-            let arl_cookie = RawCookie::build((Self::ARL_COOKIE, arl.as_str()))
-                .domain(Self::COOKIE_DOMAIN)
-                .path("/")
-                .secure(true)
-                .http_only(true)
-                .build();
-            if let Err(e) = cookie_jar.insert_raw(&arl_cookie, &cookie_origin) {
-                return Err(crate::error::Error::invalid_argument(format!(
-                    "failed to insert ARL cookie: {e}"
-                )));
-            }
+        if let Credentials::Arl(ref arl) = config.credentials
+            && let Err(e) = cookie_jar.insert_raw(&Self::arl_cookie(arl), &cookie_origin)
+        {
+            return Err(crate::error::Error::invalid_argument(format!(
+                "failed to insert ARL cookie: {e}"
+            )));
         }
 
         Ok(cookie_jar)
     }
 
+    /// Creates an ARL authentication cookie.
+    fn arl_cookie(arl: &Arl) -> RawCookie<'_> {
+        RawCookie::build((Self::ARL_COOKIE, arl.as_str()))
+            .domain(Self::COOKIE_DOMAIN)
+            .path("/")
+            .secure(true)
+            .http_only(true)
+            .build()
+    }
+
     /// Creates a new gateway client instance.
     ///
     /// # Arguments
@@ -268,13 +287,72 @@ impl Gateway {
         let cookie_jar = Self::cookie_jar(config)?;
         let http_client = HttpClient::with_cookies(config, cookie_jar)?;
 
+        let arls = match config.credentials {
+            Credentials::Arl(ref arl) => std::iter::once(arl)
+                .chain(&config.fallback_arls)
+                .cloned()
+                .collect(),
+            Credentials::Login { .. } => Vec::new(),
+        };
+
         Ok(Self {
             client_id: config.client_id,
             http_client,
             user_data: None,
+            expires_at: None,
+            arls,
+            active_arl: 0,
         })
     }
 
+    /// Returns the active ARL, if authenticating with an ARL.
+    ///
+    /// This changes when the gateway fails over to the next configured ARL.
+    #[must_use]
+    pub fn arl(&self) -> Option<&Arl> {
+        self.arls.get(self.active_arl)
+    }
+
+    /// Switches to the next configured ARL, if any.
+    ///
+    /// Returns `true` if there was another ARL to try.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ARL cookie cannot be set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cookie store mutex is poisoned.
+    fn fail_over_arl(&mut self) -> Result<bool> {
+        let next = self.active_arl + 1;
+        let Some(arl) = self.arls.get(next) else {
+            return Ok(false);
+        };
+
+        if let Some(jar) = self.http_client.cookie_jar.as_ref() {
+            jar.lock()
+                .expect("cookie mutex was poisoned")
+                .insert_raw(&Self::arl_cookie(arl), &Self::cookie_origin())
+                .map_err(|e| {
+                    Error::invalid_argument(format!("failed to insert ARL cookie: {e}"))
+                })?;
+        }
+
+        warn!(
+            "arl {} of {} invalid or expired; using arl {} of {}",
+            self.active_arl + 1,
+            self.arls.len(),
+            next + 1,
+            self.arls.len()
+        );
+        self.active_arl = next;
+        self.user_data = None;
+        self.expires_at = None;
+
+        Ok(true)
+    }
+
     /// Returns the current cookie header value, if available.
     ///
     /// Used for authentication in requests to Deezer services.
@@ -300,53 +378,59 @@ impl Gateway {
     /// # Errors
     ///
     /// Returns an error if:
-    /// * ARL token is invalid or expired
+    /// * ARL token is invalid or expired, and there is no other ARL to fail over to
     /// * Remote control is disabled
     /// * Too many devices are registered
     /// * Network request fails
     pub async fn refresh(&mut self) -> Result<()> {
-        // Send an empty JSON map
-        match self
-            .request::<UserData>(Self::EMPTY_JSON_OBJECT, None)
-            .await
-        {
-            Ok(response) => {
-                if let Some(data) = response.first() {
-                    if data.gatekeeps.remote_control.is_some_and(|remote| !remote) {
-                        return Err(Error::permission_denied(
-                            "remote control is disabled for this account; upgrade your Deezer subscription",
-                        ));
-                    }
-                    if data.user.options.too_many_devices {
-                        return Err(Error::resource_exhausted(
-                            "too many devices; remove one or more in your account settings",
-                        ));
-                    }
-                    if data.user.options.ads_audio {
-                        return Err(Error::unimplemented(
-                            "ads are not implemented; upgrade your Deezer subscription",
-                        ));
+        loop {
+            // Send an empty JSON map
+            match self
+                .request::<UserData>(Self::EMPTY_JSON_OBJECT, None)
+                .await
+            {
+                Ok(response) => {
+                    if let Some(data) = response.first() {
+                        if data.gatekeeps.remote_control.is_some_and(|remote| !remote) {
+                            return Err(Error::permission_denied(
+                                "remote control is disabled for this account; upgrade your Deezer subscription",
+                            ));
+                        }
+                        if data.user.options.too_many_devices {
+                            return Err(Error::resource_exhausted(
+                                "too many devices; remove one or more in your account settings",
+                            ));
+                        }
+                        if data.user.options.ads_audio {
+                            return Err(Error::unimplemented(
+                                "ads are not implemented; upgrade your Deezer subscription",
+                            ));
+                        }
+
+                        self.set_user_data(data.clone());
+                    } else {
+                        return Err(Error::not_found("no user data received".to_string()));
                     }
 
-                    self.set_user_data(data.clone());
-                } else {
-                    return Err(Error::not_found("no user data received".to_string()));
+                    return Ok(());
                 }
 
-                Ok(())
-            }
+                Err(e) => {
+                    if e.kind == ErrorKind::InvalidArgument {
+                        // For an invalid or expired `arl`, the response has some
+                        // fields as integer `0` which are normally typed as string,
+                        // which causes JSON deserialization to fail.
+                        if self.fail_over_arl()? {
+                            continue;
+                        }
 
-            Err(e) => {
-                if e.kind == ErrorKind::InvalidArgument {
-                    // For an invalid or expired `arl`, the response has some
-                    // fields as integer `0` which are normally typed as string,
-                    // which causes JSON deserialization to fail.
-                    return Err(Error::permission_denied(
-                        "arl invalid or expired".to_string(),
-                    ));
-                }
+                        return Err(Error::permission_denied(
+                            "arl invalid or expired".to_string(),
+                        ));
+                    }
 
-                Err(e)
+                    return Err(e);
+                }
             }
         }
     }
@@ -434,25 +518,43 @@ impl Gateway {
     #[must_use]
     #[inline]
     pub fn is_expired(&self) -> bool {
-        self.expires_at() <= SystemTime::now()
+        self.expires_at() <= Instant::now()
     }
 
     /// Returns when the current session will expire.
     ///
-    /// Returns UNIX epoch if no session is active.
+    /// Uses the monotonic clock, so the result is robust to a skewed system
+    /// clock. Returns the current instant if no session is active.
     #[must_use]
     #[inline]
-    pub fn expires_at(&self) -> SystemTime {
-        if let Some(data) = &self.user_data {
-            return data.user.options.expiration_timestamp;
-        }
-
-        SystemTime::UNIX_EPOCH
+    pub fn expires_at(&self) -> Instant {
+        self.expires_at.unwrap_or_else(Instant::now)
     }
 
     /// Updates the cached user data.
-    #[inline]
+    ///
+    /// The session lifetime is calculated against the server time when
+    /// available, falling back to the system time. The expiration is then
+    /// measured on the monotonic clock from receipt, so that devices with a
+    /// wrong system clock neither refresh constantly nor let tokens expire.
     pub fn set_user_data(&mut self, data: UserData) {
+        let now = SystemTime::now();
+        let lifetime = data
+            .user
+            .options
+            .expiration_timestamp
+            .duration_since(data.server_timestamp.unwrap_or(now))
+            .unwrap_or_default();
+        trace!("user data valid for {}s", lifetime.as_secs());
+
+        let now = Instant::now();
+        self.expires_at = now.checked_add(lifetime).or_else(|| {
+            warn!(
+                "user data lifetime of {}s is out of range",
+                lifetime.as_secs()
+            );
+            now.checked_add(Self::USER_DATA_FALLBACK_LIFETIME)
+        });
         self.user_data = Some(data);
     }
 
@@ -612,6 +714,57 @@ impl Gateway {
         }
     }
 
+    /// Fetches radio tracks based on an artist.
+    ///
+    /// # Arguments
+    ///
+    /// * `artist_id` - ID of artist to get recommendations for
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// * Network request fails
+    /// * Response parsing fails
+    pub async fn artist_radio(&mut self, artist_id: u64) -> Result<Queue> {
+        let request = mix::ArtistRequest { artist_id };
+        let body = serde_json::to_string(&request)?;
+        let response = self.request::<ArtistRadio>(body, None).await?;
+        Ok(response
+            .all()
+            .clone()
+            .into_iter()
+            .map(|item| item.0)
+            .collect())
+    }
+
+    /// Fetches radio tracks based on a track.
+    ///
+    /// The track itself is not included.
+    ///
+    /// # Arguments
+    ///
+    /// * `track_id` - ID of track to get recommendations for
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// * Network request fails
+    /// * Response parsing fails
+    pub async fn track_radio(&mut self, track_id: TrackId) -> Result<Queue> {
+        let request = mix::TrackRequest {
+            track_id,
+            start_with_input_track: false,
+        };
+        let body = serde_json::to_string(&request)?;
+        let response = self.request::<TrackRadio>(body, None).await?;
+        Ok(response
+            .all()
+            .clone()
+            .into_iter()
+            .map(|item| item.0)
+            .collect())
+    }
+
     /// Retrieves an ARL token using an OAuth access token.
     ///
     /// # Arguments
@@ -680,9 +833,7 @@ impl Gateway {
     pub fn flush_user_token(&mut self) {
         // Force refreshing user data, but do not set `user_data` to `None` so
         // so we can continue using the `api_token` it contains.
-        if let Some(data) = self.user_data.as_mut() {
-            data.user.options.expiration_timestamp = SystemTime::UNIX_EPOCH;
-        }
+        self.expires_at = None;
     }
 
     /// Logs in with email and password to obtain an ARL token.
@@ -768,9 +919,13 @@ impl Gateway {
             &[("jo", "p"), ("rto", "c"), ("i", "p")],
         )?;
 
+        let account_id = self.user_token().await?.user_id.to_string();
+
+        // Fetching the user token may have failed over to another ARL.
+        let arl = self.arl().unwrap_or(arl);
         let auth = auth::Jwt {
             arl: arl.to_string(),
-            account_id: self.user_token().await?.user_id.to_string(),
+            account_id,
         };
 
         let request = self.http_client.json(query, serde_json::to_string(&auth)?);
@@ -7,6 +7,7 @@
 //! * Network interface binding for routing control
 //! * Configurable timeouts for connections and reads
 //! * Connection keepalive for performance
+//! * Custom headers from the configuration, sent to Deezer hosts only
 //!
 //! # Session Management
 //!
@@ -31,6 +32,12 @@
 //! * Default binding to IPv4 for Deezer compatibility
 //! * Useful for VPN/tunnel routing or multi-homed systems
 //!
+//! # Trusted Hosts
+//!
+//! Optionally restricts media downloads to an allowlist of hosts:
+//! * Entries match the host itself and all of its subdomains
+//! * An empty allowlist trusts all hosts
+//!
 //! # Timeouts
 //!
 //! Provides granular timeout control:
@@ -59,16 +66,20 @@
 //! // Cookies are automatically managed for session persistence
 //! ```
 
-use std::{num::NonZeroU32, sync::Arc, time::Duration};
+use std::{collections::BTreeSet, num::NonZeroU32, sync::Arc, time::Duration};
 
 use governor::{DefaultDirectRateLimiter, Quota};
 use http::header::CONTENT_TYPE;
 use reqwest::{
     self, Body, Method, Url,
-    header::{ACCEPT_LANGUAGE, HeaderValue},
+    header::{ACCEPT_LANGUAGE, HeaderName, HeaderValue},
+    redirect,
 };
 
-use crate::{config::Config, error::Result};
+use crate::{
+    config::Config,
+    error::{Error, Result},
+};
 
 /// HTTP client with session management and rate limiting.
 ///
@@ -83,6 +94,23 @@ pub struct Client {
     /// Direct access to underlying client without rate limiting.
     pub unlimited: reqwest::Client,
 
+    /// Client for Deezer hosts.
+    ///
+    /// Like `unlimited`, but also sends the custom headers from the
+    /// configuration, so that they do not leak to third-party hosts.
+    /// Redirects to other hosts are not followed for the same reason.
+    deezer: reqwest::Client,
+
+    /// Client for media downloads from hosts other than Deezer's.
+    ///
+    /// Like `unlimited`, but only follows redirects to trusted hosts.
+    media: reqwest::Client,
+
+    /// Client for media downloads from Deezer hosts.
+    ///
+    /// Like `deezer`, but only follows redirects to trusted hosts.
+    deezer_media: reqwest::Client,
+
     /// Rate limiter for API quota compliance.
     ///
     /// Implements Deezer's 50 calls per 5-second limit.
@@ -97,6 +125,11 @@ pub struct Client {
     ///
     /// Optional to support both authenticated and public endpoints.
     pub cookie_jar: Option<Arc<reqwest_cookie_store::CookieStoreMutex>>,
+
+    /// Hosts that media may be downloaded from.
+    ///
+    /// Stored in lowercase. Empty to trust all hosts.
+    trusted_hosts: BTreeSet<String>,
 }
 
 impl Client {
@@ -138,6 +171,12 @@ impl Client {
     /// Used by `json()` method to set Content-Type header to "application/json"
     const CONTENT_TYPE_JSON: HeaderValue = HeaderValue::from_static("application/json");
 
+    /// Domains that custom headers are sent to, including their subdomains.
+    const DEEZER_DOMAINS: [&'static str; 2] = ["deezer.com", "dzcdn.net"];
+
+    /// Maximum number of redirects to follow, like the default policy.
+    const MAX_REDIRECTS: usize = 10;
+
     /// Creates a new client with optional session management.
     ///
     /// # Arguments
@@ -157,7 +196,7 @@ impl Client {
     ///
     /// Returns error if:
     /// * HTTP client creation fails
-    /// * Header values are invalid
+    /// * Custom header names or values are invalid
     ///
     /// # Panics
     ///
@@ -172,21 +211,36 @@ impl Client {
             headers.insert(ACCEPT_LANGUAGE, lang);
         }
 
+        // Custom headers override the defaults, for Deezer hosts only.
+        let mut deezer_headers = headers.clone();
+        for (name, value) in &config.http_headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| Error::invalid_argument(format!("invalid header name {name}: {e}")))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| Error::invalid_argument(format!("invalid value for {name}: {e}")))?;
+            deezer_headers.insert(name, value);
+        }
+
         // Wrap any `cookie_jar` in an `Arc` for asynchronous use.
         let cookie_jar =
             cookie_jar.map(|jar| Arc::new(reqwest_cookie_store::CookieStoreMutex::new(jar)));
 
-        let mut http_client = reqwest::Client::builder()
-            .tcp_keepalive(Self::KEEPALIVE_TIMEOUT)
-            .connect_timeout(Self::CONNECT_TIMEOUT)
-            .read_timeout(Self::READ_TIMEOUT)
-            .default_headers(headers)
-            .user_agent(&config.user_agent)
-            .local_address(config.bind_address);
+        let builder = |headers, redirect_policy| {
+            let mut http_client = reqwest::Client::builder()
+                .tcp_keepalive(Self::KEEPALIVE_TIMEOUT)
+                .connect_timeout(Self::CONNECT_TIMEOUT)
+                .read_timeout(Self::READ_TIMEOUT)
+                .default_headers(headers)
+                .redirect(redirect_policy)
+                .user_agent(&config.user_agent)
+                .local_address(config.bind_address);
 
-        if let Some(ref jar) = cookie_jar {
-            http_client = http_client.cookie_provider(Arc::clone(jar));
-        }
+            if let Some(ref jar) = cookie_jar {
+                http_client = http_client.cookie_provider(Arc::clone(jar));
+            }
+
+            http_client
+        };
 
         // Rate limit own requests as to not DoS the Deezer infrastructure.
         let replenish_interval =
@@ -198,13 +252,135 @@ impl Client {
                     .expect("calls per interval is zero"),
             );
 
+        let trusted_hosts: BTreeSet<String> = config
+            .trusted_hosts
+            .iter()
+            .map(|host| host.trim_end_matches('.').to_lowercase())
+            .collect();
+
+        let unlimited = builder(
+            headers.clone(),
+            Self::redirect_policy(false, BTreeSet::new()),
+        )
+        .build()?;
+        let deezer = builder(
+            deezer_headers.clone(),
+            Self::redirect_policy(true, BTreeSet::new()),
+        )
+        .build()?;
+
+        // Clients share their connection pool when cloned, so only build
+        // separate media clients when their redirect policy differs.
+        let (media, deezer_media) = if trusted_hosts.is_empty() {
+            (unlimited.clone(), deezer.clone())
+        } else {
+            (
+                builder(headers, Self::redirect_policy(false, trusted_hosts.clone())).build()?,
+                builder(
+                    deezer_headers,
+                    Self::redirect_policy(true, trusted_hosts.clone()),
+                )
+                .build()?,
+            )
+        };
+
         Ok(Self {
-            unlimited: http_client.build()?,
+            unlimited,
+            deezer,
+            media,
+            deezer_media,
             rate_limiter: governor::RateLimiter::direct(quota),
             cookie_jar,
+            trusted_hosts,
+        })
+    }
+
+    /// Returns whether media may be downloaded from `host`.
+    ///
+    /// A host is trusted when it equals a trusted host or is a subdomain of
+    /// one. All hosts are trusted when no trusted hosts are configured.
+    #[must_use]
+    pub fn is_trusted_host(&self, host: &str) -> bool {
+        Self::is_trusted(host, &self.trusted_hosts)
+    }
+
+    /// Returns whether `host` is within any of the lowercase `trusted_hosts`,
+    /// or whether `trusted_hosts` is empty.
+    fn is_trusted(host: &str, trusted_hosts: &BTreeSet<String>) -> bool {
+        if trusted_hosts.is_empty() {
+            return true;
+        }
+
+        let host = host.trim_end_matches('.').to_lowercase();
+        trusted_hosts
+            .iter()
+            .any(|trusted| Self::is_within_domain(&host, trusted))
+    }
+
+    /// Returns whether `host` is a Deezer host, that custom headers are sent to.
+    #[must_use]
+    pub fn is_deezer_host(host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_lowercase();
+        Self::DEEZER_DOMAINS
+            .iter()
+            .any(|domain| Self::is_within_domain(&host, domain))
+    }
+
+    /// Returns a redirect policy that refuses hosts outside `trusted_hosts`.
+    ///
+    /// With `deezer_only`, redirects to hosts other than Deezer's are not
+    /// followed: the redirect response is returned instead, so that the
+    /// custom headers are not sent to the target. An empty `trusted_hosts`
+    /// trusts all hosts.
+    fn redirect_policy(deezer_only: bool, trusted_hosts: BTreeSet<String>) -> redirect::Policy {
+        redirect::Policy::custom(move |attempt| {
+            let host = attempt.url().host_str().unwrap_or_default().to_owned();
+            if attempt.previous().len() >= Self::MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if !Self::is_trusted(&host, &trusted_hosts) {
+                attempt.error(format!("redirect to untrusted host {host}"))
+            } else if deezer_only && !Self::is_deezer_host(&host) {
+                attempt.stop()
+            } else {
+                attempt.follow()
+            }
         })
     }
 
+    /// Returns whether the lowercase `host` equals `domain` or is a subdomain of it.
+    fn is_within_domain(host: &str, domain: &str) -> bool {
+        host == domain
+            || host
+                .strip_suffix(domain)
+                .is_some_and(|prefix| prefix.ends_with('.'))
+    }
+
+    /// Returns the client without rate limiting to request `url` with.
+    ///
+    /// Requests to Deezer hosts include the custom headers from the
+    /// configuration, while requests to other hosts do not.
+    #[must_use]
+    pub fn client_for(&self, url: &Url) -> &reqwest::Client {
+        if url.host_str().is_some_and(Self::is_deezer_host) {
+            &self.deezer
+        } else {
+            &self.unlimited
+        }
+    }
+
+    /// Returns the client without rate limiting to download media from `url` with.
+    ///
+    /// Like [`client_for`](Self::client_for), but redirects to hosts that
+    /// are not trusted are refused.
+    #[must_use]
+    pub fn media_client_for(&self, url: &Url) -> &reqwest::Client {
+        if url.host_str().is_some_and(Self::is_deezer_host) {
+            &self.deezer_media
+        } else {
+            &self.media
+        }
+    }
+
     /// Creates a new client with session management.
     ///
     /// Convenience method for authenticated endpoints that require:
@@ -359,9 +535,34 @@ impl Client {
         // No need to await with jitter because the level of concurrency is low.
         // TODO : use different rate limiter for each host.
         self.rate_limiter.until_ready().await;
-        match self.unlimited.execute(request).await {
+        match self.client_for(request.url()).execute(request).await {
             Ok(response) => response.error_for_status().map_err(Into::into),
             Err(e) => Err(e.into()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_headers_are_for_deezer_hosts_only() {
+        assert!(Client::is_deezer_host("www.deezer.com"));
+        assert!(Client::is_deezer_host("e-cdns-proxy-1.dzcdn.net."));
+        assert!(Client::is_deezer_host("Deezer.com"));
+        assert!(!Client::is_deezer_host("notdeezer.com"));
+        assert!(!Client::is_deezer_host("deezer.com.example.org"));
+        assert!(!Client::is_deezer_host("podcasts.example.org"));
+    }
+
+    #[test]
+    fn trusted_hosts_include_subdomains() {
+        let trusted = BTreeSet::from(["dzcdn.net".to_string()]);
+        assert!(Client::is_trusted("dzcdn.net", &trusted));
+        assert!(Client::is_trusted("E-CDNS-PROXY-1.dzcdn.net.", &trusted));
+        assert!(!Client::is_trusted("evil-dzcdn.net", &trusted));
+        assert!(!Client::is_trusted("example.org", &trusted));
+        assert!(Client::is_trusted("example.org", &BTreeSet::new()));
+    }
+}
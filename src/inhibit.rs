@@ -0,0 +1,104 @@
+//! Sleep inhibition while playing on Linux.
+//!
+//! Takes an inhibitor lock from
+//! [systemd-logind](https://www.freedesktop.org/wiki/Software/systemd/inhibit/)
+//! on the D-Bus system bus while playback is active, so the machine does not
+//! automatically suspend when idle in the middle of a playlist. The lock is
+//! released when playback pauses or stops.
+//!
+//! Only idling is inhibited: suspending explicitly, for example by closing
+//! the lid or from a menu, still works.
+//!
+//! Only available with the `inhibit` feature.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use pleezer::{inhibit, remote::Client};
+//!
+//! async fn example(client: &Client) {
+//!     let state = client.subscribe_state();
+//!     tokio::spawn(inhibit::serve(state));
+//! }
+//! ```
+
+use tokio::sync::watch;
+use zbus::zvariant::OwnedFd;
+
+use crate::{
+    error::{Error, Result},
+    remote::PlaybackState,
+};
+
+/// Operations to inhibit.
+const WHAT: &str = "idle";
+
+/// Application that takes the lock, as shown by `systemd-inhibit --list`.
+const WHO: &str = "pleezer";
+
+/// Reason for taking the lock, as shown by `systemd-inhibit --list`.
+const WHY: &str = "Playing music";
+
+/// Inhibitor mode: block the operations for as long as the lock is held.
+const MODE: &str = "block";
+
+/// Login manager of systemd-logind.
+#[zbus::proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Manager {
+    /// Takes an inhibitor lock, which is held until the descriptor is closed.
+    fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> zbus::Result<OwnedFd>;
+}
+
+/// Inhibits sleep while playing until the client is dropped.
+///
+/// Takes an inhibitor lock when `state` starts playing, and releases it
+/// when playback pauses or stops. Failing to take the lock is logged and
+/// retried when playback starts again.
+///
+/// # Errors
+///
+/// Returns error if:
+/// * The system bus is not available
+/// * The login manager cannot be reached
+pub async fn serve(mut state: watch::Receiver<PlaybackState>) -> Result<()> {
+    let connection = zbus::Connection::system()
+        .await
+        .map_err(|e| Error::unavailable(format!("failed to connect to system bus: {e}")))?;
+    let manager = ManagerProxy::new(&connection)
+        .await
+        .map_err(|e| Error::unavailable(format!("failed to reach login manager: {e}")))?;
+    info!("inhibiting sleep while playing");
+
+    let mut was_playing = false;
+    let mut lock = None;
+
+    // Returns when the sending half is dropped with the client.
+    loop {
+        let playing = state.borrow_and_update().playing;
+        if playing != was_playing {
+            was_playing = playing;
+            if playing {
+                match manager.inhibit(WHAT, WHO, WHY, MODE).await {
+                    Ok(fd) => {
+                        debug!("sleep inhibited");
+                        lock = Some(fd);
+                    }
+                    Err(e) => warn!("failed to inhibit sleep: {e}"),
+                }
+            } else if lock.take().is_some() {
+                // Closing the descriptor releases the lock.
+                debug!("sleep no longer inhibited");
+            }
+        }
+
+        if state.changed().await.is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
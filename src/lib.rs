@@ -28,10 +28,12 @@
 //!   - [`decrypt`]: Handles encrypted content
 //!   - [`decoder`]: Audio format decoding
 //!   - [`loudness`]: Equal-loudness compensation (ISO 226:2013)
+//!   - [`metrics`]: Performance counters for the audio pipeline
 //!   - [`dither`]: High-quality dithering and noise shaping
 //!   - [`volume`]: Volume control with dithering integration
 //!   - [`player`]: Controls audio playback and queues
 //!   - [`ringbuf`]: Ring buffer for audio processing
+//!   - [`throttle`]: Download bandwidth limiting
 //!   - [`track`]: Manages track metadata and downloads
 //!
 //! * **Authentication**
@@ -48,6 +50,9 @@
 //!
 //! * **System Integration**
 //!   - [`signal`]: Signal handling (SIGTERM, SIGHUP)
+//!   - `mpris`: MPRIS interface for desktop integration (with the `mpris` feature)
+//!   - `inhibit`: Sleep inhibition while playing (with the `inhibit` feature)
+//!   - [`scrobble`]: Scrobbling of played tracks to a webhook
 //!   - [`mod@error`]: Error types and handling
 //!   - [`util`]: General helper functions
 //!
@@ -115,13 +120,20 @@ pub mod error;
 pub mod events;
 pub mod gateway;
 pub mod http;
+#[cfg(feature = "inhibit")]
+pub mod inhibit;
 pub mod loudness;
+pub mod metrics;
+#[cfg(feature = "mpris")]
+pub mod mpris;
 pub mod player;
 pub mod protocol;
 pub mod proxy;
 pub mod remote;
 pub mod ringbuf;
+pub mod scrobble;
 pub mod signal;
+pub mod throttle;
 pub mod tokens;
 pub mod track;
 pub mod util;
@@ -44,7 +44,12 @@
 //! * Maximum backoff of 10 seconds
 //! * Random jitter between attempts
 
-use std::{env, fs, path::Path, process, time::Duration};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
 
 use clap::{Parser, ValueHint, command};
 use exponential_backoff::Backoff;
@@ -54,13 +59,18 @@ use uuid::Uuid;
 
 use pleezer::{
     arl::Arl,
-    config::{Config, Credentials},
+    config::{Config, Credentials, MetadataFallbacks},
+    decoder::Decoder,
     decrypt,
     error::{Error, ErrorKind, Result},
     player::Player,
-    protocol::connect::{DeviceType, Percentage},
+    protocol::{
+        Codec,
+        connect::{AudioQuality, DeviceType, Percentage, Status},
+    },
     remote,
     signal::{self, ShutdownSignal},
+    track::{TrackId, TrackType},
 };
 
 /// Build profile indicator for logging.
@@ -99,6 +109,15 @@ const MIN_BACKOFF: Duration = Duration::from_millis(100);
 /// exponential increases.
 const MAX_BACKOFF: Duration = Duration::from_secs(10);
 
+/// Volume step of the stdin controls in percentage points.
+const STDIN_VOLUME_STEP: i8 = 5;
+
+/// Output level of the stdin controls while ducked, as a percentage of the volume.
+const STDIN_DUCK_PERCENT: u8 = 20;
+
+/// Duration of the ducking fade of the stdin controls.
+const STDIN_DUCK_FADE: Duration = Duration::from_millis(500);
+
 /// Command line arguments as parsed by `clap`.
 ///
 /// Provides configuration options for:
@@ -145,12 +164,62 @@ struct Args {
     #[arg(short, long, default_value = None, env = "PLEEZER_DEVICE")]
     device: Option<String>,
 
+    /// List all supported output formats of a device and exit
+    ///
+    /// Format: [<host>][|<device>]
+    /// Use an empty string for the system default output device.
+    #[arg(long, value_name = "DEVICE")]
+    list_formats: Option<String>,
+
+    /// List the audio codecs and containers supported by this build and exit
+    #[arg(long, default_value_t = false)]
+    list_codecs: bool,
+
+    /// Time in seconds to wait for the output device to become available
+    ///
+    /// Useful on headless systems where the audio device may appear late
+    /// at boot, like USB DACs. By default, fails right away.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 0,
+        env = "PLEEZER_DEVICE_TIMEOUT"
+    )]
+    device_timeout: u64,
+
     /// Enable volume normalization
     ///
     /// Normalizes volume across tracks to provide consistent listening levels.
     #[arg(long, default_value_t = false, env = "PLEEZER_NORMALIZE_VOLUME")]
     normalize_volume: bool,
 
+    /// Only normalize volume of these content types
+    ///
+    /// Comma-separated list of: song, episode, livestream.
+    /// If not specified, all content types are normalized.
+    #[arg(
+        long,
+        value_name = "TYPES",
+        value_delimiter = ',',
+        requires = "normalize_volume",
+        env = "PLEEZER_NORMALIZE_TYPES"
+    )]
+    normalize_types: Vec<TrackType>,
+
+    /// Audio quality per content type, instead of the account setting
+    ///
+    /// Comma-separated list of TYPE=QUALITY pairs, where TYPE is one of:
+    /// song, episode, livestream; and QUALITY is one of: low, standard,
+    /// high, lossless. For example: song=lossless,episode=standard
+    #[arg(
+        long,
+        value_name = "TYPE=QUALITY",
+        value_delimiter = ',',
+        value_parser = parse_type_quality,
+        env = "PLEEZER_QUALITY"
+    )]
+    quality: Vec<(TrackType, AudioQuality)>,
+
     /// Enable loudness compensation (ISO 226:2013)
     ///
     /// Applies frequency-dependent gain to match human hearing sensitivity.
@@ -158,6 +227,83 @@ struct Args {
     #[arg(long, default_value_t = false, env = "PLEEZER_LOUDNESS")]
     loudness: bool,
 
+    /// Reference level for loudness compensation in LUFS
+    ///
+    /// Compensation is relative to this level instead of the volume
+    /// normalization target.
+    #[arg(
+        long,
+        value_name = "LUFS",
+        allow_negative_numbers = true,
+        value_parser = clap::value_parser!(i8).range(-60..=0),
+        env = "PLEEZER_LOUDNESS_TARGET"
+    )]
+    loudness_target: Option<i8>,
+
+    /// Fade through silence over this many milliseconds when skipping
+    ///
+    /// Masks the abrupt change of a manual skip: the current track fades out
+    /// to silence, then the next track fades in. Tracks that end naturally
+    /// stay gapless. 0 to disable.
+    #[arg(
+        long,
+        value_name = "MILLISECONDS",
+        value_parser = clap::value_parser!(u64).range(0..=500),
+        default_value_t = 0,
+        env = "PLEEZER_SKIP_FADE"
+    )]
+    skip_fade: u64,
+
+    /// Fade in each track over this many milliseconds
+    ///
+    /// For a gentler start of each track. 0 to disable.
+    #[arg(
+        long,
+        value_name = "MILLISECONDS",
+        value_parser = clap::value_parser!(u64).range(0..=10_000),
+        default_value_t = 0,
+        env = "PLEEZER_FADE_IN"
+    )]
+    fade_in: u64,
+
+    /// Preferred codec for livestreams that offer both at the same bitrate
+    ///
+    /// Values: aac, mp3
+    #[arg(
+        long,
+        value_name = "CODEC",
+        default_value_t = Codec::ADTS,
+        env = "PLEEZER_LIVESTREAM_CODEC"
+    )]
+    livestream_codec: Codec,
+
+    /// Only trim encoder delay and padding of these codecs
+    ///
+    /// Comma-separated list of: aac, flac, mp3, mp4, wav.
+    /// If not specified, all codecs are trimmed for gapless playback.
+    #[arg(
+        long,
+        value_name = "CODECS",
+        value_delimiter = ',',
+        env = "PLEEZER_GAPLESS_CODECS"
+    )]
+    gapless_codecs: Vec<Codec>,
+
+    /// Maximum bitrate of livestreams in kbps
+    ///
+    /// Caps radio bitrates independently of the audio quality for songs,
+    /// to save data. Livestreams without a source within the cap fail.
+    #[arg(long, value_name = "KBPS", env = "PLEEZER_MAX_LIVESTREAM_KBPS")]
+    max_livestream_kbps: Option<usize>,
+
+    /// Directory with ReplayGain sidecar files for volume normalization
+    ///
+    /// For tracks without gain information, like user uploads. Each file is
+    /// named after the track ID with a `.txt` extension, like `-123.txt`,
+    /// and contains the track gain in dB, like `-6.5 dB`.
+    #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath, env = "PLEEZER_REPLAY_GAIN_DIR")]
+    replay_gain_dir: Option<PathBuf>,
+
     /// Set initial volume level (0-100)
     ///
     /// Applied when no volume is reported by Deezer client or when reported as maximum.
@@ -170,6 +316,19 @@ struct Args {
     )]
     initial_volume: Option<u8>,
 
+    /// Set minimum volume level for any non-zero volume (0-100)
+    ///
+    /// Non-zero volumes are rescaled between this level and full volume,
+    /// for amplifiers with an audible noise floor at low digital volumes.
+    /// Zero still mutes.
+    #[arg(
+        long,
+        value_name = "PERCENTAGE",
+        value_parser = clap::value_parser!(u8).range(0..=100),
+        env = "PLEEZER_MIN_VOLUME"
+    )]
+    min_volume: Option<u8>,
+
     /// Set dither bit depth based on DAC linearity (ENOB)
     ///
     /// Set to effective number of bits from DAC measurements, or 0 to disable dithering.
@@ -182,6 +341,18 @@ struct Args {
     )]
     dither_bits: Option<f32>,
 
+    /// Skip dithering above this bit depth
+    ///
+    /// Keeps full precision when the effective dither bit depth is higher,
+    /// for example on 24- or 32-bit outputs. Default: always dither.
+    #[arg(
+        long,
+        value_name = "BITS",
+        value_parser = clap::value_parser!(f32),
+        env = "PLEEZER_DITHER_THRESHOLD"
+    )]
+    dither_threshold: Option<f32>,
+
     /// Set noise shaping level
     ///
     /// Level 0 (default) offers maximum compatibility and safety.
@@ -204,6 +375,50 @@ struct Args {
     )]
     noise_shaping: u8,
 
+    /// Reopen the output device when the sample rate of a track differs
+    ///
+    /// By default, tracks are resampled to the sample rate of the output
+    /// device. Preloaded tracks are always resampled to stay gapless.
+    #[arg(long, default_value_t = false, env = "PLEEZER_REOPEN_ON_RATE_CHANGE")]
+    reopen_on_rate_change: bool,
+
+    /// Count and log samples that clip after volume and dithering
+    ///
+    /// Helps to tune normalization and noise shaping to avoid clipping.
+    #[arg(long, default_value_t = false, env = "PLEEZER_CLIP_DETECTION")]
+    clip_detection: bool,
+
+    /// Release the output device after being paused for this many seconds
+    ///
+    /// Lets other applications use the device while staying connected.
+    /// The device is opened again when playback resumes. 0 to disable.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 0,
+        env = "PLEEZER_IDLE_TIMEOUT"
+    )]
+    idle_timeout: u64,
+
+    /// Seconds to wait for a queue update when skipping beyond the queue
+    ///
+    /// Afterwards, playback continues from the last track of the queue.
+    /// 0 to wait indefinitely.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 10,
+        env = "PLEEZER_POSITION_TIMEOUT"
+    )]
+    position_timeout: u64,
+
+    /// Resample sample rates that noise shaping is not available for
+    ///
+    /// Resamples to the nearest of 44.1 or 48 kHz. By default, such tracks
+    /// play at their own rate without noise shaping.
+    #[arg(long, default_value_t = false, env = "PLEEZER_RESAMPLE_UNSUPPORTED")]
+    resample_unsupported: bool,
+
     /// Maximum RAM (in MB) to use for storing audio files in memory
     ///
     /// If not specified or if a track exceeds this limit, temporary files will be used.
@@ -215,12 +430,245 @@ struct Args {
     )]
     max_ram: Option<u64>,
 
+    /// Largest track (in MB) to store in RAM; larger ones use temporary files
+    ///
+    /// Works with or without --max-ram. When both are set, a track must fit
+    /// within both to be stored in RAM.
+    #[arg(
+        long,
+        value_name = "MEGABYTES",
+        value_parser = clap::value_parser!(u64).range(1..=1024*1024),
+        env = "PLEEZER_RAM_THRESHOLD"
+    )]
+    ram_threshold: Option<u64>,
+
+    /// Number of tracks to preload ahead of the current track (1-5)
+    ///
+    /// Preloading more tracks smooths transitions between very short tracks
+    /// on fast connections. Counts towards `--max-ram`.
+    #[arg(
+        long,
+        value_name = "TRACKS",
+        value_parser = clap::value_parser!(u8).range(1..=5),
+        default_value_t = 1,
+        env = "PLEEZER_PRELOAD_DEPTH"
+    )]
+    preload_depth: u8,
+
+    /// Maximum number of tracks queued for output, including the current one
+    ///
+    /// Bounds memory use with preloading. Set to 0 for no limit other than
+    /// --preload-depth.
+    #[arg(
+        long,
+        value_name = "TRACKS",
+        value_parser = clap::value_parser!(u8).range(0..=6),
+        default_value_t = 0,
+        env = "PLEEZER_MAX_QUEUED_SOURCES"
+    )]
+    max_queued_sources: u8,
+
+    /// Real-time priority of the audio output thread, or 0 to keep the default
+    ///
+    /// Reduces underruns on loaded systems. Requires privileges for real-time
+    /// scheduling, like CAP_SYS_NICE or an rtprio limit; otherwise the thread
+    /// is made less nice where permitted.
+    #[arg(
+        long,
+        value_name = "PRIORITY",
+        value_parser = clap::value_parser!(u8).range(0..=99),
+        default_value_t = 0,
+        env = "PLEEZER_AUDIO_PRIORITY"
+    )]
+    audio_priority: u8,
+
+    /// Number of times to reconnect an interrupted livestream
+    ///
+    /// Set to 0 to skip to the next track right away.
+    #[arg(
+        long,
+        value_name = "COUNT",
+        default_value_t = 3,
+        env = "PLEEZER_LIVESTREAM_RETRIES"
+    )]
+    livestream_retries: u32,
+
+    /// Number of times to retry a download that responds with 0 bytes
+    ///
+    /// Some CDN edges transiently respond with an empty body.
+    #[arg(
+        long,
+        value_name = "COUNT",
+        value_parser = clap::value_parser!(u8).range(0..=10),
+        default_value_t = 2,
+        env = "PLEEZER_ZERO_BYTE_RETRIES"
+    )]
+    zero_byte_retries: u8,
+
+    /// Number of times a track may fail to load before it is skipped
+    ///
+    /// Failures are counted per track for the session. Increase to retry
+    /// tracks that fail to load on a slow or unreliable connection.
+    #[arg(
+        long,
+        value_name = "COUNT",
+        value_parser = clap::value_parser!(u8).range(1..=10),
+        default_value_t = 1,
+        env = "PLEEZER_LOAD_FAILURES"
+    )]
+    load_failures: u8,
+
+    /// Maximum download rate in kbps, or 0 for no limit
+    ///
+    /// Prevents downloads from saturating a shared connection. Downloads
+    /// are never limited below twice the bitrate of the track.
+    #[arg(
+        long,
+        value_name = "KBPS",
+        default_value_t = 0,
+        env = "PLEEZER_MAX_DOWNLOAD_KBPS"
+    )]
+    max_download_kbps: usize,
+
+    /// Delay in seconds before reconnecting an interrupted livestream
+    ///
+    /// Doubles with every consecutive attempt.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        value_parser = clap::value_parser!(u64).range(1..=60),
+        default_value_t = 1,
+        env = "PLEEZER_LIVESTREAM_RETRY_DELAY"
+    )]
+    livestream_retry_delay: u64,
+
+    /// Resume livestreams from the live edge after pausing
+    ///
+    /// By default, livestreams resume where they were paused, lagging
+    /// behind real time.
+    #[arg(long, default_value_t = false, env = "PLEEZER_LIVESTREAM_LIVE_EDGE")]
+    livestream_live_edge: bool,
+
+    /// Verify the integrity of downloaded tracks
+    ///
+    /// Tracks that are downloaded with fewer bytes than expected are
+    /// downloaded again.
+    #[arg(long, default_value_t = false, env = "PLEEZER_VERIFY_DOWNLOADS")]
+    verify_downloads: bool,
+
+    /// Check that the output device is still current when resuming
+    ///
+    /// Reopens the device when it changed or disappeared while paused, for
+    /// example after a suspend or when a Bluetooth speaker reconnects.
+    #[arg(long, default_value_t = false, env = "PLEEZER_VERIFY_DEVICE")]
+    verify_device: bool,
+
+    /// Override the Deezer Connect protocol version
+    ///
+    /// By default this is derived from the application version.
+    /// Only useful for compatibility testing.
+    #[arg(long, value_name = "VERSION", env = "PLEEZER_PROTOCOL_VERSION")]
+    protocol_version: Option<String>,
+
+    /// Time in seconds to wait before connecting at startup
+    ///
+    /// Useful at boot when the network may not be ready yet.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 0,
+        env = "PLEEZER_STARTUP_DELAY"
+    )]
+    startup_delay: u64,
+
+    /// Timeout in seconds of each network request while starting up
+    ///
+    /// Applies to fetching the decryption key, logging in and fetching
+    /// the user token, which may take longer on a cold network.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        value_parser = clap::value_parser!(u64).range(1..=120),
+        default_value_t = 10,
+        env = "PLEEZER_STARTUP_TIMEOUT"
+    )]
+    startup_timeout: u64,
+
+    /// Number of times to retry a network request while starting up
+    ///
+    /// Only timeouts and unavailable services are retried.
+    #[arg(
+        long,
+        value_name = "COUNT",
+        default_value_t = 3,
+        env = "PLEEZER_STARTUP_RETRIES"
+    )]
+    startup_retries: u32,
+
+    /// Restart the client when the user token expires
+    ///
+    /// By default the user token is refreshed in place, keeping the session
+    /// and playback going.
+    #[arg(long, default_value_t = false, env = "PLEEZER_RESTART_ON_TOKEN_EXPIRY")]
+    restart_on_token_expiry: bool,
+
     /// Prevent other clients from taking over the connection
     ///
     /// By default, other clients can interrupt and take control of playback.
     #[arg(long, default_value_t = false, env = "PLEEZER_NO_INTERRUPTIONS")]
     no_interruptions: bool,
 
+    /// Appear as a device, but never accept connections
+    ///
+    /// The device is listed in the Deezer app, but declines to be
+    /// connected to. Useful as a placeholder while setting up devices.
+    #[arg(long, default_value_t = false, env = "PLEEZER_ANNOUNCE_ONLY")]
+    announce_only: bool,
+
+    /// Respond OK to the skip received during the handshake
+    ///
+    /// By default, pleezer responds with an error as the protocol expects.
+    /// Enable this for controllers that do not start playback otherwise.
+    #[arg(long, default_value_t = false, env = "PLEEZER_HANDSHAKE_SKIP_OK")]
+    handshake_skip_ok: bool,
+
+    /// Do not report plays to Deezer
+    ///
+    /// The controller still shows what is playing, but plays are not
+    /// reported. This may affect recommendations and "recently played".
+    #[arg(long, default_value_t = false, env = "PLEEZER_PRIVATE_LISTENING")]
+    private_listening: bool,
+
+    /// Clear the queue and release the output device when the controller stops
+    ///
+    /// By default, stopping pauses playback like other receivers that keep
+    /// the queue. The controller stays connected either way.
+    #[arg(long, default_value_t = false, env = "PLEEZER_FULL_STOP")]
+    full_stop: bool,
+
+    /// Minimum seconds between reporting plays of the same track to Deezer
+    ///
+    /// Keeps repeat-one and resuming from reporting a play every time.
+    /// Set to 0 to report every play.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 0,
+        env = "PLEEZER_MIN_REPORT_INTERVAL"
+    )]
+    min_report_interval: u64,
+
+    /// Reject queues from unknown container types
+    ///
+    /// By default, queues from container types that pleezer does not know
+    /// are resolved from their track list on a best-effort basis.
+    #[arg(
+        long,
+        default_value_t = false,
+        env = "PLEEZER_REJECT_UNKNOWN_CONTAINERS"
+    )]
+    reject_unknown_containers: bool,
+
     /// Address to bind outgoing connections to
     ///
     /// Defaults to "0.0.0.0" (IPv4 any address) since Deezer services are IPv4-only
@@ -231,8 +679,152 @@ struct Args {
     bind: String,
 
     /// Script to execute when events occur
-    #[arg(long, value_hint = ValueHint::ExecutablePath, env = "PLEEZER_HOOK")]
-    hook: Option<String>,
+    ///
+    /// Can be specified multiple times to run several hooks. Arguments may
+    /// follow the script, separated by whitespace. Arguments can contain
+    /// `{VARIABLE}` placeholders that are replaced by event variables,
+    /// for example: --hook "notify.sh {EVENT} {TITLE}"
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        value_hint = ValueHint::CommandWithArguments,
+        env = "PLEEZER_HOOK"
+    )]
+    hook: Vec<String>,
+
+    /// Maximum time in seconds a hook script may run before it is killed
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        default_value_t = 30,
+        env = "PLEEZER_HOOK_TIMEOUT"
+    )]
+    hook_timeout: u64,
+
+    /// Webhook to POST played tracks to, for scrobbling
+    ///
+    /// Tracks are posted as JSON once they played long enough. Scrobbles
+    /// that cannot be delivered are retried later.
+    #[arg(
+        long,
+        value_name = "URL",
+        value_hint = ValueHint::Url,
+        env = "PLEEZER_SCROBBLE_URL"
+    )]
+    scrobble_url: Option<url::Url>,
+
+    /// Percentage of a track that must play before it is scrobbled
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        value_parser = clap::value_parser!(u8).range(1..=100),
+        default_value_t = 50,
+        env = "PLEEZER_SCROBBLE_PERCENT"
+    )]
+    scrobble_percent: u8,
+
+    /// Seconds after which a track is scrobbled, even before --scrobble-percent
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        default_value_t = 240,
+        env = "PLEEZER_SCROBBLE_AFTER"
+    )]
+    scrobble_after: u64,
+
+    /// Title to pass to hook scripts when a track has none
+    #[arg(long, value_name = "TITLE", env = "PLEEZER_FALLBACK_TITLE")]
+    fallback_title: Option<String>,
+
+    /// Artist to pass to hook scripts when a track has none
+    #[arg(long, value_name = "ARTIST", env = "PLEEZER_FALLBACK_ARTIST")]
+    fallback_artist: Option<String>,
+
+    /// Album title to pass to hook scripts when a song has none
+    #[arg(long, value_name = "TITLE", env = "PLEEZER_FALLBACK_ALBUM_TITLE")]
+    fallback_album_title: Option<String>,
+
+    /// Cover ID to pass to hook scripts when a track has no artwork
+    #[arg(long, value_name = "ID", env = "PLEEZER_FALLBACK_COVER_ID")]
+    fallback_cover_id: Option<String>,
+
+    /// Track IDs to keep retrying instead of marking them unavailable
+    ///
+    /// Comma-separated list of track IDs. Useful to work around or debug
+    /// specific problem tracks.
+    #[arg(
+        long,
+        value_name = "TRACK_IDS",
+        value_delimiter = ',',
+        env = "PLEEZER_ALLOW_TRACKS"
+    )]
+    allow_tracks: Vec<TrackId>,
+
+    /// Track IDs to always skip
+    ///
+    /// Comma-separated list of track IDs. Takes precedence over --allow-tracks.
+    #[arg(
+        long,
+        value_name = "TRACK_IDS",
+        value_delimiter = ',',
+        env = "PLEEZER_DENY_TRACKS"
+    )]
+    deny_tracks: Vec<TrackId>,
+
+    /// Skip songs with explicit lyrics
+    #[arg(long, default_value_t = false, env = "PLEEZER_SKIP_EXPLICIT")]
+    skip_explicit: bool,
+
+    /// Hosts to allow media downloads from
+    ///
+    /// Comma-separated list of hostnames, each also allowing its subdomains.
+    /// Sources on other hosts are skipped. By default all hosts are allowed.
+    #[arg(
+        long,
+        value_name = "HOSTS",
+        value_delimiter = ',',
+        env = "PLEEZER_TRUSTED_HOSTS"
+    )]
+    trusted_hosts: Vec<String>,
+
+    /// Additional HTTP header to send with gateway and media requests
+    ///
+    /// Formatted as NAME=VALUE, overriding any default header with the same
+    /// name. Only sent to Deezer hosts. Can be repeated.
+    #[arg(
+        long = "http-header",
+        value_name = "NAME=VALUE",
+        value_parser = parse_http_header,
+        env = "PLEEZER_HTTP_HEADER"
+    )]
+    http_headers: Vec<(String, String)>,
+
+    /// Enable the MPRIS interface for media keys and desktop widgets
+    ///
+    /// Registers pleezer on the D-Bus session bus, so that the desktop can
+    /// show the current track and control playback.
+    #[cfg(feature = "mpris")]
+    #[arg(long, default_value_t = false, env = "PLEEZER_MPRIS")]
+    mpris: bool,
+
+    /// Keep the machine from suspending while playing
+    ///
+    /// Takes a systemd-logind inhibitor lock while playback is active, and
+    /// releases it on pause or stop.
+    #[cfg(feature = "inhibit")]
+    #[arg(long, default_value_t = false, env = "PLEEZER_INHIBIT_SLEEP")]
+    inhibit_sleep: bool,
+
+    /// Read playback commands from standard input
+    ///
+    /// For testing without a controller. Commands are entered one per line:
+    /// "p" to play or pause, "n" for next, "b" for previous, "+" or "-" to
+    /// change the volume, "d" or "u" to duck or unduck, and a number to seek
+    /// to that percentage.
+    #[arg(long, default_value_t = false, env = "PLEEZER_STDIN_CONTROLS")]
+    stdin_controls: bool,
 
     /// Suppress all output except warnings and errors
     #[arg(short, long, default_value_t = false, group = ARGS_GROUP_LOGGING, env = "PLEEZER_QUIET")]
@@ -256,6 +848,26 @@ struct Args {
         env = "PLEEZER_EAVESDROP"
     )]
     eavesdrop: bool,
+
+    /// Cache the bf_secret from the web player in a file
+    ///
+    /// The cached secret is used when fetching it from the web player fails.
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, env = "PLEEZER_BF_SECRET_CACHE")]
+    bf_secret_cache: Option<PathBuf>,
+
+    /// Write all protocol messages to a file
+    ///
+    /// Messages are written as pretty-printed JSON regardless of the log
+    /// level, which helps to produce bug reports. The file is rotated at 10 MB.
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, env = "PLEEZER_PROTOCOL_LOG")]
+    protocol_log: Option<PathBuf>,
+
+    /// Log the sources of podcasts and radio stations
+    ///
+    /// Shows which hosts, codecs and bitrates were offered and which was
+    /// selected, to troubleshoot playback. Full URLs are not logged.
+    #[arg(long, default_value_t = false, env = "PLEEZER_LOG_SOURCES")]
+    log_sources: bool,
 }
 
 /// Initialize logging system.
@@ -321,6 +933,47 @@ fn init_logger(config: &Args) {
     logger.init();
 }
 
+/// Parses a content type and audio quality pair.
+///
+/// The pair is formatted as `TYPE=QUALITY`, for example `episode=standard`.
+///
+/// # Errors
+///
+/// Returns error if the content type or audio quality is not recognized.
+fn parse_type_quality(s: &str) -> std::result::Result<(TrackType, AudioQuality), String> {
+    let (typ, quality) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected TYPE=QUALITY, got {s}"))?;
+
+    let typ = typ.trim().parse().map_err(|e: Error| e.to_string())?;
+    let quality = match quality.trim().parse() {
+        Ok(AudioQuality::Unknown) | Err(_) => {
+            return Err(format!("unknown audio quality: {quality}"));
+        }
+        Ok(quality) => quality,
+    };
+
+    Ok((typ, quality))
+}
+
+/// Parses an HTTP header formatted as `NAME=VALUE`.
+///
+/// # Errors
+///
+/// Returns error if there is no `=` or the name is empty.
+fn parse_http_header(s: &str) -> std::result::Result<(String, String), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got {s}"))?;
+
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("header name is empty in {s}"));
+    }
+
+    Ok((name.to_string(), value.trim().to_string()))
+}
+
 /// Parse the secrets file into a configuration value.
 ///
 /// # Security
@@ -361,6 +1014,62 @@ fn parse_secrets(secrets: impl AsRef<Path>) -> Result<toml::Table> {
     })
 }
 
+/// Parses the ARLs from the secrets, in order of preference.
+///
+/// The `arl` secret is either a single ARL, a comma-separated list of ARLs,
+/// or an array of ARLs. Returns an empty list if there is no `arl` secret.
+///
+/// # Errors
+///
+/// Returns an error if any ARL is invalid.
+fn parse_arls(secrets: &toml::Table) -> Result<Vec<Arl>> {
+    let values: Vec<&str> = match secrets.get("arl") {
+        Some(toml::Value::String(value)) => value.split(',').collect(),
+        Some(toml::Value::Array(values)) => values
+            .iter()
+            .map(|value| {
+                value
+                    .as_str()
+                    .ok_or_else(|| Error::invalid_argument("arl must be a string"))
+            })
+            .collect::<Result<_>>()?,
+        Some(_) => return Err(Error::invalid_argument("arl must be a string or an array")),
+        None => Vec::new(),
+    };
+
+    values
+        .into_iter()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// Environment variables to read secrets from, with their secrets file keys.
+const SECRETS_ENV_VARS: [(&str, &str); 4] = [
+    ("PLEEZER_ARL", "arl"),
+    ("PLEEZER_EMAIL", "email"),
+    ("PLEEZER_PASSWORD", "password"),
+    ("PLEEZER_BF_SECRET", "bf_secret"),
+];
+
+/// Read secrets from environment variables into a configuration value.
+///
+/// Used when there is no secrets file, for example in containers. The
+/// variables are not command line arguments, so that they are never logged.
+/// Variables that are not set or empty are left out.
+fn secrets_from_env() -> toml::Table {
+    SECRETS_ENV_VARS
+        .iter()
+        .filter_map(|(var, key)| {
+            env::var(var)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .map(|value| ((*key).to_string(), toml::Value::String(value)))
+        })
+        .collect()
+}
+
 /// Main application loop.
 ///
 /// Handles the core application lifecycle:
@@ -400,6 +1109,36 @@ async fn run(args: Args) -> Result<ShutdownSignal> {
         ));
     }
 
+    if args
+        .dither_threshold
+        .is_some_and(|bits| !(0.0..=24.0).contains(&bits))
+    {
+        return Err(Error::invalid_argument(
+            "dither threshold must be between 0 and 24",
+        ));
+    }
+
+    if !matches!(args.livestream_codec, Codec::ADTS | Codec::MP3) {
+        return Err(Error::invalid_argument(format!(
+            "livestream codec must be aac or mp3, not {}",
+            args.livestream_codec
+        )));
+    }
+
+    if args.list_codecs {
+        // List supported codecs and containers and exit.
+        info!("supported codecs:");
+        for codec in Decoder::supported_codecs() {
+            info!("- {codec}");
+        }
+
+        info!("supported containers:");
+        for container in Decoder::supported_containers() {
+            info!("- {container} ({})", container.mime_type());
+        }
+        return Ok(ShutdownSignal::Interrupt);
+    }
+
     if args.device.as_ref().is_some_and(|device| device == "?") {
         // List available devices and exit.
         let devices = Player::enumerate_devices();
@@ -411,7 +1150,26 @@ async fn run(args: Args) -> Result<ShutdownSignal> {
 
         info!("available stereo 44.1/48 kHz output devices:");
         for device in devices {
-            info!("- {device}");
+            match Player::describe_sample_format(&device) {
+                Some(description) => info!("- {device} ({description})"),
+                None => info!("- {device}"),
+            }
+        }
+        return Ok(ShutdownSignal::Interrupt);
+    }
+
+    if let Some(device) = args.list_formats.as_ref() {
+        // List all supported formats of a single device and exit.
+        let formats = Player::enumerate_formats(device)?;
+        if formats.is_empty() {
+            return Err(Error::not_found(format!(
+                "no output formats found for device \"{device}\""
+            )));
+        }
+
+        info!("supported output formats of device \"{device}\":");
+        for format in formats {
+            info!("- {format}");
         }
         return Ok(ShutdownSignal::Interrupt);
     }
@@ -421,21 +1179,34 @@ async fn run(args: Args) -> Result<ShutdownSignal> {
     }
 
     let config = {
-        // Get the credentials from the secrets file.
-        info!("parsing secrets from {}", args.secrets);
-        let secrets = parse_secrets(args.secrets)?;
+        // Get the credentials from the secrets file, or from the environment
+        // when there is no secrets file.
+        let secrets = match parse_secrets(&args.secrets) {
+            Ok(secrets) => {
+                info!("parsed secrets from {}", args.secrets);
+                secrets
+            }
+            Err(e) if e.kind == ErrorKind::NotFound => {
+                info!(
+                    "{} not found, reading secrets from environment",
+                    args.secrets
+                );
+                secrets_from_env()
+            }
+            Err(e) => return Err(e),
+        };
 
-        let credentials = match secrets.get("arl").and_then(|value| value.as_str()) {
+        let mut arls = parse_arls(&secrets)?.into_iter();
+        let credentials = match arls.next() {
             Some(arl) => {
-                let result = arl.parse::<Arl>()?;
-                info!("using arl from secrets file");
-                Credentials::Arl(result)
+                info!("using arl from secrets");
+                Credentials::Arl(arl)
             }
             None => {
                 let email = secrets
                     .get("email")
                     .and_then(|email| email.as_str())
-                    .ok_or_else(|| Error::unauthenticated("email not found"))?;
+                    .ok_or_else(|| Error::unauthenticated("neither arl nor email found"))?;
                 let password = secrets
                     .get("password")
                     .and_then(|password| password.as_str())
@@ -448,6 +1219,11 @@ async fn run(args: Args) -> Result<ShutdownSignal> {
             }
         };
 
+        let fallback_arls: Vec<_> = arls.collect();
+        if !fallback_arls.is_empty() {
+            info!("using {} fallback arl(s)", fallback_arls.len());
+        }
+
         let bf_secret = match secrets.get("bf_secret").and_then(|value| value.as_str()) {
             Some(value) => {
                 let key = value.parse::<decrypt::Key>()?;
@@ -517,6 +1293,7 @@ async fn run(args: Args) -> Result<ShutdownSignal> {
             app_name: app_name.clone(),
             app_version,
             app_lang,
+            protocol_version: args.protocol_version,
 
             device_id,
             device_type: args.device_type,
@@ -526,27 +1303,104 @@ async fn run(args: Args) -> Result<ShutdownSignal> {
                 .unwrap_or_else(|| app_name.clone()),
 
             interruptions: !args.no_interruptions,
+            announce_only: args.announce_only,
+            private_listening: args.private_listening,
+            full_stop: args.full_stop,
+            min_report_interval: Duration::from_secs(args.min_report_interval),
+            reject_unknown_containers: args.reject_unknown_containers,
+            handshake_skip_status: if args.handshake_skip_ok {
+                Status::OK
+            } else {
+                Status::Error
+            },
+            startup_delay: Duration::from_secs(args.startup_delay),
+            startup_timeout: Duration::from_secs(args.startup_timeout),
+            startup_retries: args.startup_retries,
+            restart_on_token_expiry: args.restart_on_token_expiry,
+            shuffle_seed: None,
 
             normalization: args.normalize_volume,
+            quality_overrides: args.quality.into_iter().collect(),
+            normalization_types: if args.normalize_types.is_empty() {
+                [TrackType::Song, TrackType::Episode, TrackType::Livestream].into()
+            } else {
+                args.normalize_types.into_iter().collect()
+            },
             loudness: args.loudness,
+            loudness_target: args.loudness_target,
+            fade_in: (args.fade_in > 0).then(|| Duration::from_millis(args.fade_in)),
+            skip_fade: (args.skip_fade > 0).then(|| Duration::from_millis(args.skip_fade)),
+            livestream_codec: args.livestream_codec,
+            gapless_codecs: if args.gapless_codecs.is_empty() {
+                [Codec::ADTS, Codec::FLAC, Codec::MP3, Codec::MP4, Codec::WAV].into()
+            } else {
+                args.gapless_codecs.into_iter().collect()
+            },
+            max_livestream_kbps: args.max_livestream_kbps,
+            replay_gain_dir: args.replay_gain_dir,
             initial_volume: args
                 .initial_volume
                 .map(|volume| Percentage::from_percent(volume as f32)),
+            min_volume: args
+                .min_volume
+                .map(|volume| Percentage::from_percent(volume.into())),
 
             dither_bits: args.dither_bits,
+            dither_threshold: args.dither_threshold,
             noise_shaping: args.noise_shaping,
+            resample_unsupported: args.resample_unsupported,
+            reopen_on_rate_change: args.reopen_on_rate_change,
+            clip_detection: args.clip_detection,
+            idle_timeout: (args.idle_timeout > 0).then(|| Duration::from_secs(args.idle_timeout)),
+            position_timeout: (args.position_timeout > 0)
+                .then(|| Duration::from_secs(args.position_timeout)),
 
             // Convert MB to bytes
             max_ram: args.max_ram.map(|mb| mb * 1024 * 1024),
-            hook: args.hook,
+            ram_threshold: args.ram_threshold.map(|mb| mb * 1024 * 1024),
+            preload_depth: args.preload_depth.into(),
+            max_queued_sources: (args.max_queued_sources > 0)
+                .then(|| args.max_queued_sources.into()),
+            audio_priority: (args.audio_priority > 0).then_some(args.audio_priority),
+
+            verify_downloads: args.verify_downloads,
+            verify_device: args.verify_device,
+            device_timeout: Duration::from_secs(args.device_timeout),
+            livestream_retries: args.livestream_retries,
+            zero_byte_retries: args.zero_byte_retries,
+            load_failures: args.load_failures,
+            max_download_kbps: (args.max_download_kbps > 0).then_some(args.max_download_kbps),
+            livestream_retry_delay: Duration::from_secs(args.livestream_retry_delay),
+            livestream_live_edge: args.livestream_live_edge,
+            hooks: args.hook,
+            hook_timeout: Duration::from_secs(args.hook_timeout),
+            metadata_fallbacks: MetadataFallbacks {
+                title: args.fallback_title,
+                artist: args.fallback_artist,
+                album_title: args.fallback_album_title,
+                cover_id: args.fallback_cover_id,
+            },
+            scrobble_url: args.scrobble_url,
+            scrobble_percent: args.scrobble_percent,
+            scrobble_after: Duration::from_secs(args.scrobble_after),
+
+            allow_tracks: args.allow_tracks.into_iter().collect(),
+            deny_tracks: args.deny_tracks.into_iter().collect(),
+            skip_explicit: args.skip_explicit,
+            trusted_hosts: args.trusted_hosts.into_iter().collect(),
+            http_headers: args.http_headers.into_iter().collect(),
 
             client_id,
             user_agent,
 
             credentials,
+            fallback_arls,
             bf_secret,
+            bf_secret_cache: args.bf_secret_cache,
 
             eavesdrop: args.eavesdrop,
+            protocol_log: args.protocol_log,
+            log_sources: args.log_sources,
             bind_address: args.bind.parse()?,
         }
     };
@@ -555,6 +1409,38 @@ async fn run(args: Args) -> Result<ShutdownSignal> {
     let mut client = remote::Client::new(&config, player)?;
     let mut signals = signal::Handler::new()?;
 
+    // The MPRIS interface is not essential, so failing to serve it is not fatal.
+    // It stops when the client is dropped.
+    #[cfg(feature = "mpris")]
+    if args.mpris {
+        let controls = client.control_sender();
+        let state = client.subscribe_state();
+        let identity = config.device_name.clone();
+        tokio::spawn(async move {
+            if let Err(e) = pleezer::mpris::serve(identity, controls, state).await {
+                error!("mpris interface stopped: {e}");
+            }
+        });
+    }
+
+    // Like MPRIS, inhibiting sleep is not essential.
+    #[cfg(feature = "inhibit")]
+    if args.inhibit_sleep {
+        let state = client.subscribe_state();
+        tokio::spawn(async move {
+            if let Err(e) = pleezer::inhibit::serve(state).await {
+                error!("sleep inhibitor stopped: {e}");
+            }
+        });
+    }
+
+    if args.stdin_controls {
+        spawn_stdin_controls(client.control_sender(), client.subscribe_state());
+    }
+
+    #[cfg(unix)]
+    spawn_stats_dump(client.subscribe_stats())?;
+
     // Main application loop. This restarts the new remote client when it gets disconnected for
     // whatever reason. This could be from a network failure or an arl that expired. In this case,
     // we try to recover from the error by restarting the client. If the error is a permission
@@ -624,6 +1510,104 @@ async fn run(args: Args) -> Result<ShutdownSignal> {
     }
 }
 
+/// Parses a line of the stdin controls into a playback control.
+///
+/// Returns `None` if the line is not a valid command. Seeking needs the
+/// duration of the current track from `state`.
+fn parse_stdin_control(line: &str, state: &remote::PlaybackState) -> Option<remote::Control> {
+    use remote::Control;
+
+    match line.trim() {
+        "p" => Some(Control::PlayPause),
+        "n" => Some(Control::Next),
+        "b" => Some(Control::Previous),
+        "+" => Some(Control::ChangeVolume(STDIN_VOLUME_STEP)),
+        "-" => Some(Control::ChangeVolume(-STDIN_VOLUME_STEP)),
+        "d" => Some(Control::Duck {
+            percent: STDIN_DUCK_PERCENT,
+            fade: STDIN_DUCK_FADE,
+        }),
+        "u" => Some(Control::Unduck(STDIN_DUCK_FADE)),
+        percent => {
+            let percent = percent.parse::<f32>().ok()?.clamp(0.0, 100.0);
+            let duration = state.duration?;
+            Some(Control::Seek(duration.mul_f32(percent / 100.0)))
+        }
+    }
+}
+
+/// Reads playback commands from standard input and sends them to the client.
+///
+/// Reading standard input blocks, so this runs on its own thread. It stops
+/// when standard input is closed or the client is dropped.
+fn spawn_stdin_controls(
+    controls: tokio::sync::mpsc::UnboundedSender<remote::Control>,
+    state: tokio::sync::watch::Receiver<remote::PlaybackState>,
+) {
+    info!("reading controls from stdin: p=play/pause, n=next, b=previous, +/-=volume, 0-100=seek");
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match parse_stdin_control(&line, &state.borrow()) {
+                Some(control) => {
+                    if controls.send(control).is_err() {
+                        break;
+                    }
+                }
+                None => warn!("unknown stdin command: {}", line.trim()),
+            }
+        }
+    });
+}
+
+/// Logs the session statistics whenever SIGUSR1 is received.
+///
+/// Stops when the client is dropped.
+///
+/// # Errors
+///
+/// Returns an error if the signal handler cannot be registered.
+#[cfg(unix)]
+fn spawn_stats_dump(mut stats: tokio::sync::watch::Receiver<remote::SessionStats>) -> Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut sigusr1 = signal(SignalKind::user_defined1())?;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = sigusr1.recv() => {
+                    let remote::SessionStats {
+                        starts,
+                        connects,
+                        disconnects,
+                        websocket_reconnects,
+                        token_refreshes,
+                        unavailable_tracks,
+                    } = stats.borrow().clone();
+                    info!(
+                        "session statistics: {starts} starts, {connects} connects, \
+                         {disconnects} disconnects, {websocket_reconnects} websocket reconnects, \
+                         {token_refreshes} token refreshes, {unavailable_tracks} unavailable tracks"
+                    );
+                }
+                result = stats.changed() => {
+                    if result.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
 /// Application entry point.
 ///
 /// Sets up the environment and manages the application lifecycle:
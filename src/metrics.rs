@@ -0,0 +1,283 @@
+//! Performance counters for the audio pipeline.
+//!
+//! Tracks whether decoding and DSP keep up with the output device:
+//! * Samples delivered to the output
+//! * Buffer underruns reported by the output device
+//! * Average processing time per block of samples
+//!
+//! Separately, [`clip_detected`] counts samples that clip at the output.
+//!
+//! Timing every sample would itself cost noticeable CPU on low-end hardware.
+//! Instead, one in every [`Metrics::TIMING_INTERVAL`] blocks of
+//! [`Metrics::BLOCK_SIZE`] samples is timed as a whole. The interval is a
+//! prime so that the timed blocks do not align with decoder packet sizes.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use rodio::{ChannelCount, Source, source::SeekError};
+
+/// Shared performance counters.
+///
+/// Updated from the audio thread and read from the player, using relaxed
+/// atomics as the counters are informational only.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Number of samples delivered to the output.
+    samples: AtomicU64,
+
+    /// Number of buffer underruns reported by the output device.
+    underruns: AtomicU64,
+
+    /// Number of blocks that were timed.
+    timed_blocks: AtomicU64,
+
+    /// Total processing time of the timed blocks in nanoseconds.
+    timed_nanos: AtomicU64,
+}
+
+/// Point-in-time copy of the performance counters.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Number of samples delivered to the output.
+    pub samples: u64,
+
+    /// Number of buffer underruns reported by the output device.
+    pub underruns: u64,
+
+    /// Average decoding and DSP time per block of [`Metrics::BLOCK_SIZE`] samples.
+    pub average_block_time: Duration,
+}
+
+impl Metrics {
+    /// Number of samples that the average processing time is reported for.
+    pub const BLOCK_SIZE: u64 = 1024;
+
+    /// Interval in blocks at which a block is timed.
+    pub const TIMING_INTERVAL: u64 = 17;
+
+    /// Creates a new set of counters, all zero.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a buffer underrun of the output device.
+    #[inline]
+    pub fn add_underrun(&self) {
+        self.underruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns a copy of the current counters.
+    #[must_use]
+    pub fn snapshot(&self) -> Snapshot {
+        let timed_blocks = self.timed_blocks.load(Ordering::Relaxed);
+        let timed_nanos = self.timed_nanos.load(Ordering::Relaxed);
+        let average_block_time = timed_nanos
+            .checked_div(timed_blocks)
+            .map_or(Duration::ZERO, Duration::from_nanos);
+
+        Snapshot {
+            samples: self.samples.load(Ordering::Relaxed),
+            underruns: self.underruns.load(Ordering::Relaxed),
+            average_block_time,
+        }
+    }
+
+    /// Records the processing time of a timed block.
+    #[inline]
+    fn add_timing(&self, elapsed: Duration) {
+        self.timed_blocks.fetch_add(1, Ordering::Relaxed);
+        self.timed_nanos.fetch_add(
+            elapsed.as_nanos().try_into().unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+    }
+}
+
+/// Returns whether a stream error reports a buffer underrun, also known as
+/// an xrun.
+#[must_use]
+pub fn is_underrun(err: &cpal::StreamError) -> bool {
+    matches!(err, cpal::StreamError::BufferUnderrun)
+}
+
+/// Audio source that updates performance counters as it is played.
+#[derive(Debug)]
+pub struct Instrumented<I> {
+    /// The underlying audio source
+    input: I,
+
+    /// Counters to update
+    metrics: Arc<Metrics>,
+
+    /// Number of samples delivered since the counters were last updated
+    count: u64,
+
+    /// Processing time of the block being timed so far
+    elapsed: Duration,
+}
+
+/// Wraps a source to update `metrics` with every sample it delivers.
+#[must_use]
+pub fn instrumented<I>(input: I, metrics: Arc<Metrics>) -> Instrumented<I>
+where
+    I: Source,
+{
+    Instrumented {
+        input,
+        metrics,
+        count: 0,
+        elapsed: Duration::ZERO,
+    }
+}
+
+impl<I> Iterator for Instrumented<I>
+where
+    I: Source,
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.count += 1;
+
+        // Time the first block of every interval, excluding the time spent
+        // by the caller between samples.
+        let sample = if self.count <= Metrics::BLOCK_SIZE {
+            let start = Instant::now();
+            let sample = self.input.next();
+            self.elapsed += start.elapsed();
+            if self.count == Metrics::BLOCK_SIZE {
+                self.metrics.add_timing(self.elapsed);
+                self.elapsed = Duration::ZERO;
+            }
+            sample
+        } else {
+            self.input.next()
+        };
+
+        // Update the shared sample counter once per interval.
+        if self.count == Metrics::BLOCK_SIZE * Metrics::TIMING_INTERVAL {
+            self.metrics
+                .samples
+                .fetch_add(self.count, Ordering::Relaxed);
+            self.count = 0;
+        }
+
+        sample
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> Source for Instrumented<I>
+where
+    I: Source,
+{
+    #[inline]
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    #[inline]
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
+
+/// Audio source that counts samples exceeding full scale.
+#[derive(Debug)]
+pub struct ClipDetected<I> {
+    /// The underlying audio source
+    input: I,
+
+    /// Number of samples that clipped
+    clipped: Arc<AtomicU64>,
+}
+
+/// Wraps a source to count samples beyond ±1.0 into `clipped`.
+///
+/// Resets the counter, so it counts the clips of this source only.
+#[must_use]
+pub fn clip_detected<I>(input: I, clipped: Arc<AtomicU64>) -> ClipDetected<I>
+where
+    I: Source,
+{
+    clipped.store(0, Ordering::Relaxed);
+    ClipDetected { input, clipped }
+}
+
+impl<I> Iterator for ClipDetected<I>
+where
+    I: Source,
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = self.input.next()?;
+        if sample.abs() > 1.0 {
+            self.clipped.fetch_add(1, Ordering::Relaxed);
+        }
+        Some(sample)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> Source for ClipDetected<I>
+where
+    I: Source,
+{
+    #[inline]
+    fn current_span_len(&self) -> Option<usize> {
+        self.input.current_span_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> ChannelCount {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    #[inline]
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
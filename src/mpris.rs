@@ -0,0 +1,375 @@
+//! MPRIS interface for desktop integration on Linux.
+//!
+//! Exposes the player on the D-Bus session bus following the
+//! [MPRIS 2](https://specifications.freedesktop.org/mpris-spec/latest/)
+//! specification, so media keys, status bars and other desktop widgets can:
+//! * Show the current track with its metadata and cover art
+//! * Show the playback status and position
+//! * Control playback: play, pause, next, previous and seek
+//!
+//! The interface mirrors the [`PlaybackState`] published by the
+//! [`Client`](crate::remote::Client) and forwards controls to it as
+//! [`Control`]s. The Deezer Connect controller stays in charge of the queue,
+//! so the track list, volume and loop status are not exposed.
+//!
+//! Only available with the `mpris` feature.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use pleezer::{mpris, remote::Client};
+//!
+//! async fn example(client: &Client) {
+//!     let controls = client.control_sender();
+//!     let state = client.subscribe_state();
+//!     tokio::spawn(mpris::serve("pleezer", controls, state));
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use tokio::sync::{mpsc::UnboundedSender, watch};
+use zbus::{
+    interface,
+    object_server::SignalEmitter,
+    zvariant::{ObjectPath, OwnedValue, Value},
+};
+
+use crate::{
+    error::{Error, Result},
+    remote::{Control, PlaybackState},
+};
+
+/// Object path of the MPRIS interfaces.
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+
+/// Prefix of the bus name, followed by the instance.
+const BUS_NAME_PREFIX: &str = "org.mpris.MediaPlayer2.pleezer";
+
+/// Object path for tracks without an ID, as defined by the specification.
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+/// Difference from the expected position that is reported as a seek.
+const SEEK_THRESHOLD: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Root interface of the media player.
+struct Root {
+    /// Name to identify the player as
+    identity: String,
+}
+
+// D-Bus methods and properties take `self`, even when they do not use it.
+#[allow(clippy::unused_self)]
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    /// Raising is not supported by a headless player.
+    fn raise(&self) {}
+
+    /// Quitting is not supported over D-Bus.
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> String {
+        self.identity.clone()
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Player interface of the media player.
+struct Player {
+    /// Channel to send controls to the client
+    controls: UnboundedSender<Control>,
+
+    /// Last known playback state
+    state: PlaybackState,
+}
+
+impl Player {
+    /// Sends a control to the client, logging when the client is gone.
+    fn send(&self, control: Control) {
+        if let Err(e) = self.controls.send(control) {
+            error!("failed to send {control:?}: {e}");
+        }
+    }
+}
+
+#[allow(clippy::unused_self)]
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        self.send(Control::Next);
+    }
+
+    fn previous(&self) {
+        self.send(Control::Previous);
+    }
+
+    fn pause(&self) {
+        self.send(Control::Pause);
+    }
+
+    fn play_pause(&self) {
+        self.send(Control::PlayPause);
+    }
+
+    /// Stopping is mapped onto pausing, to keep the queue.
+    fn stop(&self) {
+        self.send(Control::Pause);
+    }
+
+    fn play(&self) {
+        self.send(Control::Play);
+    }
+
+    /// Seeks relative to the current position, in microseconds.
+    fn seek(&self, offset: i64) {
+        let position = self.state.position();
+        let offset_abs = std::time::Duration::from_micros(offset.unsigned_abs());
+        let target = if offset < 0 {
+            position.saturating_sub(offset_abs)
+        } else {
+            position.saturating_add(offset_abs)
+        };
+
+        // Seeking past the end skips to the next track.
+        if self
+            .state
+            .duration
+            .is_some_and(|duration| target >= duration)
+        {
+            self.send(Control::Next);
+        } else {
+            self.send(Control::Seek(target));
+        }
+    }
+
+    /// Seeks to an absolute position, in microseconds, if the track matches.
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        if track_id.as_str() != track_path(&self.state).as_str() {
+            return;
+        }
+
+        if let Ok(position) = u64::try_from(position) {
+            self.send(Control::Seek(std::time::Duration::from_micros(position)));
+        }
+    }
+
+    /// Opening URIs is not supported, as the controller owns the queue.
+    fn open_uri(&self, _uri: &str) {}
+
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        let status = if self.state.playing {
+            "Playing"
+        } else if self.state.track_id.is_some() {
+            "Paused"
+        } else {
+            "Stopped"
+        };
+        status.to_string()
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let state = &self.state;
+        let mut metadata = HashMap::new();
+
+        let mut insert = |key: &str, value: Value<'_>| {
+            if let Ok(value) = OwnedValue::try_from(value) {
+                metadata.insert(key.to_string(), value);
+            }
+        };
+
+        insert("mpris:trackid", Value::from(track_path(state)));
+        if let Some(duration) = state.duration {
+            insert("mpris:length", Value::from(micros(duration)));
+        }
+        if let Some(url) = &state.cover_url {
+            insert("mpris:artUrl", Value::from(url.as_str()));
+        }
+        if let Some(title) = &state.title {
+            insert("xesam:title", Value::from(title.as_str()));
+        }
+        if let Some(artist) = &state.artist {
+            insert("xesam:artist", Value::from(vec![artist.as_str()]));
+        }
+        if let Some(album) = &state.album {
+            insert("xesam:album", Value::from(album.as_str()));
+        }
+
+        metadata
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        micros(self.state.position())
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        self.state.track_id.is_some()
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        self.state.track_id.is_some()
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        self.state.track_id.is_some()
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        self.state.track_id.is_some()
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        self.state.duration.is_some()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+/// Returns the object path that identifies the current track.
+fn track_path(state: &PlaybackState) -> ObjectPath<'static> {
+    state
+        .track_id
+        .and_then(|track_id| {
+            // Object paths cannot contain dashes of negative IDs.
+            let id = track_id.to_string().replace('-', "_");
+            ObjectPath::try_from(format!("/org/pleezer/track/{id}")).ok()
+        })
+        .unwrap_or_else(|| ObjectPath::from_static_str_unchecked(NO_TRACK))
+}
+
+/// Converts a duration to microseconds, as used throughout MPRIS.
+fn micros(duration: std::time::Duration) -> i64 {
+    i64::try_from(duration.as_micros()).unwrap_or(i64::MAX)
+}
+
+/// Serves the MPRIS interfaces until the client is dropped.
+///
+/// Registers on the session bus as `identity`, forwards controls to
+/// `controls` and emits property changes as `state` changes.
+///
+/// # Errors
+///
+/// Returns error if:
+/// * The session bus is not available
+/// * The bus name cannot be acquired
+/// * Signals cannot be emitted
+pub async fn serve(
+    identity: impl Into<String>,
+    controls: UnboundedSender<Control>,
+    mut state: watch::Receiver<PlaybackState>,
+) -> Result<()> {
+    let bus_name = format!("{BUS_NAME_PREFIX}.instance{}", std::process::id());
+    let root = Root {
+        identity: identity.into(),
+    };
+    let player = Player {
+        controls,
+        state: state.borrow().clone(),
+    };
+
+    let connection = zbus::connection::Builder::session()
+        .and_then(|builder| builder.name(bus_name.as_str()))
+        .and_then(|builder| builder.serve_at(OBJECT_PATH, root))
+        .and_then(|builder| builder.serve_at(OBJECT_PATH, player))
+        .map_err(|e| Error::unavailable(format!("failed to set up mpris: {e}")))?
+        .build()
+        .await
+        .map_err(|e| Error::unavailable(format!("failed to connect to session bus: {e}")))?;
+    info!("mpris interface available as {bus_name}");
+
+    let player = connection
+        .object_server()
+        .interface::<_, Player>(OBJECT_PATH)
+        .await
+        .map_err(|e| Error::internal(format!("mpris interface not found: {e}")))?;
+
+    // Returns when the sending half is dropped with the client.
+    while state.changed().await.is_ok() {
+        let new = state.borrow_and_update().clone();
+        let mut iface = player.get_mut().await;
+        let old = std::mem::replace(&mut iface.state, new);
+        let new = &iface.state;
+        let emitter = player.signal_emitter();
+
+        let result = async {
+            if old.playing != new.playing || old.track_id != new.track_id {
+                iface.playback_status_changed(emitter).await?;
+            }
+
+            if old.track_id != new.track_id
+                || old.title != new.title
+                || old.duration != new.duration
+                || old.cover_url != new.cover_url
+            {
+                iface.metadata_changed(emitter).await?;
+                iface.can_seek_changed(emitter).await?;
+            } else if old.position().abs_diff(new.position()) > SEEK_THRESHOLD {
+                // Positions are not signalled as properties, as they change
+                // continuously. Only report jumps to keep widgets in sync.
+                Player::seeked(emitter, micros(new.position())).await?;
+            }
+
+            Ok::<_, zbus::Error>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            warn!("failed to signal mpris changes: {e}");
+        }
+    }
+
+    Ok(())
+}
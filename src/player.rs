@@ -70,11 +70,21 @@
 //! player.stop();
 //! ```
 
-use std::{collections::HashSet, f32, sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    f32,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use cpal::traits::{DeviceTrait, HostTrait};
 use md5::{Digest, Md5};
-use rodio::{ChannelCount, Source, math::db_to_linear, source::LimitSettings};
+use rodio::{
+    ChannelCount, Source,
+    math::db_to_linear,
+    source::{LimitSettings, UniformSourceIterator},
+};
 use stream_download::storage::{
     adaptive::AdaptiveStorageProvider, memory::MemoryStorageProvider, temp::TempStorageProvider,
 };
@@ -83,19 +93,21 @@ use url::Url;
 use crate::{
     config::Config,
     decoder::Decoder,
-    decrypt::{self},
+    decrypt::{self, Key, KeySource},
     dither,
     error::{Error, ErrorKind, Result},
     events::Event,
     http,
+    metrics::{self, Metrics},
     protocol::{
+        Codec,
         connect::{
             Percentage,
             contents::{AudioQuality, RepeatMode},
         },
         gateway::{self, MediaUrl},
     },
-    track::{DEFAULT_BITS_PER_SAMPLE, Track, TrackId},
+    track::{DEFAULT_BITS_PER_SAMPLE, DEFAULT_SAMPLE_RATE, Storage, Track, TrackId, TrackType},
     util::{ToF32, UNITY_GAIN},
     volume::Volume,
 };
@@ -158,6 +170,56 @@ pub struct Player {
     /// or become unavailable.
     skip_tracks: HashSet<TrackId>,
 
+    /// Number of tracks marked unavailable since the player was created.
+    unavailable_count: u64,
+
+    /// Number of times each track failed to load.
+    ///
+    /// Tracks are marked unavailable once they reach `load_failures`.
+    failed_loads: HashMap<TrackId, u8>,
+
+    /// Number of times a track may fail to load before it is marked unavailable.
+    load_failures: u8,
+
+    /// Set of track IDs that were downloaded again after failing
+    /// the integrity check.
+    ///
+    /// Tracks are only downloaded again once to prevent loops.
+    redownloaded_tracks: HashSet<TrackId>,
+
+    /// Set of track IDs that were retried after a forbidden download.
+    ///
+    /// Tracks are only retried once, with a renewed license token.
+    license_retried: HashSet<TrackId>,
+
+    /// Whether loading waits for a renewed license token.
+    ///
+    /// Set after a forbidden download, and cleared when a license token is
+    /// delivered with [`set_license_token`](Self::set_license_token) or the
+    /// renewal is cancelled.
+    license_pending: bool,
+
+    /// Whether to verify the integrity of completed downloads.
+    ///
+    /// When enabled, tracks with truncated downloads are
+    /// downloaded again.
+    verify_downloads: bool,
+
+    /// Set of track IDs that are never marked as unavailable.
+    ///
+    /// Tracks in this set are retried instead of skipped
+    /// when they fail to load.
+    allow_tracks: HashSet<TrackId>,
+
+    /// Set of track IDs that are always skipped.
+    ///
+    /// Tracks in this set are skipped without attempting
+    /// to load them. Takes precedence over `allow_tracks`.
+    deny_tracks: HashSet<TrackId>,
+
+    /// Whether to skip songs with explicit lyrics.
+    skip_explicit: bool,
+
     /// Current position in the queue.
     ///
     /// May exceed queue length to prepare for
@@ -184,6 +246,11 @@ pub struct Player {
     /// Whether volume normalization is enabled.
     normalization: bool,
 
+    /// Track types that volume normalization is applied to.
+    ///
+    /// Only takes effect when `normalization` is enabled.
+    normalization_types: HashSet<TrackType>,
+
     /// Whether equal-loudness compensation is enabled.
     ///
     /// When enabled, applies frequency-dependent gain based on
@@ -191,17 +258,55 @@ pub struct Player {
     /// human hearing sensitivity variations.
     loudness: bool,
 
+    /// Duration of the fade-in at the start of each track.
+    fade_in: Option<Duration>,
+
+    /// Duration to fade through silence when skipping manually.
+    skip_fade: Option<Duration>,
+
+    /// Whether the next track to load fades in from silence after a manual skip.
+    fading_through_silence: bool,
+
+    /// Preferred codec for livestreams that offer both AAC and MP3.
+    livestream_codec: Codec,
+
+    /// Codecs to trim encoder delay and padding of, for gapless playback.
+    gapless_codecs: HashSet<Codec>,
+
+    /// Whether to log the sources of external content.
+    log_sources: bool,
+
+    /// Maximum bitrate of livestreams in kbps, if capped.
+    max_livestream_kbps: Option<usize>,
+
+    /// Audio quality per content type, overriding `audio_quality`.
+    quality_overrides: BTreeMap<TrackType, AudioQuality>,
+
+    /// Directory with `ReplayGain` sidecar files, named after track IDs.
+    replay_gain_dir: Option<PathBuf>,
+
     /// Target gain for volume normalization in dB.
     ///
     /// Used to calculate normalization ratios.
     gain_target_db: i8,
 
+    /// Reference level for equal-loudness compensation in LUFS.
+    ///
+    /// Follows `gain_target_db` when `None`.
+    loudness_target: Option<i8>,
+
     /// Raw volume setting as a percentage (0.0 to 1.0).
     ///
     /// This stores the user-set volume before logarithmic scaling is applied.
     /// The actual output volume uses logarithmic scaling for better perceived control.
     volume: Percentage,
 
+    /// Attenuation while ducked, as a ratio of the volume.
+    ///
+    /// Unity gain when not ducked. Kept apart from `volume`, so the user's
+    /// volume is restored when unducking.
+    duck_ratio: f32,
+
     /// Dithered volume control shared across all sources.
     ///
     /// Provides volume adjustment with dithering for improved audio quality.
@@ -210,9 +315,15 @@ pub struct Player {
     /// Bit depth for dithering.
     dither_bits: Option<f32>,
 
+    /// Bit depth above which dithering is skipped.
+    dither_threshold: Option<f32>,
+
     /// Noise shaping for dithering.
     noise_shaping: u8,
 
+    /// Whether to count samples that clip at the output.
+    clip_detection: bool,
+
     /// Channel for sending playback events.
     ///
     /// Events include:
@@ -227,6 +338,50 @@ pub struct Player {
     /// Format: `[<host>][|<device>][|<sample rate>][|<sample format>]`.
     device: String,
 
+    /// Sample rate to open the device at, overriding the device specification.
+    ///
+    /// Set when reopening the device to match the sample rate of a track.
+    device_rate: Option<u32>,
+
+    /// Configuration of the open output device.
+    output_config: Option<rodio::SupportedStreamConfig>,
+
+    /// Whether to reopen the output device when the sample rate of a track differs.
+    reopen_on_rate_change: bool,
+
+    /// Time after which a paused player releases the output device.
+    idle_timeout: Option<Duration>,
+
+    /// Performance counters of the audio pipeline.
+    metrics: Arc<Metrics>,
+
+    /// Where the decryption key in use came from.
+    bf_secret_source: KeySource,
+
+    /// When the performance counters were last logged.
+    metrics_logged_at: std::time::Instant,
+
+    /// When playback was paused with the output device open.
+    /// Used for the idle timeout, and to resume livestreams at the live edge.
+    paused_since: Option<std::time::Instant>,
+
+    /// Time to wait for a queue update when the position is beyond the queue.
+    position_timeout: Option<Duration>,
+
+    /// Since when the position has been beyond the queue.
+    out_of_range_since: Option<std::time::Instant>,
+
+    /// Whether the output device was released after being idle.
+    ///
+    /// Loading tracks is suspended until the device is opened again.
+    idle_released: bool,
+
+    /// Whether to check that the output device is still current on resume.
+    verify_device: bool,
+
+    /// Name of the open output device.
+    device_name: Option<String>,
+
     /// Audio output sink.
     ///
     /// Handles final audio output and volume control.
@@ -260,11 +415,23 @@ pub struct Player {
     /// Receiver is notified when track finishes.
     current_rx: Option<std::sync::mpsc::Receiver<()>>,
 
-    /// Completion signal for preloaded track.
+    /// Completion signals for preloaded tracks, in queue order.
     ///
-    /// Receiver is notified when preloaded track
+    /// Receivers are notified when preloaded tracks
     /// would finish. Used for gapless playback.
-    preload_rx: Option<std::sync::mpsc::Receiver<()>>,
+    preload_rx: VecDeque<std::sync::mpsc::Receiver<()>>,
+
+    /// Maximum number of tracks to preload ahead of the current track.
+    preload_depth: usize,
+
+    /// Maximum number of sources queued for output, if bounded.
+    max_queued_sources: Option<usize>,
+
+    /// Real-time priority of the output thread, if raised.
+    audio_priority: Option<u8>,
+
+    /// Whether the preloaded next track was reported as fully buffered.
+    next_buffered: bool,
 
     /// When to start preloading next track.
     preload_start: Duration,
@@ -277,6 +444,46 @@ pub struct Player {
     /// Maximum RAM in bytes that can be used for storing audio files.
     /// `None` means use temporary files instead of RAM.
     max_ram: Option<u64>,
+
+    /// Largest download in bytes to store in RAM, if set.
+    ram_threshold: Option<u64>,
+
+    /// How long to wait for the audio device to appear when opening it.
+    device_timeout: Duration,
+
+    /// Maximum number of times to reconnect an interrupted livestream.
+    livestream_retries: u32,
+
+    /// Number of times to request a source again that responded with 0 bytes.
+    zero_byte_retries: u8,
+
+    /// Maximum download rate in kbps, if limited.
+    max_download_kbps: Option<usize>,
+
+    /// Delay before reconnecting an interrupted livestream.
+    ///
+    /// Doubles with every consecutive attempt.
+    livestream_retry_delay: Duration,
+
+    /// Whether to resume livestreams from the live edge after pausing.
+    livestream_live_edge: bool,
+
+    /// Number of consecutive reconnection attempts for the current livestream.
+    livestream_attempts: u32,
+
+    /// When the next attempt to load the current track may be made, like
+    /// reconnecting an interrupted livestream.
+    retry_at: Option<std::time::Instant>,
+
+    /// Volume that any non-zero volume is raised to at least.
+    min_volume: Percentage,
+
+    /// Whether to resample sample rates that noise shaping is not available for.
+    resample_unsupported: bool,
+
+    /// Whether the queue is open-ended and extended by the controller side,
+    /// like Flow. Such queues do not wrap around at the end.
+    extendable: bool,
 }
 
 impl Player {
@@ -299,6 +506,166 @@ impl Player {
     /// sudden audio cutoffs that can cause popping sounds.
     const FADE_DURATION: Duration = Duration::from_millis(50);
 
+    /// Duration after which a livestream is considered stable.
+    ///
+    /// When a livestream played at least this long before it was
+    /// interrupted, the reconnection attempts are reset.
+    const LIVESTREAM_STABLE_DURATION: Duration = Duration::from_secs(30);
+
+    /// Delay before retrying a network request while starting up.
+    const STARTUP_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+    /// Delay before loading a track again that failed to load, but is not
+    /// marked unavailable yet.
+    const LOAD_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+    /// Number of hex digits of the `bf_secret` checksum to log.
+    const BF_SECRET_MD5_PREFIX_LEN: usize = 8;
+
+    /// Interval at which the performance counters are logged while playing.
+    const METRICS_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// Fetches the `bf_secret` from the web player.
+    ///
+    /// Transient failures are retried with the startup timeout and retries
+    /// from the configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the secret cannot be fetched within the retries.
+    async fn fetch_bf_secret(client: &http::Client, config: &Config) -> Result<Key> {
+        let mut attempt = 0;
+        loop {
+            let result =
+                tokio::time::timeout(config.startup_timeout, Config::try_key(client)).await;
+            match result.map_err(Error::from).and_then(|key| key) {
+                Ok(key) => break Ok(key),
+                Err(e) if e.is_transient() && attempt < config.startup_retries => {
+                    attempt += 1;
+                    warn!(
+                        "failed to fetch bf_secret: {e}; retrying (attempt {attempt} of {})",
+                        config.startup_retries
+                    );
+                    tokio::time::sleep(Self::STARTUP_RETRY_DELAY).await;
+                }
+                Err(e) => break Err(e),
+            }
+        }
+    }
+
+    /// Returns whether the `bf_secret` matches the known checksum.
+    fn is_valid_bf_secret(bf_secret: &Key) -> bool {
+        format!("{:x}", Md5::digest(**bf_secret)) == Config::BF_SECRET_MD5
+    }
+
+    /// Fetches the `bf_secret` from the web player, falling back to the cache.
+    ///
+    /// A secret that matches the known checksum is preferred, whether fetched
+    /// or cached. When Deezer rotates the secret, the web player has the new
+    /// one while the checksum is outdated, so an unverified secret is used
+    /// when no valid one is available. A fetched secret is written to the
+    /// cache file, if configured, unless that would replace a valid secret
+    /// with an unverified one.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the secret cannot be fetched and none is cached.
+    async fn fetch_or_cached_bf_secret(
+        client: &http::Client,
+        config: &Config,
+    ) -> Result<(Key, KeySource)> {
+        let cache = config.bf_secret_cache.as_deref();
+        let fetched = Self::fetch_bf_secret(client, config).await;
+
+        if let Ok(secret) = &fetched
+            && Self::is_valid_bf_secret(secret)
+        {
+            if let Some(path) = cache {
+                Self::write_cached_bf_secret(path, secret).await;
+            }
+            return Ok((*secret, KeySource::WebPlayer));
+        }
+
+        let cached = match cache {
+            Some(path) => Self::read_cached_bf_secret(path).await,
+            None => None,
+        };
+
+        match (fetched, cached) {
+            (Ok(_), Some(secret)) if Self::is_valid_bf_secret(&secret) => {
+                warn!(
+                    "the bf_secret from the web player has an unknown checksum; using the cached one"
+                );
+                Ok((secret, KeySource::Cached))
+            }
+            (Err(e), Some(secret)) if Self::is_valid_bf_secret(&secret) => {
+                warn!("failed to fetch bf_secret: {e}; using the cached one");
+                Ok((secret, KeySource::Cached))
+            }
+            (Ok(secret), _) => {
+                warn!(
+                    "the bf_secret from the web player has an unknown checksum, it may have been rotated"
+                );
+                if let Some(path) = cache {
+                    Self::write_cached_bf_secret(path, &secret).await;
+                }
+                Ok((secret, KeySource::WebPlayer))
+            }
+            (Err(e), Some(secret)) => {
+                warn!(
+                    "failed to fetch bf_secret: {e}; using the cached one with an unknown checksum"
+                );
+                Ok((secret, KeySource::Cached))
+            }
+            (Err(e), None) => Err(e),
+        }
+    }
+
+    /// Reads the `bf_secret` from the cache file.
+    ///
+    /// Returns `None` if the file does not exist or holds no valid key.
+    async fn read_cached_bf_secret(path: &Path) -> Option<Key> {
+        let contents = match tokio::fs::read_to_string(path).await {
+            Ok(contents) => contents,
+            Err(e) => {
+                debug!("no bf_secret cached in {}: {e}", path.display());
+                return None;
+            }
+        };
+
+        match contents.trim().parse::<Key>() {
+            Ok(secret) => Some(secret),
+            Err(e) => {
+                warn!("invalid bf_secret cached in {}: {e}", path.display());
+                None
+            }
+        }
+    }
+
+    /// Writes the `bf_secret` to the cache file.
+    ///
+    /// The file is created readable and writable by the owner only. Failures
+    /// are logged and otherwise ignored.
+    async fn write_cached_bf_secret(path: &Path, secret: &Key) {
+        use tokio::io::AsyncWriteExt;
+
+        let mut options = tokio::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+
+        let result = async {
+            let mut file = options.open(path).await?;
+            file.write_all(&**secret).await?;
+            file.flush().await
+        }
+        .await;
+
+        if let Err(e) = result {
+            warn!("failed to cache bf_secret in {}: {e}", path.display());
+        }
+    }
+
     /// Creates a new player instance.
     ///
     /// # Arguments
@@ -315,22 +682,37 @@ impl Player {
     ///
     /// Returns error if:
     /// * HTTP client creation fails
-    /// * Decryption key is invalid
+    /// * Decryption key cannot be fetched from the web player or the cache
     pub async fn new(config: &Config, device: &str) -> Result<Self> {
         let client = http::Client::without_cookies(config)?;
 
-        let bf_secret = if let Some(secret) = config.bf_secret {
-            secret
-        } else {
-            debug!("no bf_secret specified, fetching one from the web player");
-            Config::try_key(&client).await?
+        let (bf_secret, bf_secret_source) = match config.bf_secret {
+            Some(secret) if Self::is_valid_bf_secret(&secret) => (secret, KeySource::Configured),
+            Some(_) => {
+                // The configured secret may be outdated, so try the one that
+                // the web player currently uses before giving up.
+                warn!("the configured bf_secret is not valid, fetching one from the web player");
+                let fetched = Self::fetch_or_cached_bf_secret(&client, config).await?;
+                warn!(
+                    "using the bf_secret from the {}; update or remove the configured one",
+                    fetched.1
+                );
+                fetched
+            }
+            None => {
+                debug!("no bf_secret specified, fetching one from the web player");
+                Self::fetch_or_cached_bf_secret(&client, config).await?
+            }
         };
 
-        if format!("{:x}", Md5::digest(*bf_secret)) == Config::BF_SECRET_MD5 {
-            decrypt::set_bf_secret(bf_secret)?;
-        } else {
-            return Err(Error::permission_denied("the bf_secret is not valid"));
-        }
+        // Log only a prefix of the checksum, which is enough to tell secrets
+        // apart without revealing them.
+        let checksum = format!("{:x}", Md5::digest(*bf_secret));
+        info!(
+            "using bf_secret from {bf_secret_source} (md5 {}...)",
+            &checksum[..Self::BF_SECRET_MD5_PREFIX_LEN]
+        );
+        decrypt::set_bf_secret(bf_secret)?;
 
         #[expect(clippy::cast_possible_truncation)]
         let gain_target_db = gateway::user_data::Gain::default().target as i8;
@@ -339,8 +721,19 @@ impl Player {
         let volume = Percentage::from_ratio(dithered_volume.volume());
 
         Ok(Self {
+            bf_secret_source,
             queue: Vec::new(),
             skip_tracks: HashSet::new(),
+            unavailable_count: 0,
+            failed_loads: HashMap::new(),
+            load_failures: config.load_failures.max(1),
+            redownloaded_tracks: HashSet::new(),
+            license_retried: HashSet::new(),
+            license_pending: false,
+            verify_downloads: config.verify_downloads,
+            allow_tracks: config.allow_tracks.iter().copied().collect(),
+            deny_tracks: config.deny_tracks.iter().copied().collect(),
+            skip_explicit: config.skip_explicit,
             position: 0,
             audio_quality: AudioQuality::default(),
             client,
@@ -348,59 +741,87 @@ impl Player {
             media_url: MediaUrl::default().into(),
             repeat_mode: RepeatMode::default(),
             normalization: config.normalization,
+            normalization_types: config.normalization_types.iter().copied().collect(),
             loudness: config.loudness,
+            fade_in: config.fade_in,
+            skip_fade: config.skip_fade,
+            fading_through_silence: false,
+            livestream_codec: config.livestream_codec,
+            gapless_codecs: config.gapless_codecs.iter().copied().collect(),
+            log_sources: config.log_sources,
+            max_livestream_kbps: config.max_livestream_kbps,
+            quality_overrides: config.quality_overrides.clone(),
+            replay_gain_dir: config.replay_gain_dir.clone(),
             gain_target_db,
+            loudness_target: config.loudness_target,
             volume,
+            duck_ratio: UNITY_GAIN,
             dithered_volume,
             dither_bits: config.dither_bits,
+            dither_threshold: config.dither_threshold,
             noise_shaping: config.noise_shaping,
+            clip_detection: config.clip_detection,
             event_tx: None,
             playing_since: Duration::ZERO,
             deferred_seek: None,
             current_rx: None,
-            preload_rx: None,
+            preload_rx: VecDeque::new(),
+            preload_depth: config.preload_depth,
+            max_queued_sources: config.max_queued_sources,
+            audio_priority: config.audio_priority,
+            next_buffered: false,
             preload_start: Duration::ZERO,
             device: device.to_owned(),
+            device_rate: None,
+            output_config: None,
+            reopen_on_rate_change: config.reopen_on_rate_change,
+            idle_timeout: config.idle_timeout,
+            metrics: Arc::new(Metrics::new()),
+            metrics_logged_at: std::time::Instant::now(),
+            paused_since: None,
+            position_timeout: config.position_timeout,
+            out_of_range_since: None,
+            idle_released: false,
+            verify_device: config.verify_device,
+            device_name: None,
             sink: None,
             stream: None,
             stream_error_rx: None,
             sources: None,
             max_ram: config.max_ram,
+            ram_threshold: config.ram_threshold,
+            device_timeout: config.device_timeout,
+            livestream_retries: config.livestream_retries,
+            zero_byte_retries: config.zero_byte_retries,
+            max_download_kbps: config.max_download_kbps,
+            livestream_retry_delay: config.livestream_retry_delay,
+            livestream_live_edge: config.livestream_live_edge,
+            livestream_attempts: 0,
+            retry_at: None,
+            extendable: false,
+            resample_unsupported: config.resample_unsupported,
+            min_volume: config.min_volume.unwrap_or(Percentage::ZERO),
         })
     }
 
-    /// Selects and configures an audio output device.
+    /// Resolves an audio host and output device by name.
     ///
     /// # Arguments
     ///
-    /// * `device` - Device specification string in format:
-    ///   ```text
-    ///   [<host>][|<device>][|<sample rate>][|<sample format>]
-    ///   ```
-    ///   All parts are optional. Use empty string for system default.
-    ///
-    /// # Returns
-    ///
-    /// Returns the selected device and its configuration.
+    /// * `host` - Host name (case-insensitive), or empty or `None` for the default host
+    /// * `device` - Device name (case-insensitive), or empty or `None` for the
+    ///   default output device of the host
     ///
     /// # Errors
     ///
     /// Returns error if:
     /// * Host is not found
     /// * Device is not found
-    /// * Sample rate is invalid
-    /// * Sample format is not supported
-    /// * Device cannot be acquired (e.g., in use by another application)
-    #[expect(clippy::too_many_lines)]
-    fn get_device(device: &str) -> Result<(rodio::Device, rodio::SupportedStreamConfig)> {
-        // The device string has the following format:
-        // "[<host>][|<device>][|<sample rate>][|<sample format>]" (case-insensitive)
-        // From left to right, the fields are optional, but each field
-        // depends on the preceding fields being specified.
-        let mut components = device.split('|');
-
-        // The host is the first field.
-        let host = match components.next() {
+    fn resolve_device(
+        host: Option<&str>,
+        device: Option<&str>,
+    ) -> Result<(cpal::Host, rodio::Device)> {
+        let host = match host {
             Some("") | None => cpal::default_host(),
             Some(name) => {
                 let host_ids = cpal::available_hosts();
@@ -419,7 +840,7 @@ impl Player {
         };
 
         // The device is the second field.
-        let device = match components.next() {
+        let device = match device {
             Some("") | None => host.default_output_device().ok_or_else(|| {
                 Error::not_found(format!(
                     "default audio output device not found on {}",
@@ -439,6 +860,40 @@ impl Player {
             }
         };
 
+        Ok((host, device))
+    }
+
+    /// Selects and configures an audio output device.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - Device specification string in format:
+    ///   ```text
+    ///   [<host>][|<device>][|<sample rate>][|<sample format>]
+    ///   ```
+    ///   All parts are optional. Use empty string for system default.
+    ///
+    /// # Returns
+    ///
+    /// Returns the selected device and its configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// * Host is not found
+    /// * Device is not found
+    /// * Sample rate is invalid
+    /// * Sample format is not supported
+    /// * Device cannot be acquired (e.g., in use by another application)
+    #[expect(clippy::too_many_lines)]
+    fn get_device(device: &str) -> Result<(rodio::Device, rodio::SupportedStreamConfig)> {
+        // The device string has the following format:
+        // "[<host>][|<device>][|<sample rate>][|<sample format>]" (case-insensitive)
+        // From left to right, the fields are optional, but each field
+        // depends on the preceding fields being specified.
+        let mut components = device.split('|');
+        let (host, device) = Self::resolve_device(components.next(), components.next())?;
+
         let rate = match components.next() {
             Some("") | None => None,
             Some(rate) => Some(
@@ -565,6 +1020,51 @@ impl Player {
         Ok((device, config))
     }
 
+    /// Waits for the configured audio output device to appear.
+    ///
+    /// Retries with exponential backoff while the device is not found, until
+    /// `device_timeout` has elapsed. This handles devices that become available
+    /// late, like USB DACs that enumerate after boot. Other errors are
+    /// returned immediately.
+    ///
+    /// Returns right away if the device is already open. Call this before
+    /// opening the device from an async context, for example with `play()`,
+    /// which does not wait itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the device is not found within the timeout, or if
+    /// the device specification is invalid.
+    pub async fn wait_for_device(&self) -> Result<()> {
+        const MIN_BACKOFF: Duration = Duration::from_millis(100);
+        const MAX_BACKOFF: Duration = Duration::from_secs(2);
+
+        if self.is_started() {
+            return Ok(());
+        }
+
+        let started = tokio::time::Instant::now();
+        let mut backoff = MIN_BACKOFF;
+        loop {
+            match Self::get_device(&self.device_spec()).map(|_| ()) {
+                Err(e) if e.kind == ErrorKind::NotFound => {
+                    let remaining = self.device_timeout.saturating_sub(started.elapsed());
+                    if remaining.is_zero() {
+                        return Err(e);
+                    }
+
+                    debug!(
+                        "{e}; retrying for another {:.0}s",
+                        remaining.as_secs_f32().ceil()
+                    );
+                    tokio::time::sleep(backoff.min(remaining)).await;
+                    backoff = backoff.saturating_mul(2).min(MAX_BACKOFF);
+                }
+                result => return result,
+            }
+        }
+    }
+
     /// Opens and configures the audio output device for playback if not already open.
     ///
     /// Called internally when needed (e.g., by `play()`) to initialize the audio device.
@@ -591,12 +1091,21 @@ impl Player {
         // Create a channel for stream error notifications.
         let (stream_error_tx, stream_error_rx) = tokio::sync::mpsc::unbounded_channel();
         self.stream_error_rx = Some(stream_error_rx);
+        let stream_metrics = Arc::clone(&self.metrics);
         let callback = move |err: cpal::StreamError| {
+            // Underruns are recovered from by the backend, so only count them.
+            if metrics::is_underrun(&err) {
+                stream_metrics.add_underrun();
+                return;
+            }
+
             // Forward the error to the main thread for handling
             let _drop = stream_error_tx.send(err);
         };
 
-        let (device, device_config) = Self::get_device(&self.device)?;
+        let (device, device_config) = Self::get_device(&self.device_spec())?;
+        self.device_name = device.name().ok();
+        self.output_config = Some(device_config.clone());
         let mut stream_handle = rodio::OutputStreamBuilder::default()
             .with_device(device)
             .with_supported_config(&device_config)
@@ -604,6 +1113,17 @@ impl Player {
             .open_stream()?;
 
         stream_handle.log_on_drop(false);
+
+        // The output thread is owned by the audio backend, so raise its priority from
+        // a source that does nothing but that when the mixer pulls it.
+        if let Some(priority) = self.audio_priority {
+            stream_handle
+                .mixer()
+                .add(rodio::source::EmptyCallback::new(Box::new(move || {
+                    raise_thread_priority(priority);
+                })));
+        }
+
         let sink = rodio::Sink::connect_new(stream_handle.mixer());
 
         // Determine the dither bit depth
@@ -637,7 +1157,12 @@ impl Player {
                 };
                 Some(bits)
             })
-            .and_then(|bits| if bits > 0.0 { Some(bits) } else { None });
+            .and_then(|bits| if bits > 0.0 { Some(bits) } else { None })
+            .filter(|bits| {
+                // Keep full precision above the threshold.
+                self.dither_threshold
+                    .is_none_or(|threshold| *bits <= threshold)
+            });
         if let Some(bits) = dither_bits {
             debug!("dithering: {bits} effective number of bits");
         } else {
@@ -646,7 +1171,7 @@ impl Player {
 
         // Set the volume to the last known value. Do not use `self.set_volume` because
         // it will short-circuit when trying to set the volume to what `self.volume` already is.
-        let log_volume = Self::log_volume(self.volume.as_ratio());
+        let log_volume = Self::output_volume(self.volume.as_ratio(), self.min_volume.as_ratio());
         self.dithered_volume = Arc::new(Volume::new(log_volume, dither_bits));
 
         if self.noise_shaping == 0 {
@@ -658,58 +1183,209 @@ impl Player {
         // The output source will output silence when the queue is empty.
         // That will cause the sink to report as "playing", so we need to pause it.
         let (sources, output) = rodio::queue::queue(true);
-        sink.append(output);
+        sink.append(metrics::instrumented(output, Arc::clone(&self.metrics)));
         sink.pause();
 
         self.sink = Some(sink);
         self.sources = Some(sources);
         self.stream = Some(stream_handle);
+        self.idle_released = false;
 
         Ok(())
     }
 
+    /// Returns the configuration of the open output device.
+    ///
+    /// This is the configuration that was negotiated with the device, which
+    /// may differ from what was requested. Returns `None` if the output
+    /// device is not open.
+    #[must_use]
+    pub fn output_config(&self) -> Option<&rodio::SupportedStreamConfig> {
+        self.output_config.as_ref()
+    }
+
+    /// Returns the sample rate of the open output device.
+    #[inline]
+    fn output_rate(&self) -> Option<u32> {
+        self.output_config
+            .as_ref()
+            .map(|config| config.sample_rate().0)
+    }
+
     /// Closes the audio output device and stops playback.
     ///
     /// Releases audio device resources and clears any queued audio.
     /// The player can be restarted with `start()`.
     ///
     /// Note: This method is automatically called when the player is dropped,
-    /// ensuring proper cleanup of audio device resources.
+    /// ensuring proper cleanup of audio device resources. It blocks the
+    /// current thread while fading out; from async contexts, prefer
+    /// [`shutdown`](Self::shutdown).
     pub fn stop(&mut self) {
-        let original_volume = self.ramp_volume(0.0);
+        if self.sink.is_some() {
+            let original_volume = self.ramp_volume(0.0);
+            self.stop_sink();
+
+            // Restore the original volume after stopping the sink, for when the player is restarted.
+            self.ramp_volume(original_volume);
+        }
+
+        self.release_output();
+    }
+
+    /// Closes the audio output device and stops playback without blocking.
+    ///
+    /// Like [`stop`](Self::stop), but fades out cooperatively so that it does
+    /// not stall an async executor. After shutting down, dropping the player
+    /// does not block.
+    pub async fn shutdown(&mut self) {
+        if self.sink.is_some() {
+            let original_volume = self.ramp_volume_async(0.0).await;
+            self.stop_sink();
+
+            // Restore the original volume after stopping the sink, for when the player is restarted.
+            self.ramp_volume_async(original_volume).await;
+        }
+
+        self.release_output();
+    }
 
+    /// Stops the sink, discarding any queued audio.
+    fn stop_sink(&mut self) {
         // Don't care if the sink is already dropped: we're already "stopped".
         if let Ok(sink) = self.sink_mut() {
             debug!("closing output device");
             sink.stop();
         }
+    }
 
-        // Restore the original volume after stopping the sink, for when the player is restarted.
-        self.ramp_volume(original_volume);
-
+    /// Releases the audio output device and its queue.
+    fn release_output(&mut self) {
         self.sources = None;
         self.stream = None;
         self.sink = None;
+        self.output_config = None;
+        self.device_name = None;
+        self.paused_since = None;
     }
 
-    /// The list of sample rates to enumerate.
+    /// Releases the output device after being paused for the idle timeout.
     ///
-    /// Only includes the two most common sample rates in Hz:
-    /// * 44100 - CD audio, most streaming services
-    /// * 48000 - Professional digital audio, video production, many sound cards
-    const SAMPLE_RATES: [u32; 2] = [44_100, 48_000];
+    /// The current track is reloaded at its position when the device is
+    /// opened again, for example by `play()`.
+    fn release_idle_device(&mut self) {
+        info!("releasing output device after being idle");
+        self.release_device();
+        self.idle_released = true;
+    }
 
-    /// The list of sample formats to enumerate.
+    /// Closes the output device, keeping the position of the current track.
     ///
-    /// Only includes the three most common sample formats:
-    /// * I16 - 16-bit signed integer
-    /// * I24 - 24-bit signed integer (stored in 4 bytes)
-    /// * I32 - 32-bit signed integer
-    /// * F32 - 32-bit floating point
-    const SAMPLE_FORMATS: [cpal::SampleFormat; 4] = [
-        cpal::SampleFormat::I16,
-        cpal::SampleFormat::I24,
-        cpal::SampleFormat::I32,
+    /// The current track is reloaded at its position when the device is
+    /// opened again.
+    fn release_device(&mut self) {
+        let position = self.get_pos().saturating_sub(self.playing_since);
+        let resume = self.is_loaded() && !self.track().is_some_and(Track::is_livestream);
+
+        self.clear();
+        self.stop();
+        if resume {
+            self.deferred_seek = Some(position);
+        }
+    }
+
+    /// Returns whether the open output device is no longer the one to use.
+    ///
+    /// The device may change while paused, for example when a Bluetooth
+    /// speaker reconnects as the new default device, or disappear after the
+    /// system was suspended. Returns `false` if no device is open.
+    fn device_changed(&self) -> bool {
+        let Some(opened) = &self.device_name else {
+            return false;
+        };
+
+        let spec = self.device_spec();
+        let mut components = spec.split('|');
+        match Self::resolve_device(components.next(), components.next()) {
+            Ok((_, device)) => match device.name() {
+                Ok(name) if name == *opened => false,
+                Ok(name) => {
+                    info!("output device changed from {opened} to {name}");
+                    true
+                }
+                Err(e) => {
+                    warn!("output device {opened} is no longer available: {e}");
+                    true
+                }
+            },
+            Err(e) => {
+                warn!("output device {opened} is no longer available: {e}");
+                true
+            }
+        }
+    }
+
+    /// Returns the device specification to open the output device with.
+    ///
+    /// Replaces the sample rate of the configured specification when the
+    /// device is reopened at the sample rate of a track.
+    fn device_spec(&self) -> String {
+        match self.device_rate {
+            Some(rate) => {
+                let mut components: Vec<_> = self.device.split('|').map(str::to_string).collect();
+                components.resize(components.len().max(3), String::new());
+                components[2] = rate.to_string();
+                components.join("|")
+            }
+            None => self.device.clone(),
+        }
+    }
+
+    /// Reopens the output device at another sample rate.
+    ///
+    /// Keeps the playback state. When the device does not support the
+    /// sample rate, it is opened as configured again, and tracks are
+    /// resampled instead.
+    fn reopen_device(&mut self, rate: u32) {
+        info!("reopening output device at {rate} Hz");
+        let was_playing = self.sink.as_ref().is_some_and(|sink| !sink.is_paused());
+
+        self.stop();
+        self.device_rate = Some(rate);
+        if let Err(e) = self.start() {
+            warn!("failed to open output device at {rate} Hz: {e}; resampling instead");
+            self.device_rate = None;
+            if let Err(e) = self.start() {
+                error!("failed to reopen output device: {e}");
+                return;
+            }
+        }
+
+        // The new sink starts counting from zero.
+        self.playing_since = Duration::ZERO;
+        if was_playing && let Ok(sink) = self.sink_mut() {
+            sink.play();
+        }
+    }
+
+    /// The list of sample rates to enumerate.
+    ///
+    /// Only includes the two most common sample rates in Hz:
+    /// * 44100 - CD audio, most streaming services
+    /// * 48000 - Professional digital audio, video production, many sound cards
+    const SAMPLE_RATES: [u32; 2] = [44_100, 48_000];
+
+    /// The list of sample formats to enumerate.
+    ///
+    /// Only includes the three most common sample formats:
+    /// * I16 - 16-bit signed integer
+    /// * I24 - 24-bit signed integer (stored in 4 bytes)
+    /// * I32 - 32-bit signed integer
+    /// * F32 - 32-bit floating point
+    const SAMPLE_FORMATS: [cpal::SampleFormat; 4] = [
+        cpal::SampleFormat::I16,
+        cpal::SampleFormat::I24,
+        cpal::SampleFormat::I32,
         cpal::SampleFormat::F32,
     ];
 
@@ -730,7 +1406,8 @@ impl Player {
     ///   - F32 (32-bit float)
     /// * Stereo output (2 channels)
     ///
-    /// Default device is marked with "(default)" suffix.
+    /// Use [`describe_sample_format`](Self::describe_sample_format) for the
+    /// human-readable bit depth of each specification.
     ///
     /// Note: Other device configurations can still be used by explicitly
     /// specifying them in the device string passed to `new()`.
@@ -740,37 +1417,79 @@ impl Player {
     /// A vector of device specification strings, as sorted by the host.
     #[must_use]
     pub fn enumerate_devices() -> Vec<String> {
-        let hosts = cpal::available_hosts();
-        let mut result = Vec::new();
-
-        // Enumerate all available hosts, devices and configs.
-        for host in hosts
+        cpal::available_hosts()
             .into_iter()
             .filter_map(|id| cpal::host_from_id(id).ok())
-        {
-            if let Ok(devices) = host.output_devices() {
-                for device in devices {
-                    if let Ok(device_name) = device.name()
-                        && let Ok(configs) = device.supported_output_configs()
-                    {
-                        for config in configs {
-                            if config.channels() == 2
-                                && Self::SAMPLE_FORMATS.contains(&config.sample_format())
-                            {
-                                for sample_rate in &Self::SAMPLE_RATES {
-                                    if let Some(config) =
-                                        config.try_with_sample_rate(cpal::SampleRate(*sample_rate))
-                                    {
-                                        let line = format!(
-                                            "{}|{}|{}|{}",
-                                            host.id().name(),
-                                            device_name,
-                                            config.sample_rate().0,
-                                            config.sample_format(),
-                                        );
-
-                                        result.push(line);
-                                    }
+            .flat_map(|host| Self::enumerate_host_devices(&host))
+            .collect()
+    }
+
+    /// Lists available audio hosts.
+    ///
+    /// Only hosts that can be initialized are returned. The names can be
+    /// passed to [`enumerate_devices_for_host`](Self::enumerate_devices_for_host)
+    /// and used as the host part of a device specification string.
+    ///
+    /// # Returns
+    ///
+    /// A vector of host names, as sorted by the audio backend.
+    #[must_use]
+    pub fn enumerate_hosts() -> Vec<String> {
+        cpal::available_hosts()
+            .into_iter()
+            .filter(|id| cpal::host_from_id(*id).is_ok())
+            .map(|id| id.name().to_string())
+            .collect()
+    }
+
+    /// Lists available audio output devices of a single host.
+    ///
+    /// Enumerates the same configurations as [`enumerate_devices`](Self::enumerate_devices),
+    /// for the host with the given name (case-insensitive).
+    ///
+    /// # Returns
+    ///
+    /// A vector of device specification strings, as sorted by the host.
+    /// Empty if the host is not available.
+    #[must_use]
+    pub fn enumerate_devices_for_host(host: &str) -> Vec<String> {
+        cpal::available_hosts()
+            .into_iter()
+            .find(|id| id.name().eq_ignore_ascii_case(host))
+            .and_then(|id| cpal::host_from_id(id).ok())
+            .map(|host| Self::enumerate_host_devices(&host))
+            .unwrap_or_default()
+    }
+
+    /// Lists stereo 44.1/48 kHz output configurations of a host.
+    ///
+    /// Returns device specification strings in the format
+    /// `<host>|<device>|<sample rate>|<sample format>`.
+    fn enumerate_host_devices(host: &cpal::Host) -> Vec<String> {
+        let mut result = Vec::new();
+
+        if let Ok(devices) = host.output_devices() {
+            for device in devices {
+                if let Ok(device_name) = device.name()
+                    && let Ok(configs) = device.supported_output_configs()
+                {
+                    for config in configs {
+                        if config.channels() == 2
+                            && Self::SAMPLE_FORMATS.contains(&config.sample_format())
+                        {
+                            for sample_rate in &Self::SAMPLE_RATES {
+                                if let Some(config) =
+                                    config.try_with_sample_rate(cpal::SampleRate(*sample_rate))
+                                {
+                                    let line = format!(
+                                        "{}|{}|{}|{}",
+                                        host.id().name(),
+                                        device_name,
+                                        config.sample_rate().0,
+                                        config.sample_format(),
+                                    );
+
+                                    result.push(line);
                                 }
                             }
                         }
@@ -782,6 +1501,89 @@ impl Player {
         result
     }
 
+    /// Lists all output configurations supported by a device.
+    ///
+    /// Unlike [`enumerate_devices`](Self::enumerate_devices), this does not
+    /// filter on channels, sample rates or sample formats, so users can build
+    /// a device specification string for any supported configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - Device specification string in format `[<host>][|<device>]`;
+    ///   any further fields are ignored
+    ///
+    /// # Returns
+    ///
+    /// A vector of configuration descriptions with the channel count, sample
+    /// rate range and sample format including its bit depth.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// * Host is not found
+    /// * Device is not found
+    /// * Device configurations cannot be queried
+    pub fn enumerate_formats(device: &str) -> Result<Vec<String>> {
+        let mut components = device.split('|');
+        let (_, device) = Self::resolve_device(components.next(), components.next())?;
+
+        let result = device
+            .supported_output_configs()?
+            .map(|config| {
+                let channels = match config.channels() {
+                    1 => "mono".to_string(),
+                    2 => "stereo".to_string(),
+                    channels => format!("{channels} channels"),
+                };
+
+                format!(
+                    "{channels}, {}-{} Hz, {} ({})",
+                    config.min_sample_rate().0,
+                    config.max_sample_rate().0,
+                    config.sample_format(),
+                    Self::describe_format(config.sample_format()),
+                )
+            })
+            .collect();
+
+        Ok(result)
+    }
+
+    /// Describes the sample format of a device specification string.
+    ///
+    /// # Returns
+    ///
+    /// A human-readable bit depth like "24-bit integer", or `None` if the
+    /// specification does not end with a known sample format.
+    #[must_use]
+    pub fn describe_sample_format(device: &str) -> Option<String> {
+        let format = device.rsplit('|').next()?;
+        Self::SAMPLE_FORMATS
+            .into_iter()
+            .find(|sample_format| sample_format.to_string().eq_ignore_ascii_case(format))
+            .map(Self::describe_format)
+    }
+
+    /// Returns the bit depth and type of a sample format, like "16-bit integer".
+    fn describe_format(format: cpal::SampleFormat) -> String {
+        // 24-bit samples are stored in 4 bytes.
+        let bits = if format == cpal::SampleFormat::I24 {
+            24
+        } else {
+            format.sample_size() * 8
+        };
+
+        let kind = if format.is_float() {
+            "float"
+        } else if format.is_uint() {
+            "unsigned integer"
+        } else {
+            "integer"
+        };
+
+        format!("{bits}-bit {kind}")
+    }
+
     /// Advances to the next track in the queue.
     ///
     /// Handles:
@@ -793,6 +1595,9 @@ impl Player {
     /// * `None`: Stops at end of queue
     /// * `One`: Stays on current track
     /// * `All`: Loops back to start of queue
+    ///
+    /// Extendable queues never loop back: the position moves past the end
+    /// and playback resumes when the queue is extended.
     fn go_next(&mut self) {
         let old_position = self.position;
         let repeat_mode = self.repeat_mode();
@@ -801,6 +1606,11 @@ impl Player {
             if next < self.queue.len() {
                 // Move to the next track.
                 self.position = next;
+            } else if self.extendable {
+                // Reached the end of an open-ended queue: move past the end and
+                // wait for the queue to be extended, regardless of repeat mode.
+                debug!("reached end of extendable queue");
+                self.position = next;
             } else {
                 // Reached the end of the queue: rewind to the beginning.
                 self.set_position(0);
@@ -813,6 +1623,7 @@ impl Player {
         }
 
         if self.position() != old_position {
+            self.livestream_attempts = 0;
             self.dithered_volume
                 .set_track_bit_depth(self.track().and_then(|track| track.bits_per_sample));
             self.preload_start = self.calc_preload_start(self.track().and_then(Track::duration));
@@ -850,6 +1661,36 @@ impl Player {
     /// Used when calculating normalization from `ReplayGain` metadata.
     const REPLAY_GAIN_LUFS: i8 = -18;
 
+    /// Reads the `ReplayGain` of a track from a sidecar file.
+    ///
+    /// The file is named after the track ID with a `.txt` extension, and
+    /// contains the gain in dB, optionally followed by "dB". Returns `None`
+    /// when the file does not exist or cannot be parsed.
+    async fn read_replay_gain(dir: &Path, track_id: TrackId) -> Option<f32> {
+        let path = dir.join(format!("{track_id}.txt"));
+        let contents = match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => contents,
+            Err(e) => {
+                trace!("no replay gain sidecar at {}: {e}", path.display());
+                return None;
+            }
+        };
+
+        let value = contents.trim();
+        let value = value
+            .strip_suffix("dB")
+            .or_else(|| value.strip_suffix("db"))
+            .unwrap_or(value)
+            .trim();
+        match value.parse::<f32>() {
+            Ok(gain) if gain.is_finite() => Some(gain),
+            _ => {
+                warn!("invalid replay gain in {}: {value}", path.display());
+                None
+            }
+        }
+    }
+
     /// Loads and prepares a track for playback.
     ///
     /// Downloads and configures audio processing:
@@ -884,11 +1725,16 @@ impl Player {
         // The current RAM usage is determined by the current track's file size, if that would fit
         // within the maximum allowed RAM. Otherwise, the current track is stored in a temporary
         // file.
-        let mut ram_usage = self.track().and_then(Track::file_size).unwrap_or(0);
-        if let Some(max_ram) = self.max_ram
-            && ram_usage > max_ram
-        {
-            ram_usage = 0;
+        // With deeper preloading, the same goes for every track loaded before this one.
+        let mut ram_usage = 0;
+        for loaded in self.queue.iter().take(position).skip(self.position) {
+            let file_size = loaded.file_size().unwrap_or(0);
+            if self
+                .max_ram
+                .is_none_or(|max_ram| ram_usage.saturating_add(file_size) <= max_ram)
+            {
+                ram_usage += file_size;
+            }
         }
 
         let track = self
@@ -901,60 +1747,122 @@ impl Player {
             .as_mut()
             .ok_or_else(|| Error::unavailable("audio sources not available"))?;
 
-        if track.handle().is_none() {
-            let download = tokio::time::timeout(Self::NETWORK_TIMEOUT, async {
-                // Start downloading the track.
-                let medium = track
-                    .get_medium(
-                        &self.client,
-                        &self.media_url,
-                        self.audio_quality,
-                        self.license_token.clone(),
-                    )
-                    .await?;
-
-                // The default buffer size is determined by the track's prefetch size. This is
-                // overridden with the available RAM, if the maximum RAM was configured and the
-                // track is not a livestream.
-                let mut buffer_size = track.prefetch_size();
-                if let Some(max_ram) = self.max_ram
-                    && !track.is_livestream()
-                {
-                    let ram_left = max_ram
-                        .saturating_sub(ram_usage)
-                        .try_into()
-                        .unwrap_or(usize::MAX);
+        if !track.is_opened() {
+            // Local tracks are read directly, without network access.
+            let download = if track.is_local() {
+                track.open_local()?
+            } else {
+                tokio::time::timeout(Self::NETWORK_TIMEOUT, async {
+                    // Start downloading the track.
+                    let medium = track
+                        .get_medium(
+                            &self.client,
+                            &self.media_url,
+                            self.audio_quality,
+                            &self.quality_overrides,
+                            self.livestream_codec,
+                            self.max_livestream_kbps,
+                            self.license_token.clone(),
+                        )
+                        .await?;
+
+                    // The default buffer size is determined by the track's prefetch size. This is
+                    // overridden with the RAM threshold and the available RAM, whichever is lower,
+                    // if either was configured and the track is not a livestream.
+                    let mut buffer_size = track.prefetch_size();
+                    let in_ram = self.max_ram.is_some() || self.ram_threshold.is_some();
+                    if in_ram && !track.is_livestream() {
+                        let mut threshold = self.ram_threshold.map_or(usize::MAX, |threshold| {
+                            threshold.try_into().unwrap_or(usize::MAX)
+                        });
+
+                        if let Some(max_ram) = self.max_ram {
+                            let ram_left = max_ram
+                                .saturating_sub(ram_usage)
+                                .try_into()
+                                .unwrap_or(usize::MAX);
+
+                            debug!(
+                                "memory reserved before start of download: {} KB, left: {} KB",
+                                ram_usage / 1024,
+                                ram_left / 1024
+                            );
 
-                    debug!(
-                        "memory reserved before start of download: {} KB, left: {} KB",
-                        ram_usage / 1024,
-                        ram_left / 1024
-                    );
+                            threshold = threshold.min(ram_left);
+                        }
+
+                        // never go below the prefetch size that was set before
+                        if threshold > buffer_size {
+                            buffer_size = threshold;
+                        }
+                    }
 
-                    // never go below the prefetch size that was set before
-                    if ram_left > buffer_size {
-                        buffer_size = ram_left;
+                    // This will set up the storage as follows:
+                    // - livestreams: stored in RAM, bounded by the prefetch size
+                    // - non-livestreams, no maximum RAM or threshold set: stored in temporary files
+                    // - non-livestreams, maximum RAM or threshold set: stored in RAM if within the
+                    // threshold and the RAM left, or temporary files otherwise
+                    let storage = AdaptiveStorageProvider::with_fixed_and_variable(
+                        MemoryStorageProvider,
+                        TempStorageProvider::default(),
+                        buffer_size
+                            .try_into()
+                            .map_err(|e| Error::internal(format!("prefetch size error: {e}")))?,
+                    );
+                    let download = track
+                        .start_download(
+                            &self.client,
+                            &medium,
+                            storage,
+                            self.zero_byte_retries,
+                            self.max_download_kbps,
+                        )
+                        .await?;
+
+                    if self.log_sources && track.is_external() {
+                        info!(
+                            "sources of {} {track}: {}; selected {} ({} {} kbps)",
+                            track.typ(),
+                            track.external_sources().join(", "),
+                            track.source_host().unwrap_or("unknown host"),
+                            track
+                                .codec()
+                                .map_or("unknown codec".to_string(), |codec| codec.to_string()),
+                            track
+                                .bitrate()
+                                .map_or("unknown".to_string(), |kbps| kbps.to_string()),
+                        );
                     }
-                }
 
-                // This will set up the storage as follows:
-                // - livestreams: stored in RAM, bounded by the prefetch size
-                // - non-livestreams, no maximum RAM set: stored in temporary files
-                // - non-livestreams, maximum RAM set: stored in RAM if the RAM left is sufficient,
-                // or temporary files otherwise
-                let storage = AdaptiveStorageProvider::with_fixed_and_variable(
-                    MemoryStorageProvider,
-                    TempStorageProvider::default(),
-                    buffer_size
-                        .try_into()
-                        .map_err(|e| Error::internal(format!("prefetch size error: {e}")))?,
-                );
-                track.start_download(&self.client, &medium, storage).await
-            })
-            .await??;
+                    // Record the buffer decisions, following the storage setup above.
+                    let in_memory = track.is_livestream()
+                        || (in_ram
+                            && track.file_size().is_some_and(|file_size| {
+                                usize::try_from(file_size).is_ok_and(|size| size <= buffer_size)
+                            }));
+                    let storage = if in_memory {
+                        Storage::Memory
+                    } else {
+                        Storage::TempFile
+                    };
+                    debug!(
+                        "{} {track}: buffer size {} KB, stored in {storage}",
+                        track.typ(),
+                        buffer_size / 1024
+                    );
+                    track.buffer_size = Some(buffer_size);
+                    track.storage = Some(storage);
+
+                    Ok::<_, Error>(download)
+                })
+                .await??
+            };
 
             // Create a new decoder for the track.
-            let mut decoder = Decoder::new(track, download)?;
+            let gapless = track
+                .codec()
+                .is_none_or(|codec| self.gapless_codecs.contains(&codec));
+            let mut decoder = Decoder::new(track, download, gapless)?;
             track.sample_rate = Some(decoder.sample_rate());
             track.channels = Some(decoder.channels());
             if let Some(bits_per_sample) = decoder.bits_per_sample() {
@@ -962,6 +1870,9 @@ impl Player {
             }
 
             // Seek to the deferred position if set.
+            let resumed = self
+                .deferred_seek
+                .is_some_and(|progress| !progress.is_zero());
             if let Some(progress) = self.deferred_seek.take() {
                 // Set the track position only if `progress` is beyond the track start. We start
                 // at the beginning anyway, and this prevents decoder errors.
@@ -972,75 +1883,132 @@ impl Player {
                 }
             }
 
+            // Match the output device to the sample rate of the track, if enabled. This is only
+            // possible for the current track: preloaded tracks play gaplessly on the device that
+            // is already open.
+            let rate = decoder.sample_rate();
+            if self.reopen_on_rate_change
+                && position == self.position
+                && self
+                    .output_rate()
+                    .is_some_and(|output_rate| output_rate != rate)
+            {
+                self.reopen_device(rate);
+            }
+
+            // Reopening the device borrows the whole player, so borrow these again.
+            let track = self.queue.get_mut(position).ok_or_else(|| {
+                Error::not_found(format!("track at position {position} not found"))
+            })?;
+            let sources = self
+                .sources
+                .as_mut()
+                .ok_or_else(|| Error::unavailable("audio sources not available"))?;
+
+            // Estimate the track loudness from the Deezer gain, or else `ReplayGain` metadata,
+            // or else a `ReplayGain` sidecar file.
+            let mut replay_gain = decoder.replay_gain();
+            if track.gain().is_none()
+                && replay_gain.is_none()
+                && let Some(dir) = self.replay_gain_dir.as_deref()
+            {
+                replay_gain = Self::read_replay_gain(dir, track.id()).await;
+            }
+            track.loudness = track.gain().or_else(|| {
+                replay_gain.map(|replay_gain| {
+                    debug!("track replay gain: {replay_gain:.1} dB");
+                    f32::from(Self::REPLAY_GAIN_LUFS) - replay_gain
+                })
+            });
+
             // Apply volume normalization if enabled.
             let mut difference = 0.0;
-            if self.normalization {
-                match track.gain() {
-                    Some(gain) => difference = f32::from(self.gain_target_db) - gain,
+            if self.normalization && self.normalization_types.contains(&track.typ()) {
+                match track.loudness {
+                    Some(track_lufs) => difference = f32::from(self.gain_target_db) - track_lufs,
                     None => {
-                        if let Some(replay_gain) = decoder.replay_gain() {
-                            debug!("track replay gain: {replay_gain:.1} dB");
-                            let track_lufs = f32::from(Self::REPLAY_GAIN_LUFS) - replay_gain;
-                            difference = f32::from(self.gain_target_db) - track_lufs;
-                        } else {
-                            warn!(
-                                "{} {track} has no gain information, skipping normalization",
-                                track.typ()
-                            );
-                        }
+                        warn!(
+                            "{} {track} has no gain information, skipping normalization",
+                            track.typ()
+                        );
                     }
                 }
             }
 
             let lufs_target = if self.loudness {
-                Some(self.gain_target_db.into())
+                Some(self.loudness_target().into())
             } else {
                 None
             };
 
-            let rx = if 2.0 * difference.abs() <= f32::EPSILON * difference.abs() {
-                // No normalization needed, just append the decoder.
-                sources.append_with_signal(dither::dithered_volume(
-                    decoder,
-                    self.dithered_volume.clone(),
-                    lufs_target,
-                    self.noise_shaping,
-                ))
+            // Resample to the rate of the output device before processing, so that dithering and
+            // noise shaping apply at the output rate. Otherwise, resample rates that noise shaping
+            // is not available for, if enabled.
+            let resample_rate = if let Some(output_rate) = self
+                .output_config
+                .as_ref()
+                .map(|config| config.sample_rate().0)
+                && output_rate != rate
+            {
+                debug!("resampling {rate} Hz to {output_rate} Hz of the output device");
+                Some(output_rate)
+            } else if self.noise_shaping == 0 || dither::NOISE_SHAPING_SAMPLE_RATES.contains(&rate)
+            {
+                None
+            } else if self.resample_unsupported {
+                let target = Self::SAMPLE_RATES
+                    .into_iter()
+                    .min_by_key(|target| target.abs_diff(rate))
+                    .unwrap_or(DEFAULT_SAMPLE_RATE);
+                info!("resampling {rate} Hz to {target} Hz for noise shaping");
+                Some(target)
             } else {
-                let ratio = db_to_linear(difference);
-                let amplified = decoder.amplify(ratio);
-                if difference < 1.0 {
-                    debug!(
-                        "normalizing {} {track} by {difference:.1} dB ({})",
-                        track.typ(),
-                        Percentage::from_ratio(ratio)
-                    );
+                info!("playing {rate} Hz without noise shaping; not resampling");
+                None
+            };
 
-                    sources.append_with_signal(dither::dithered_volume(
-                        amplified,
-                        self.dithered_volume.clone(),
-                        lufs_target,
-                        self.noise_shaping,
-                    ))
-                } else {
-                    debug!(
-                        "normalizing {} {track} by {difference:.1} dB ({}) with dynamic limiting",
-                        track.typ(),
-                        Percentage::from_ratio(ratio)
-                    );
+            // Livestreams may change their sample rate mid-stream. Resample them to a fixed rate,
+            // so that the rest of the chain is not built for the wrong rate.
+            let resample_rate = resample_rate.or_else(|| track.is_livestream().then_some(rate));
+
+            // Fade in from the start of the track, if enabled. This is separate from the ramp
+            // that prevents popping when starting or pausing playback.
+            // A manual skip fades in over the skip fade instead.
+            let fade_in = if std::mem::take(&mut self.fading_through_silence) {
+                self.skip_fade
+            } else {
+                self.fade_in
+            };
+            let decoder: Box<dyn Source<Item = SampleFormat> + Send> = match fade_in {
+                Some(duration) if !resumed => Box::new(decoder.fade_in(duration)),
+                _ => Box::new(decoder),
+            };
 
-                    let limiter = LimitSettings::default()
-                        .with_threshold(Self::NORMALIZE_THRESHOLD_DB)
-                        .with_knee_width(Self::NORMALIZE_KNEE_WIDTH_DB)
-                        .with_attack(Self::NORMALIZE_ATTACK_TIME)
-                        .with_release(Self::NORMALIZE_RELEASE_TIME);
-                    sources.append_with_signal(dither::dithered_volume(
-                        amplified.limit(limiter),
-                        self.dithered_volume.clone(),
+            let volume = self.dithered_volume.clone();
+            let rx = match resample_rate {
+                Some(target) => {
+                    let channels = decoder.channels();
+                    Self::append_source(
+                        sources,
+                        UniformSourceIterator::new(decoder, channels, target),
+                        track,
+                        difference,
+                        volume,
                         lufs_target,
                         self.noise_shaping,
-                    ))
+                        self.clip_detection,
+                    )
                 }
+                None => Self::append_source(
+                    sources,
+                    decoder,
+                    track,
+                    difference,
+                    volume,
+                    lufs_target,
+                    self.noise_shaping,
+                    self.clip_detection,
+                ),
             };
 
             let sample_rate = track.sample_rate.map_or("unknown".to_string(), |rate| {
@@ -1097,6 +2065,31 @@ impl Player {
     pub async fn run(&mut self) -> Result<()> {
         const RUN_FREQUENCY: Duration = Duration::from_millis(10);
         loop {
+            // Periodically log the performance counters while playing.
+            if self.is_playing() && self.metrics_logged_at.elapsed() >= Self::METRICS_LOG_INTERVAL {
+                self.metrics_logged_at = std::time::Instant::now();
+                let metrics = self.metrics();
+                debug!(
+                    "performance: {} samples, {} underruns, {} µs per {} samples",
+                    metrics.samples,
+                    metrics.underruns,
+                    metrics.average_block_time.as_micros(),
+                    Metrics::BLOCK_SIZE
+                );
+            }
+
+            // Recover from a position beyond the queue that no queue update followed.
+            self.check_position_timeout();
+
+            // Release the output device when paused for too long.
+            if let Some(idle_timeout) = self.idle_timeout
+                && self
+                    .paused_since
+                    .is_some_and(|paused_since| paused_since.elapsed() >= idle_timeout)
+            {
+                self.release_idle_device();
+            }
+
             // Check for stream errors and handle them.
             if let Some(error_rx) = &mut self.stream_error_rx
                 && let Ok(err) = error_rx.try_recv()
@@ -1105,19 +2098,72 @@ impl Player {
                 return Err(err.into());
             }
 
+            // Rebuild the output queue when more sources are queued than allowed,
+            // for example after lowering the limit. Only the current track is
+            // reloaded, at its current position.
+            if let Some(max_queued_sources) = self.max_queued_sources
+                && self.queued_sources() > max_queued_sources
+                && self.is_loaded()
+            {
+                warn!(
+                    "{} sources queued, more than {max_queued_sources}; rebuilding output queue",
+                    self.queued_sources()
+                );
+                let position = self.get_pos().saturating_sub(self.playing_since);
+                self.clear();
+                if !self.track().is_some_and(Track::is_livestream) {
+                    self.deferred_seek = Some(position);
+                }
+            }
+
+            // Report when the preloaded next track is fully buffered.
+            if !self.next_buffered
+                && !self.preload_rx.is_empty()
+                && self.next_track().is_some_and(Track::is_complete)
+            {
+                self.next_buffered = true;
+                self.notify(Event::NextTrackBuffered);
+            }
+
             match self.current_rx.as_mut() {
                 Some(current_rx) => {
                     if current_rx.try_recv().is_ok() {
                         // Case 1: Current track finished; advance to the next track.
+                        let played = self.get_pos().saturating_sub(self.playing_since);
+                        if played >= Self::LIVESTREAM_STABLE_DURATION {
+                            self.livestream_attempts = 0;
+                        }
+
+                        if self.clip_detection
+                            && let Some(track) = self.track()
+                        {
+                            let clipped = track.clipped_samples();
+                            if clipped > 0 {
+                                warn!("{} {track} clipped {clipped} samples", track.typ());
+                            }
+                        }
+
                         // Save the point in time when the track finished playing.
                         self.playing_since = self.get_pos();
-                        self.current_rx = self.preload_rx.take();
+                        self.current_rx = self.preload_rx.pop_front();
+                        self.next_buffered = false;
                         if let Some(track) = self.track_mut() {
                             // Finished tracks are dropped from the queue, which also removes
                             // their associated download, so reset the state.
                             track.reset_download();
                         }
-                        self.go_next();
+
+                        let stats = decrypt::statistics();
+                        debug!(
+                            "decryption statistics: {} blocks ({} bytes) decrypted, {} blocks skipped",
+                            stats.blocks_decrypted, stats.bytes_decrypted, stats.blocks_skipped
+                        );
+
+                        // Livestreams do not finish by themselves: reconnect when
+                        // one was interrupted, instead of advancing.
+                        if self.current_rx.is_some() || !self.retry_livestream() {
+                            self.go_next();
+                        }
                     } else if self.repeat_mode == RepeatMode::One {
                         // Case 2: To repeat the current track re-using the current download,
                         // check if we are near the end of the track.
@@ -1134,41 +2180,95 @@ impl Player {
                                 }
                             }
                         }
-                    } else if self.preload_rx.is_none()
-                        && self.track().is_some_and(Track::is_complete)
+                    } else if self.preload_rx.len() < self.preload_limit()
+                        && !self.license_pending
+                        && self
+                            .queue
+                            .get(self.position.saturating_add(self.preload_rx.len()))
+                            .is_some_and(Track::is_complete)
                         && self.get_pos() >= self.preload_start
                     {
-                        // Case 3: Preload the next track for gapless playback.
-                        let next_position = self.position.saturating_add(1);
+                        // Case 3: Preload the next tracks for gapless playback, one after the
+                        // other once the previous download completed. Preloads must be
+                        // contiguous, so stop at a track that is skipped.
+                        let next_position = self
+                            .position
+                            .saturating_add(self.preload_rx.len())
+                            .saturating_add(1);
                         if let Some(next_track) = self.queue.get(next_position) {
                             let next_track_id = next_track.id();
                             let next_track_typ = next_track.typ();
-                            if !self.skip_tracks.contains(&next_track_id) {
+                            if !self.is_skipped(next_track_id) {
                                 match self.load_track(next_position).await {
                                     Ok(rx) => {
-                                        self.preload_rx = rx;
+                                        if rx.is_some()
+                                            && next_position == self.position.saturating_add(1)
+                                        {
+                                            self.notify(Event::NextTrackPreloaded);
+                                        }
+                                        self.preload_rx.extend(rx);
                                     }
                                     Err(e) => {
                                         error!("failed to preload next {next_track_typ}: {e}");
-                                        self.mark_unavailable(next_track_id);
+                                        self.notify(Event::from(&e));
+                                        if self.retry_license(&e, next_track_id) {
+                                            // Retried once the license token is renewed.
+                                        } else if self.mark_unavailable(next_track_id) {
+                                            self.preload_start = self
+                                                .get_pos()
+                                                .saturating_add(Self::LOAD_RETRY_DELAY);
+                                        }
                                     }
                                 }
                             }
                         }
+                    } else if self.verify_downloads
+                        && let Some(track) = self.track()
+                        && track.is_truncated()
+                        && !self.redownloaded_tracks.contains(&track.id())
+                    {
+                        // Case 4: The download of the current track is corrupt; download it
+                        // again and continue from the current position.
+                        let track_id = track.id();
+                        let e = Error::data_loss(format!(
+                            "download of {} {track} is truncated",
+                            track.typ()
+                        ));
+                        error!("{e}; downloading again");
+                        self.notify(Event::from(&e));
+                        self.redownloaded_tracks.insert(track_id);
+
+                        let position = self.get_pos().saturating_sub(self.playing_since);
+                        self.clear();
+                        self.deferred_seek = Some(position);
                     }
                 }
 
                 None => {
+                    // Wait before retrying, like reconnecting an interrupted livestream,
+                    // or until the output device is opened again after being idle, or
+                    // until the license token is renewed.
+                    if self.idle_released
+                        || self.license_pending
+                        || self
+                            .retry_at
+                            .is_some_and(|retry_at| std::time::Instant::now() < retry_at)
+                    {
+                        tokio::time::sleep(RUN_FREQUENCY).await;
+                        continue;
+                    }
+
                     if let Some(track) = self.track() {
                         let track_id = track.id();
                         let track_typ = track.typ();
                         let track_dur = track.duration();
                         let track_bits = track.bits_per_sample;
-                        if self.skip_tracks.contains(&track_id) {
+                        if self.is_skipped(track_id) {
                             self.go_next();
                         } else {
                             match self.load_track(self.position).await {
                                 Ok(rx) => {
+                                    self.retry_at = None;
                                     if let Some(rx) = rx {
                                         self.current_rx = Some(rx);
                                         self.dithered_volume.set_track_bit_depth(track_bits);
@@ -1181,7 +2281,15 @@ impl Player {
                                 }
                                 Err(e) => {
                                     error!("failed to load {track_typ}: {e}");
-                                    self.mark_unavailable(track_id);
+                                    self.notify(Event::from(&e));
+                                    if self.retry_license(&e, track_id) {
+                                        // Retried once the license token is renewed.
+                                    } else if !self.retry_livestream()
+                                        && self.mark_unavailable(track_id)
+                                    {
+                                        self.retry_at = std::time::Instant::now()
+                                            .checked_add(Self::LOAD_RETRY_DELAY);
+                                    }
                                 }
                             }
                         }
@@ -1194,25 +2302,260 @@ impl Player {
         }
     }
 
+    /// Appends a source to the output queue with normalization and dithering.
+    ///
+    /// # Arguments
+    ///
+    /// * `sources` - Output queue to append to
+    /// * `source` - Decoded audio of the track
+    /// * `track` - Track being appended, for logging and clip counting
+    /// * `difference` - Normalization gain in dB, zero for none
+    /// * `volume` - Volume control with dithering parameters
+    /// * `lufs_target` - LUFS target for equal loudness compensation, if enabled
+    /// * `noise_shaping` - Noise shaping profile
+    /// * `clip_detection` - Whether to count samples that clip at the output
+    ///
+    /// # Returns
+    ///
+    /// Receiver that is signaled when the source finishes playing.
+    #[expect(clippy::too_many_arguments)]
+    fn append_source<S>(
+        sources: &rodio::queue::SourcesQueueInput,
+        source: S,
+        track: &Track,
+        difference: f32,
+        volume: Arc<Volume>,
+        lufs_target: Option<f32>,
+        noise_shaping: u8,
+        clip_detection: bool,
+    ) -> std::sync::mpsc::Receiver<()>
+    where
+        S: Source + Send + 'static,
+    {
+        let output = if 2.0 * difference.abs() <= f32::EPSILON * difference.abs() {
+            // No normalization needed, just append the decoder.
+            dither::dithered_volume(source, volume, lufs_target, noise_shaping)
+        } else {
+            let ratio = db_to_linear(difference);
+            let amplified = source.amplify(ratio);
+            if difference < 1.0 {
+                debug!(
+                    "normalizing {} {track} by {difference:.1} dB ({})",
+                    track.typ(),
+                    Percentage::from_ratio(ratio)
+                );
+
+                dither::dithered_volume(amplified, volume, lufs_target, noise_shaping)
+            } else {
+                debug!(
+                    "normalizing {} {track} by {difference:.1} dB ({}) with dynamic limiting",
+                    track.typ(),
+                    Percentage::from_ratio(ratio)
+                );
+
+                let limiter = LimitSettings::default()
+                    .with_threshold(Self::NORMALIZE_THRESHOLD_DB)
+                    .with_knee_width(Self::NORMALIZE_KNEE_WIDTH_DB)
+                    .with_attack(Self::NORMALIZE_ATTACK_TIME)
+                    .with_release(Self::NORMALIZE_RELEASE_TIME);
+                dither::dithered_volume(
+                    amplified.limit(limiter),
+                    volume,
+                    lufs_target,
+                    noise_shaping,
+                )
+            }
+        };
+
+        if clip_detection {
+            sources.append_with_signal(metrics::clip_detected(output, track.clipped_handle()))
+        } else {
+            sources.append_with_signal(output)
+        }
+    }
+
     /// Calculates the start time for preloading a track.
     ///
     /// The start time is calculated based on the current position and the track duration.
-    /// If the track duration is not available, preloads may start immediately.
+    /// If the track duration is not available, preloads may start immediately. Very short
+    /// tracks end within the preload window, so preloads start immediately as well.
     fn calc_preload_start(&self, track_duration: Option<Duration>) -> Duration {
         self.get_pos()
-            .saturating_add(track_duration.map_or(Duration::ZERO, |duration| {
-                duration.saturating_sub(Track::PREFETCH_DURATION.saturating_mul(2))
-            }))
+            .saturating_add(Self::preload_offset(track_duration))
+    }
+
+    /// Returns how far into a track of `track_duration` preloading starts.
+    fn preload_offset(track_duration: Option<Duration>) -> Duration {
+        track_duration.map_or(Duration::ZERO, |duration| {
+            duration.saturating_sub(Track::PREFETCH_DURATION.saturating_mul(2))
+        })
+    }
+
+    /// Returns the performance counters of the audio pipeline.
+    ///
+    /// Counters accumulate over the lifetime of the player, so the difference
+    /// between two snapshots shows recent activity.
+    #[must_use]
+    #[inline]
+    pub fn metrics(&self) -> metrics::Snapshot {
+        self.metrics.snapshot()
+    }
+
+    /// Returns where the decryption key in use came from.
+    ///
+    /// Helps to tell whether a configured or a freshly fetched `bf_secret`
+    /// is in use when debugging playback failures.
+    #[must_use]
+    #[inline]
+    pub fn bf_secret_source(&self) -> KeySource {
+        self.bf_secret_source
+    }
+
+    /// Determines whether to retry a track after a forbidden download.
+    ///
+    /// A forbidden download usually means that the license token is stale.
+    /// [`Event::LicenseForbidden`] lets the client renew it. Loading waits
+    /// until the renewed token is delivered, after which the track is
+    /// retried once instead of being marked unavailable.
+    fn retry_license(&mut self, e: &Error, track_id: TrackId) -> bool {
+        if e.kind == ErrorKind::Unauthenticated && self.license_retried.insert(track_id) {
+            warn!("retrying {track_id} with a renewed license token");
+            if !self.license_pending {
+                self.license_pending = true;
+                self.notify(Event::LicenseForbidden);
+            }
+            return true;
+        }
+
+        false
+    }
+
+    /// Stops waiting for a renewed license token.
+    ///
+    /// Tracks that wait for it are retried with the current license token,
+    /// and marked unavailable if they fail again. No effect when no renewal
+    /// is pending.
+    #[inline]
+    pub fn cancel_license_renewal(&mut self) {
+        self.license_pending = false;
+    }
+
+    /// Schedules a reconnection of the current livestream.
+    ///
+    /// The delay doubles with every consecutive attempt, up to the
+    /// configured number of retries.
+    ///
+    /// Returns `false` if the current track is not a livestream, or if
+    /// the maximum number of attempts has been reached.
+    fn retry_livestream(&mut self) -> bool {
+        if !self.track().is_some_and(Track::is_livestream)
+            || self.livestream_attempts >= self.livestream_retries
+        {
+            self.retry_at = None;
+            return false;
+        }
+
+        let delay = self
+            .livestream_retry_delay
+            .saturating_mul(1 << self.livestream_attempts.min(16));
+        self.livestream_attempts = self.livestream_attempts.saturating_add(1);
+        warn!(
+            "livestream interrupted; reconnecting in {:.1}s (attempt {} of {})",
+            delay.as_secs_f32(),
+            self.livestream_attempts,
+            self.livestream_retries
+        );
+
+        self.retry_at = std::time::Instant::now().checked_add(delay);
+        true
     }
 
     /// Marks a track as unavailable for playback.
     ///
     /// Tracks marked unavailable will be skipped during playback.
     /// Logs a warning the first time a track is marked unavailable.
-    fn mark_unavailable(&mut self, track_id: TrackId) {
+    ///
+    /// Tracks on the allow list are never marked unavailable, and other
+    /// tracks only after failing to load `load_failures` times. Until then,
+    /// they are retried.
+    ///
+    /// Returns whether the track will be retried, in which case the caller
+    /// should wait [`LOAD_RETRY_DELAY`](Self::LOAD_RETRY_DELAY) before
+    /// loading it again.
+    fn mark_unavailable(&mut self, track_id: TrackId) -> bool {
+        if self.allow_tracks.contains(&track_id) && !self.deny_tracks.contains(&track_id) {
+            debug!(
+                "not marking allowed track {track_id} as unavailable, retrying in {}s",
+                Self::LOAD_RETRY_DELAY.as_secs()
+            );
+            return true;
+        }
+
+        let failures = self.failed_loads.entry(track_id).or_default();
+        *failures = failures.saturating_add(1);
+        if *failures < self.load_failures {
+            warn!(
+                "track {track_id} failed to load; retrying in {}s (failure {failures} of {})",
+                Self::LOAD_RETRY_DELAY.as_secs(),
+                self.load_failures
+            );
+            return true;
+        }
+        self.failed_loads.remove(&track_id);
+
         if self.skip_tracks.insert(track_id) {
             warn!("marking track {track_id} as unavailable");
+            self.unavailable_count += 1;
         }
+
+        false
+    }
+
+    /// Returns the number of tracks marked unavailable since the player was created.
+    #[must_use]
+    #[inline]
+    pub fn unavailable_count(&self) -> u64 {
+        self.unavailable_count
+    }
+
+    /// Returns whether a track should be skipped during playback.
+    ///
+    /// A track is skipped when it was marked unavailable, or
+    /// when it is on the deny list.
+    fn is_skipped(&self, track_id: TrackId) -> bool {
+        self.skip_tracks.contains(&track_id) || self.deny_tracks.contains(&track_id)
+    }
+
+    /// Sets the track IDs that should never be marked as unavailable.
+    ///
+    /// Allowed tracks that were previously marked unavailable are
+    /// made available again, so they will be retried.
+    pub fn set_allow_tracks(&mut self, track_ids: impl IntoIterator<Item = TrackId>) {
+        self.allow_tracks = track_ids.into_iter().collect();
+        self.skip_tracks
+            .retain(|track_id| !self.allow_tracks.contains(track_id));
+    }
+
+    /// Sets the track IDs that should always be skipped.
+    ///
+    /// Takes effect on the next track load; a track that is
+    /// already playing will not be interrupted.
+    pub fn set_deny_tracks(&mut self, track_ids: impl IntoIterator<Item = TrackId>) {
+        self.deny_tracks = track_ids.into_iter().collect();
+    }
+
+    /// Returns the track IDs that are never marked as unavailable.
+    #[must_use]
+    #[inline]
+    pub fn allow_tracks(&self) -> &HashSet<TrackId> {
+        &self.allow_tracks
+    }
+
+    /// Returns the track IDs that are always skipped.
+    #[must_use]
+    #[inline]
+    pub fn deny_tracks(&self) -> &HashSet<TrackId> {
+        &self.deny_tracks
     }
 
     /// Sends a playback event notification.
@@ -1259,10 +2602,28 @@ impl Player {
     /// * Audio device fails to open
     /// * Device is no longer available
     pub fn play(&mut self) -> Result<()> {
+        // Reopen the output device if it changed while paused.
+        if self.verify_device && self.is_started() && !self.is_playing() && self.device_changed() {
+            info!("reopening output device");
+            self.release_device();
+        }
+
         // Ensure the audio device is open.
         self.start()?;
+        let was_paused = self.paused_since.take().is_some();
 
         if !self.is_playing() {
+            // Drop the stale buffer of a paused livestream, so the run loop
+            // reopens it at the live edge.
+            if was_paused
+                && self.livestream_live_edge
+                && self.is_loaded()
+                && self.track().is_some_and(Track::is_livestream)
+            {
+                debug!("resuming livestream from the live edge");
+                self.clear();
+            }
+
             debug!("starting playback");
             let original_volume = self.ramp_volume(0.0);
 
@@ -1323,6 +2684,12 @@ impl Player {
         let _ = self.sink_mut().map(|sink| sink.pause());
         self.notify(Event::Pause);
 
+        // Remember the pause, which also counts towards releasing the output device.
+        if self.is_started() {
+            self.paused_since
+                .get_or_insert_with(std::time::Instant::now);
+        }
+
         // Reset the volume to its original value.
         self.ramp_volume(original_volume);
     }
@@ -1389,6 +2756,9 @@ impl Player {
         self.position = 0;
         self.queue = tracks;
         self.skip_tracks = HashSet::new();
+        self.redownloaded_tracks = HashSet::new();
+        self.license_retried = HashSet::new();
+        self.skip_explicit_tracks(0);
     }
 
     /// Returns a reference to the next track in the queue, if any.
@@ -1418,46 +2788,185 @@ impl Player {
     /// * Reorders remaining tracks to match provided order
     /// * Updates internal queue position
     /// * Clears preloaded tracks to reflect new order
+    ///
+    /// Track IDs may occur more than once, for example when a playlist
+    /// contains the same track twice. Each ID is matched to the first
+    /// remaining instance in the queue, so that repeated tracks keep their
+    /// relative order and each instance is moved exactly once.
     pub fn reorder_queue(&mut self, track_ids: &[TrackId]) {
-        let current_track_id = self.track().map(Track::id);
-        let next_track_id = self.next_track().map(Track::id);
+        let current_position = self.position;
+        let next_position = self.position.saturating_add(1);
+
+        // Reorder the queue based on the new track order.
+        let queue_ids: Vec<_> = self.queue.iter().map(Track::id).collect();
+        let order = Self::reorder_positions(&queue_ids, track_ids);
+
+        let mut old_queue: Vec<Option<Track>> = std::mem::take(&mut self.queue)
+            .into_iter()
+            .map(Some)
+            .collect();
+        let mut new_queue = Vec::with_capacity(order.len());
+        let mut new_position = None;
+        for old_position in order {
+            if let Some(mut new_track) = old_queue[old_position].take() {
+                // Reset the download state of tracks that are not in the current or next position.
+                if old_position == current_position {
+                    new_position = Some(new_queue.len());
+                } else if old_position != next_position {
+                    new_track.reset_download();
+                }
+
+                new_queue.push(new_track);
+            }
+        }
+
+        // Find the new position of the current track in the new queue.
+        self.position = new_position.unwrap_or_default();
+
+        // Set the new queue and clear the current track and preloaded tracks.
+        self.queue = new_queue;
+        self.preload_rx.clear();
+        self.next_buffered = false;
+        self.sources.as_mut().map(|sources| sources.clear());
+    }
+
+    /// Returns the positions in `queue_ids` of the tracks in their new order.
+    ///
+    /// Each ID in `track_ids` is matched to the first remaining instance in
+    /// `queue_ids`, so that repeated tracks keep their relative order and each
+    /// instance is moved exactly once. IDs that are not in the queue are left
+    /// out.
+    fn reorder_positions(queue_ids: &[TrackId], track_ids: &[TrackId]) -> Vec<usize> {
+        let mut taken = vec![false; queue_ids.len()];
+        track_ids
+            .iter()
+            .filter_map(|track_id| {
+                let position = queue_ids
+                    .iter()
+                    .zip(&taken)
+                    .position(|(id, taken)| !taken && id == track_id)?;
+                taken[position] = true;
+                Some(position)
+            })
+            .collect()
+    }
+
+    /// Refreshes the access tokens of the tracks in the queue.
+    ///
+    /// Tracks are matched by ID to `fresh`, which holds freshly fetched
+    /// copies of the queue's tracks. Download state and queue order are kept.
+    /// Tracks that fell back are matched by their original ID.
+    ///
+    /// Returns the number of tracks that were refreshed.
+    pub fn refresh_tokens(&mut self, fresh: &[Track]) -> usize {
+        let fresh: HashMap<_, _> = fresh.iter().map(|track| (track.id(), track)).collect();
+
+        let mut refreshed = 0;
+        for track in &mut self.queue {
+            let id = track.original_id().unwrap_or_else(|| track.id());
+            if let Some(fresh) = fresh.get(&id) {
+                track.refresh_tokens(fresh);
+                refreshed += 1;
+            }
+        }
+
+        refreshed
+    }
+
+    /// Preloads the next track right away.
+    ///
+    /// Normally the next track is preloaded shortly before the current track
+    /// ends, once the current track has finished downloading. This starts
+    /// loading it immediately instead, for an instant start when skipping.
+    /// No effect without a current track, or when the next track is skipped
+    /// or already preloaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the next track fails to load. The run loop retries it
+    /// when the preload is due.
+    pub async fn prefetch_next(&mut self) -> Result<()> {
+        if self.current_rx.is_none() || !self.preload_rx.is_empty() {
+            return Ok(());
+        }
+
+        let next_position = self.position.saturating_add(1);
+        if self
+            .queue
+            .get(next_position)
+            .is_none_or(|track| self.is_skipped(track.id()))
+        {
+            return Ok(());
+        }
+
+        debug!("prefetching next track");
+        let rx = self.load_track(next_position).await?;
+        if rx.is_some() {
+            self.notify(Event::NextTrackPreloaded);
+        }
+        self.preload_rx.extend(rx);
 
-        // Reorder the queue based on the new track order.
-        let mut new_queue = Vec::with_capacity(track_ids.len());
-        for new_track_id in track_ids {
-            if let Some(position) = self
-                .queue
-                .iter()
-                .position(|track| &track.id() == new_track_id)
-            {
-                let mut new_track = self.queue.remove(position);
+        Ok(())
+    }
 
-                // Reset the download state of tracks that are not in the current or next position.
-                if ![current_track_id, next_track_id].contains(&Some(new_track.id())) {
-                    new_track.reset_download();
-                }
+    /// Recovers when the position stays beyond the queue for too long.
+    ///
+    /// Positions beyond the queue are allowed to prepare for queue updates.
+    /// When no update arrives within the position timeout, an error event is
+    /// emitted and the position is clamped to the last track of the queue.
+    /// Extendable queues are left alone, as they wait to be extended.
+    fn check_position_timeout(&mut self) {
+        let Some(timeout) = self.position_timeout else {
+            return;
+        };
 
-                new_queue.push(new_track);
-            }
+        if self.queue.is_empty() || self.position < self.queue.len() || self.extendable {
+            self.out_of_range_since = None;
+            return;
         }
 
-        // Find the new position of the current track in the new queue.
-        self.position = new_queue
-            .iter()
-            .position(|track| Some(track.id()) == current_track_id)
-            .unwrap_or_default();
+        let since = *self
+            .out_of_range_since
+            .get_or_insert_with(std::time::Instant::now);
+        if since.elapsed() < timeout {
+            return;
+        }
 
-        // Set the new queue and clear the current track and preloaded track.
-        self.queue = new_queue;
-        self.preload_rx = None;
-        self.sources.as_mut().map(|sources| sources.clear());
+        let last = self.queue.len() - 1;
+        let e = Error::out_of_range(format!(
+            "position {} is beyond the queue of {} tracks; clamping to {last}",
+            self.position,
+            self.queue.len()
+        ));
+        error!("{e}");
+        self.notify(Event::from(&e));
+
+        self.out_of_range_since = None;
+        self.set_position(last);
     }
 
     /// Adds tracks to the end of the queue.
     ///
     /// Preserves current playback position and state.
     pub fn extend_queue(&mut self, tracks: Vec<Track>) {
+        let offset = self.queue.len();
         self.queue.extend(tracks);
+        self.skip_explicit_tracks(offset);
+    }
+
+    /// Marks songs with explicit lyrics to be skipped, if enabled.
+    ///
+    /// Only considers the tracks in the queue from `offset` onwards.
+    fn skip_explicit_tracks(&mut self, offset: usize) {
+        if !self.skip_explicit {
+            return;
+        }
+
+        for track in self.queue.iter().skip(offset) {
+            if track.is_explicit() && self.skip_tracks.insert(track.id()) {
+                info!("skipping explicit {} {track}", track.typ());
+            }
+        }
     }
 
     /// Sets the current playback position in the queue.
@@ -1481,7 +2990,7 @@ impl Player {
         // need to drop the preload. This only works if the player is playing: only then does the
         // playback loop advance to the next track.
         if target == self.position.saturating_add(1)
-            && self.preload_rx.is_some()
+            && !self.preload_rx.is_empty()
             && self.is_playing()
         {
             match self.set_progress(Percentage::ONE_HUNDRED) {
@@ -1495,6 +3004,34 @@ impl Player {
         self.position = target;
     }
 
+    /// Skips to a position in the queue, fading through silence if enabled.
+    ///
+    /// With a skip fade, the current track fades out to silence, and then
+    /// the target track is loaded and fades in from silence. The tracks do
+    /// not overlap. The fade-out yields to the executor between steps, so it
+    /// does not block the runtime.
+    ///
+    /// Without a skip fade, or when not playing, this is the same as
+    /// [`set_position`](Self::set_position).
+    pub async fn skip_to(&mut self, target: usize) {
+        if let Some(duration) = self.skip_fade
+            && self.is_playing()
+            && self.position != target
+        {
+            info!("fading through silence to playlist position {target}");
+            let original_volume = self.ramp_volume_async_over(0.0, duration).await;
+            self.clear();
+            self.position = target;
+            self.fading_through_silence = true;
+
+            // Nothing plays after clearing, so this restores the volume right away.
+            self.ramp_volume(original_volume);
+            return;
+        }
+
+        self.set_position(target);
+    }
+
     /// Clears the playback state.
     ///
     /// When sink is active:
@@ -1525,25 +3062,43 @@ impl Player {
 
             // With Rodio having dropped the previous output queue, we need to create a new one.
             let (sources, output) = rodio::queue::queue(true);
-            sink.append(output);
+            sink.append(metrics::instrumented(output, Arc::clone(&self.metrics)));
             self.sources = Some(sources);
         }
 
         // Restore the original volume.
         self.ramp_volume(original_volume);
 
-        // Resetting the sink drops any downloads of the current and next tracks.
+        // Resetting the sink drops any downloads of the current and preloaded tracks.
         // We need to reset the download state of those tracks.
-        if let Some(current) = self.track_mut() {
-            current.reset_download();
-        }
-        if let Some(next) = self.next_track_mut() {
-            next.reset_download();
+        let loaded = self.preload_rx.len().max(1).saturating_add(1);
+        for track in self.queue.iter_mut().skip(self.position).take(loaded) {
+            track.reset_download();
         }
 
         self.playing_since = Duration::ZERO;
         self.current_rx = None;
-        self.preload_rx = None;
+        self.preload_rx.clear();
+        self.next_buffered = false;
+        self.livestream_attempts = 0;
+        self.retry_at = None;
+    }
+
+    /// Returns whether the queue is open-ended.
+    #[must_use]
+    #[inline]
+    pub fn is_extendable(&self) -> bool {
+        self.extendable
+    }
+
+    /// Sets whether the queue is open-ended, like Flow.
+    ///
+    /// When reaching the end of an extendable queue, the player moves past
+    /// the end and waits for [`extend_queue`](Self::extend_queue) instead of
+    /// wrapping around to the start with `RepeatMode::All`.
+    #[inline]
+    pub fn set_extendable(&mut self, extendable: bool) {
+        self.extendable = extendable;
     }
 
     /// Returns the current repeat mode.
@@ -1563,9 +3118,10 @@ impl Player {
         self.repeat_mode = repeat_mode;
 
         if repeat_mode == RepeatMode::One {
-            // This only clears the preloaded track.
+            // This only clears the preloaded tracks.
             self.sources.as_mut().map(|sources| sources.clear());
-            self.preload_rx = None;
+            self.preload_rx.clear();
+            self.next_buffered = false;
         }
     }
 
@@ -1664,12 +3220,146 @@ impl Player {
         if target > 0.0 && target < 1.0 {
             debug!(
                 "volume scaled logarithmically to {}%",
-                Percentage::from_ratio(Self::log_volume(target))
+                Percentage::from_ratio(Self::output_volume(target, self.min_volume.as_ratio()))
             );
         }
         current
     }
 
+    /// Inverts the logarithmic volume scaling of [`log_volume`](Self::log_volume).
+    ///
+    /// Above 10% volume the exponential curve is inverted analytically. Below,
+    /// the additional linear fade has no closed-form inverse, so the volume is
+    /// found by bisection, which converges as the curve is monotonic.
+    ///
+    /// # Arguments
+    ///
+    /// * `amplitude` - Amplitude ratio between 0.0 and 1.0
+    ///
+    /// # Returns
+    ///
+    /// Linear volume value between 0.0 and 1.0
+    #[must_use]
+    fn inverse_log_volume(amplitude: f32) -> f32 {
+        if amplitude.is_nan() || amplitude <= 0.0 {
+            return 0.0;
+        }
+        if amplitude >= UNITY_GAIN {
+            return UNITY_GAIN;
+        }
+
+        let volume =
+            f32::ln(amplitude * Self::LOG_VOLUME_SCALE_FACTOR) / Self::LOG_VOLUME_GROWTH_RATE;
+        if volume >= 0.1 {
+            return volume;
+        }
+
+        // 24 iterations are enough for full `f32` precision in this interval.
+        let (mut low, mut high) = (0.0, 0.1);
+        for _ in 0..24 {
+            let mid = f32::midpoint(low, high);
+            if Self::log_volume(mid) < amplitude {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        f32::midpoint(low, high)
+    }
+
+    /// Returns the last volume setting in dBFS.
+    ///
+    /// Converts the volume after logarithmic scaling, so that 100% is 0 dBFS
+    /// and 50% is about -30 dBFS. Returns negative infinity when muted.
+    #[must_use]
+    pub fn volume_db(&self) -> f32 {
+        20.0 * f32::log10(Self::output_volume(
+            self.volume.as_ratio(),
+            self.min_volume.as_ratio(),
+        ))
+    }
+
+    /// Applies the minimum volume floor and logarithmic scaling to a volume.
+    ///
+    /// Any non-zero volume is rescaled into the range from the floor to full
+    /// volume, so that low volumes still use the upper range of the output.
+    /// Zero still mutes.
+    ///
+    /// # Arguments
+    ///
+    /// * `volume` - Linear volume value between 0.0 and 1.0
+    /// * `min_volume` - Minimum volume floor between 0.0 and 1.0
+    ///
+    /// # Returns
+    ///
+    /// Output amplitude between 0.0 and 1.0
+    #[must_use]
+    fn output_volume(volume: f32, min_volume: f32) -> f32 {
+        if volume > 0.0 && min_volume > 0.0 {
+            return Self::log_volume(min_volume + (UNITY_GAIN - min_volume) * volume);
+        }
+
+        Self::log_volume(volume)
+    }
+
+    /// Inverts [`output_volume`](Self::output_volume).
+    ///
+    /// Amplitudes below the minimum volume floor result in the lowest
+    /// volume above mute.
+    #[must_use]
+    fn inverse_output_volume(amplitude: f32, min_volume: f32) -> f32 {
+        let volume = Self::inverse_log_volume(amplitude);
+        if volume > 0.0 && min_volume > 0.0 {
+            if min_volume >= UNITY_GAIN {
+                return UNITY_GAIN;
+            }
+            return ((volume - min_volume) / (UNITY_GAIN - min_volume)).max(f32::MIN_POSITIVE);
+        }
+
+        volume
+    }
+
+    /// Returns the minimum volume floor.
+    #[must_use]
+    #[inline]
+    pub fn min_volume(&self) -> Percentage {
+        self.min_volume
+    }
+
+    /// Sets the minimum volume floor.
+    ///
+    /// Any non-zero volume maps to at least this volume, while zero still
+    /// mutes. Applied with the next volume change.
+    #[inline]
+    pub fn set_min_volume(&mut self, min_volume: Percentage) {
+        self.min_volume = min_volume;
+    }
+
+    /// Sets playback volume in dBFS.
+    ///
+    /// Converts the level to the linear volume that, after logarithmic scaling,
+    /// results in the same amplitude, and then applies it like
+    /// [`set_volume`](Self::set_volume), including ramping. Levels at or above
+    /// 0 dBFS set full volume, and negative infinity mutes.
+    ///
+    /// # Arguments
+    ///
+    /// * `db` - Target volume in dBFS
+    ///
+    /// # Returns
+    ///
+    /// Returns the previous volume.
+    pub fn set_volume_db(&mut self, db: f32) -> Percentage {
+        let amplitude = f32::powf(10.0, db / 20.0);
+        let volume = Self::inverse_output_volume(amplitude, self.min_volume.as_ratio());
+        debug!(
+            "volume of {db:.1} dBFS is {}",
+            Percentage::from_ratio(volume)
+        );
+        self.set_volume(Percentage::from_ratio(volume))
+    }
+
     /// Gradually changes audio volume over a short duration to prevent popping.
     ///
     /// Applies a logarithmic volume ramp between the current and target volumes over
@@ -1692,9 +3382,7 @@ impl Player {
         let original_volume = self.volume().as_ratio();
 
         // Ramp only if the target is different from the current volume
-        if 2.0 * (original_volume - target).abs()
-            > f32::EPSILON * (original_volume.abs() + target.abs())
-        {
+        if Self::volume_differs(original_volume, target) {
             // Store the unscaled volume setting for playback reporting.
             self.volume = Percentage::from_ratio(target);
 
@@ -1703,9 +3391,7 @@ impl Player {
                 let millis = Self::FADE_DURATION.as_millis();
                 for i in 1..millis {
                     let progress = i.to_f32_lossy() / millis.to_f32_lossy();
-                    let faded = original_volume * (1.0 - progress) + target * progress;
-                    let log_faded = Self::log_volume(faded);
-                    self.dithered_volume.set_volume(log_faded);
+                    self.set_faded_volume(original_volume, target, progress);
 
                     // This blocks the current thread for 1 ms, but is better than making the
                     // function async and waiting for the future to complete.
@@ -1713,19 +3399,127 @@ impl Player {
                 }
             }
 
-            let log_target = Self::log_volume(target);
-            self.dithered_volume.set_volume(log_target);
+            self.finish_ramp(target);
+        }
 
-            if let Some(dither_bits) = self.dithered_volume.effective_bit_depth()
-                && target > 0.0
-            {
-                debug!("volume control dither: {dither_bits:.1} bits");
+        original_volume
+    }
+
+    /// Gradually changes audio volume without blocking the current thread.
+    ///
+    /// Like [`ramp_volume`](Self::ramp_volume), but yields to the executor
+    /// between steps. Returns the original volume before ramping.
+    async fn ramp_volume_async(&mut self, target: f32) -> f32 {
+        self.ramp_volume_async_over(target, Self::FADE_DURATION)
+            .await
+    }
+
+    /// Gradually changes audio volume over `duration` without blocking.
+    ///
+    /// Like [`ramp_volume_async`](Self::ramp_volume_async), but for fades of
+    /// another duration. Returns the original volume before ramping.
+    async fn ramp_volume_async_over(&mut self, target: f32, duration: Duration) -> f32 {
+        let original_volume = self.volume().as_ratio();
+
+        if Self::volume_differs(original_volume, target) {
+            self.volume = Percentage::from_ratio(target);
+
+            if self.current_rx.is_some() {
+                let millis = duration.as_millis();
+                let mut interval = tokio::time::interval(Duration::from_millis(1));
+                for i in 1..millis {
+                    interval.tick().await;
+                    let progress = i.to_f32_lossy() / millis.to_f32_lossy();
+                    self.set_faded_volume(original_volume, target, progress);
+                }
             }
+
+            self.finish_ramp(target);
         }
 
         original_volume
     }
 
+    /// Sets the output amplitude for a volume, attenuated while ducked.
+    fn apply_output_volume(&mut self, volume: f32) {
+        let amplitude = Self::output_volume(volume, self.min_volume.as_ratio()) * self.duck_ratio;
+        self.dithered_volume.set_volume(amplitude);
+    }
+
+    /// Temporarily attenuates the output, for example for announcements.
+    ///
+    /// Fades to `ratio` of the current volume over `fade`. The volume
+    /// setting is kept, and changing the volume while ducked stays
+    /// attenuated. Restore the output with [`unduck`](Self::unduck).
+    ///
+    /// The fade yields to the executor between steps, so it does not block
+    /// the runtime.
+    pub async fn duck(&mut self, ratio: f32, fade: Duration) {
+        let ratio = ratio.clamp(0.0, UNITY_GAIN);
+        info!("ducking to {}", Percentage::from_ratio(ratio));
+        self.ramp_duck_ratio(ratio, fade).await;
+    }
+
+    /// Restores the output after [`duck`](Self::duck), fading over `fade`.
+    pub async fn unduck(&mut self, fade: Duration) {
+        if self.is_ducked() {
+            info!("unducking");
+            self.ramp_duck_ratio(UNITY_GAIN, fade).await;
+        }
+    }
+
+    /// Returns whether the output is ducked.
+    #[must_use]
+    #[inline]
+    pub fn is_ducked(&self) -> bool {
+        Self::volume_differs(self.duck_ratio, UNITY_GAIN)
+    }
+
+    /// Gradually changes the duck ratio over `fade`.
+    ///
+    /// Like [`ramp_volume_async`](Self::ramp_volume_async), yields to the
+    /// executor between steps.
+    async fn ramp_duck_ratio(&mut self, target: f32, fade: Duration) {
+        let original = self.duck_ratio;
+        let volume = self.volume.as_ratio();
+
+        if self.current_rx.is_some() {
+            let millis = fade.as_millis();
+            let mut interval = tokio::time::interval(Duration::from_millis(1));
+            for i in 1..millis {
+                interval.tick().await;
+                let progress = i.to_f32_lossy() / millis.to_f32_lossy();
+                self.duck_ratio = original * (1.0 - progress) + target * progress;
+                self.apply_output_volume(volume);
+            }
+        }
+
+        self.duck_ratio = target;
+        self.apply_output_volume(volume);
+    }
+
+    /// Returns whether two volumes differ enough to ramp between them.
+    fn volume_differs(original: f32, target: f32) -> bool {
+        2.0 * (original - target).abs() > f32::EPSILON * (original.abs() + target.abs())
+    }
+
+    /// Sets the output volume to a step of a volume ramp.
+    fn set_faded_volume(&mut self, original: f32, target: f32, progress: f32) {
+        let faded = original * (1.0 - progress) + target * progress;
+        self.apply_output_volume(faded);
+    }
+
+    /// Sets the output volume to the target of a volume ramp.
+    fn finish_ramp(&mut self, target: f32) {
+        self.apply_output_volume(target);
+
+        if let Some(dither_bits) = self.dithered_volume.effective_bit_depth()
+            && target > 0.0
+        {
+            debug!("volume control dither: {dither_bits:.1} bits");
+        }
+    }
+
     /// Returns current playback progress.
     ///
     /// Returns None if no track is playing or track duration is unknown.
@@ -1745,15 +3539,53 @@ impl Player {
                     return Some(Percentage::ZERO);
                 }
 
-                // The progress is the difference between the current position of the sink, which
-                // is the total duration played, and the time the current track started playing.
                 let duration = track.duration()?;
-                let progress = self.get_pos().saturating_sub(self.playing_since);
+                let progress = self.position_in_track();
                 Some(Percentage::from_ratio(progress.div_duration_f32(duration)))
             }
         })
     }
 
+    /// Returns the position in the current track as reported by the sink.
+    ///
+    /// This is the difference between the current position of the sink, which
+    /// is the total duration played, and the time the current track started
+    /// playing. Returns `Duration::ZERO` if no track is loaded.
+    #[must_use]
+    pub fn sink_position(&self) -> Duration {
+        self.get_pos().saturating_sub(self.playing_since)
+    }
+
+    /// Returns how far the current track has been decoded.
+    ///
+    /// Runs ahead of the [sink position](Self::sink_position) by the audio
+    /// queued for output. Returns `None` if no track is loaded.
+    #[must_use]
+    pub fn decoder_position(&self) -> Option<Duration> {
+        self.is_loaded()
+            .then(|| self.track().map(Track::decoded))
+            .flatten()
+    }
+
+    /// Returns the best estimate of the audible position in the current track.
+    ///
+    /// The sink position follows the output, but is derived from counting
+    /// samples, which may drift from the media time after resampling. The
+    /// output can never be ahead of the decoder, so the sink position is
+    /// capped to the decoder position.
+    fn position_in_track(&self) -> Duration {
+        let sink_position = self.sink_position();
+        match self.decoder_position() {
+            Some(decoder_position) if sink_position > decoder_position => {
+                trace!(
+                    "sink position {sink_position:?} ahead of decoder position {decoder_position:?}"
+                );
+                decoder_position
+            }
+            _ => sink_position,
+        }
+    }
+
     /// Returns duration of current track.
     ///
     /// For normal tracks, returns total duration.
@@ -1822,17 +3654,18 @@ impl Player {
                 warn!("limiting seek to {minutes:02}:{seconds:02} due to buffering");
             }
 
-            // Try to seek only if the track has started downloading, otherwise defer the seek.
+            // Try to seek only if the track has been opened, otherwise defer the seek.
             // This prevents stalling the player when seeking in a track that has not started.
-            match track
-                .handle()
-                .ok_or_else(|| {
-                    Error::unavailable(format!(
-                        "download of {} {track} not yet started",
-                        track.typ()
-                    ))
-                })
-                .map(|_| self.ramp_volume(0.0))
+            let opened = if track.is_opened() {
+                Ok(())
+            } else {
+                Err(Error::unavailable(format!(
+                    "download of {} {track} not yet started",
+                    track.typ()
+                )))
+            };
+            match opened
+                .map(|()| self.ramp_volume(0.0))
                 .and_then(|original_volume| {
                     let seek_result = self
                         .sink_mut()
@@ -1869,9 +3702,12 @@ impl Player {
     }
 
     /// Sets the license token for media access.
+    ///
+    /// Tracks that wait for a renewed license token are retried with it.
     #[inline]
     pub fn set_license_token(&mut self, license_token: impl Into<String>) {
         self.license_token = license_token.into();
+        self.license_pending = false;
     }
 
     /// Enables or disables volume normalization.
@@ -1880,6 +3716,46 @@ impl Player {
         self.normalization = normalization;
     }
 
+    /// Sets the track types that volume normalization is applied to.
+    ///
+    /// Takes effect on the next track load.
+    #[inline]
+    pub fn set_normalization_types(&mut self, types: impl IntoIterator<Item = TrackType>) {
+        self.normalization_types = types.into_iter().collect();
+    }
+
+    /// Returns the number of sources queued for output, including the current track.
+    #[must_use]
+    pub fn queued_sources(&self) -> usize {
+        usize::from(self.current_rx.is_some()) + self.preload_rx.len()
+    }
+
+    /// Sets the maximum number of sources queued for output, or `None` for no bound.
+    ///
+    /// When lowered below the number of queued sources, the output queue is
+    /// rebuilt from the current track.
+    #[inline]
+    pub fn set_max_queued_sources(&mut self, max_queued_sources: Option<usize>) {
+        self.max_queued_sources = max_queued_sources;
+    }
+
+    /// Returns the number of tracks that may be preloaded ahead of the current track.
+    ///
+    /// This is the preload depth, bounded by the maximum number of queued sources.
+    fn preload_limit(&self) -> usize {
+        self.max_queued_sources
+            .map_or(self.preload_depth, |max_queued_sources| {
+                self.preload_depth.min(max_queued_sources.saturating_sub(1))
+            })
+    }
+
+    /// Returns the track types that volume normalization is applied to.
+    #[must_use]
+    #[inline]
+    pub fn normalization_types(&self) -> &HashSet<TrackType> {
+        &self.normalization_types
+    }
+
     /// Sets target gain for volume normalization.
     ///
     /// Logs info message if normalization is enabled.
@@ -1894,6 +3770,23 @@ impl Player {
         self.gain_target_db = gain_target_db;
     }
 
+    /// Sets the reference level for equal-loudness compensation.
+    ///
+    /// `None` follows the normalization target. Applies from the next
+    /// track that is loaded.
+    pub fn set_loudness_target(&mut self, loudness_target: Option<i8>) {
+        self.loudness_target = loudness_target;
+    }
+
+    /// Returns the reference level for equal-loudness compensation in LUFS.
+    ///
+    /// This is the configured level, or else the normalization target.
+    #[must_use]
+    #[inline]
+    pub fn loudness_target(&self) -> i8 {
+        self.loudness_target.unwrap_or(self.gain_target_db)
+    }
+
     /// Sets preferred audio quality for playback.
     ///
     /// Note: Actual quality may be lower if track is not
@@ -1910,6 +3803,23 @@ impl Player {
         self.normalization
     }
 
+    /// Returns whether equal-loudness compensation is enabled.
+    #[must_use]
+    #[inline]
+    pub fn loudness(&self) -> bool {
+        self.loudness
+    }
+
+    /// Returns whether equal-loudness compensation is in effect.
+    ///
+    /// Compensation applies to loaded tracks when enabled. At full output
+    /// volume the listening level equals the reference level, so the
+    /// compensation is flat and not considered in effect.
+    #[must_use]
+    pub fn is_loudness_active(&self) -> bool {
+        self.loudness && self.current_rx.is_some() && self.dithered_volume.volume() < UNITY_GAIN
+    }
+
     /// Returns current license token.
     #[must_use]
     #[inline]
@@ -1960,9 +3870,169 @@ impl Player {
     }
 }
 
+/// Raises the priority of the current thread.
+///
+/// Tries real-time scheduling with `priority` first, where supported. Without
+/// privileges for that, falls back to the highest priority of the current
+/// scheduling policy, which lowers the nice level on Linux. Failures are
+/// logged, as playback works without a raised priority.
+fn raise_thread_priority(priority: u8) {
+    use thread_priority::{ThreadPriority, set_current_thread_priority};
+
+    #[cfg(unix)]
+    {
+        use thread_priority::{
+            ThreadPriorityValue,
+            unix::{
+                RealtimeThreadSchedulePolicy, ThreadSchedulePolicy, set_thread_priority_and_policy,
+                thread_native_id,
+            },
+        };
+
+        if let Ok(value) = ThreadPriorityValue::try_from(priority) {
+            match set_thread_priority_and_policy(
+                thread_native_id(),
+                ThreadPriority::Crossplatform(value),
+                ThreadSchedulePolicy::Realtime(RealtimeThreadSchedulePolicy::Fifo),
+            ) {
+                Ok(()) => {
+                    debug!("audio thread scheduled in real time with priority {priority}");
+                    return;
+                }
+                Err(e) => debug!("real-time scheduling of audio thread not permitted: {e}"),
+            }
+        }
+    }
+
+    match set_current_thread_priority(ThreadPriority::Max) {
+        Ok(()) => debug!("audio thread priority raised"),
+        Err(e) => warn!("failed to raise audio thread priority: {e}"),
+    }
+}
+
 /// Ensures proper cleanup of audio device resources when player is dropped.
 impl Drop for Player {
+    /// Stops the player if it was not shut down already.
+    ///
+    /// This is a best-effort fallback that blocks while fading out. Call
+    /// [`Player::shutdown`] first to tear down without blocking.
     fn drop(&mut self) {
         self.stop();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track_ids(ids: &[i64]) -> Vec<TrackId> {
+        ids.iter().map(|&id| TrackId::new(id).unwrap()).collect()
+    }
+
+    #[test]
+    fn reorder_moves_each_duplicate_once() {
+        let queue = track_ids(&[1, 2, 1, 3, 1]);
+        let order = Player::reorder_positions(&queue, &track_ids(&[3, 1, 2, 1, 1]));
+        assert_eq!(order, [3, 0, 1, 2, 4]);
+
+        let mut moved = order.clone();
+        moved.sort_unstable();
+        assert_eq!(moved, [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn reorder_follows_current_instance() {
+        // The second instance of track 1 is playing.
+        let queue = track_ids(&[1, 2, 1, 3]);
+        let current = 2;
+
+        let order = Player::reorder_positions(&queue, &track_ids(&[2, 3, 1, 1]));
+        assert_eq!(order.iter().position(|&old| old == current), Some(3));
+    }
+
+    #[test]
+    fn reorder_leaves_out_unknown_ids() {
+        let queue = track_ids(&[1, 2]);
+        let order = Player::reorder_positions(&queue, &track_ids(&[2, 4, 1, 1]));
+        assert_eq!(order, [1, 0]);
+    }
+
+    #[test]
+    fn preload_offset_of_short_track_is_zero() {
+        assert_eq!(
+            Player::preload_offset(Some(Duration::from_secs(1))),
+            Duration::ZERO
+        );
+        assert_eq!(
+            Player::preload_offset(Some(Track::SHORT_DURATION)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn preload_offset_of_long_track_leaves_preload_window() {
+        assert_eq!(
+            Player::preload_offset(Some(Duration::from_secs(180))),
+            Duration::from_secs(174)
+        );
+        assert_eq!(Player::preload_offset(None), Duration::ZERO);
+    }
+
+    #[test]
+    fn inverse_log_volume_round_trips() {
+        // Covers both the linear fade below 10% and the exponential curve above.
+        for percent in 0..=100u8 {
+            let volume = f32::from(percent) / 100.0;
+            let amplitude = Player::log_volume(volume);
+            let inverse = Player::inverse_log_volume(amplitude);
+            assert!(
+                (inverse - volume).abs() < 1e-4,
+                "{volume} maps to {amplitude}, which inverts to {inverse}"
+            );
+        }
+    }
+
+    #[test]
+    fn inverse_log_volume_clamps_out_of_range() {
+        assert!(Player::inverse_log_volume(0.0).abs() < f32::EPSILON);
+        assert!(Player::inverse_log_volume(-1.0).abs() < f32::EPSILON);
+        assert!(Player::inverse_log_volume(f32::NAN).abs() < f32::EPSILON);
+        assert!((Player::inverse_log_volume(2.0) - UNITY_GAIN).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn min_volume_floor_applies_at_boundary() {
+        let min_volume = 0.2;
+        let floor = Player::log_volume(min_volume);
+
+        // Zero still mutes, but the lowest volume above it maps to the floor.
+        assert!(Player::output_volume(0.0, min_volume).abs() < f32::EPSILON);
+        let lowest = Player::output_volume(f32::MIN_POSITIVE, min_volume);
+        assert!((lowest - floor).abs() < 1e-6);
+        assert!(Player::output_volume(0.01, min_volume) >= floor);
+        assert!((Player::output_volume(UNITY_GAIN, min_volume) - UNITY_GAIN).abs() < f32::EPSILON);
+
+        // Without a floor, volumes map as before.
+        assert!((Player::output_volume(0.01, 0.0) - Player::log_volume(0.01)).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn inverse_output_volume_round_trips_with_floor() {
+        let min_volume = 0.2;
+        for percent in 1..=100u8 {
+            let volume = f32::from(percent) / 100.0;
+            let amplitude = Player::output_volume(volume, min_volume);
+            let inverse = Player::inverse_output_volume(amplitude, min_volume);
+            assert!(
+                (inverse - volume).abs() < 1e-4,
+                "{volume} maps to {amplitude}, which inverts to {inverse}"
+            );
+        }
+
+        // Amplitudes below the floor result in the lowest volume above mute.
+        let below = Player::log_volume(min_volume) / 2.0;
+        let inverse = Player::inverse_output_volume(below, min_volume);
+        assert!(inverse > 0.0 && inverse < 1e-4);
+        assert!(Player::inverse_output_volume(0.0, min_volume).abs() < f32::EPSILON);
+    }
+}
@@ -26,7 +26,7 @@ use std::{fmt, str::FromStr, time::Duration};
 use crate::{error::Error, util::ToF32};
 
 /// Supported audio formats.
-#[derive(Copy, Clone, Default, Eq, PartialEq, SerializeDisplay, Debug, Hash)]
+#[derive(Copy, Clone, Default, Eq, PartialEq, PartialOrd, Ord, SerializeDisplay, Debug, Hash)]
 pub enum Codec {
     /// Audio Data Transport Stream container
     ///
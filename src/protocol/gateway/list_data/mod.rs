@@ -214,6 +214,14 @@ pub enum ListData {
         #[serde_as(as = "Option<DisplayFromStr>")]
         gain: Option<f64>,
 
+        /// Whether the song has explicit lyrics.
+        ///
+        /// Defaults to `false` when not provided.
+        #[serde(default)]
+        #[serde(rename = "EXPLICIT_LYRICS")]
+        #[serde(deserialize_with = "bool_from_string")]
+        explicit: bool,
+
         /// Authentication token for song playback.
         ///
         /// This token is required to access the song's media content and:
@@ -0,0 +1,118 @@
+//! Deezer artist and track radio endpoints.
+//!
+//! This module handles fetching tracks for radios based on a specific
+//! artist or track. Like Flow, these provide batches of recommended tracks
+//! that can be fetched repeatedly to extend the queue.
+//!
+//! # Wire Format
+//!
+//! Artist radio request:
+//! ```json
+//! {
+//!     "art_id": "27"
+//! }
+//! ```
+//!
+//! Track radio request:
+//! ```json
+//! {
+//!     "sng_id": "3135556",
+//!     "start_with_input_track": "false"
+//! }
+//! ```
+//!
+//! Responses contain a list of tracks in the same format as [`ListData`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use deezer::gateway::{Response, ArtistRadio};
+//!
+//! // Request tracks similar to an artist
+//! let request = ArtistRequest { artist_id: 27 };
+//!
+//! let response: Response<ArtistRadio> = /* gateway response */;
+//! for track in response.all() {
+//!     println!("Radio track: {} by {}", track.title, track.artist);
+//! }
+//! ```
+
+use std::ops::Deref;
+
+use serde::{Deserialize, Serialize};
+use serde_with::{DisplayFromStr, serde_as};
+
+use super::{ListData, Method};
+use crate::track::TrackId;
+
+/// Gateway method name for retrieving artist radio tracks.
+///
+/// Returns a batch of tracks by the artist and similar artists.
+impl Method for ArtistRadio {
+    const METHOD: &'static str = "smart.getSmartRadio";
+}
+
+/// Gateway method name for retrieving track radio tracks.
+///
+/// Returns a batch of tracks similar to the track.
+impl Method for TrackRadio {
+    const METHOD: &'static str = "song.getSearchTrackMix";
+}
+
+/// Wrapper for artist radio track data.
+///
+/// Contains the same track information as [`ListData`].
+#[derive(Clone, PartialEq, Deserialize, Debug)]
+#[serde(transparent)]
+pub struct ArtistRadio(pub ListData);
+
+/// Wrapper for track radio track data.
+///
+/// Contains the same track information as [`ListData`].
+#[derive(Clone, PartialEq, Deserialize, Debug)]
+#[serde(transparent)]
+pub struct TrackRadio(pub ListData);
+
+/// Provides access to the underlying track data.
+impl Deref for ArtistRadio {
+    type Target = ListData;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Provides access to the underlying track data.
+impl Deref for TrackRadio {
+    type Target = ListData;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Request parameters for artist radio tracks.
+#[serde_as]
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Debug, Hash)]
+pub struct ArtistRequest {
+    /// Artist ID to base the radio on.
+    #[serde(rename = "art_id")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub artist_id: u64,
+}
+
+/// Request parameters for track radio tracks.
+#[serde_as]
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Debug, Hash)]
+pub struct TrackRequest {
+    /// Track ID to base the radio on.
+    #[serde(rename = "sng_id")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub track_id: TrackId,
+
+    /// Whether the radio starts with the track itself.
+    #[serde_as(as = "DisplayFromStr")]
+    pub start_with_input_track: bool,
+}
@@ -5,7 +5,7 @@
 //! * Authentication tokens ([`arl`])
 //! * User data and settings ([`user_data`])
 //! * Content listings ([`list_data`])
-//! * Radio stations ([`user_radio`])
+//! * Radio stations ([`user_radio`], [`mix`])
 //!
 //! Supports multiple content types:
 //! * Songs - Regular music tracks
@@ -40,6 +40,7 @@
 
 pub mod arl;
 pub mod list_data;
+pub mod mix;
 pub mod user_data;
 pub mod user_radio;
 
@@ -48,6 +49,7 @@ pub use list_data::{
     EpisodeData, ListData, LivestreamData, LivestreamUrl, LivestreamUrls, Queue, SongData,
     episodes, livestream, songs,
 };
+pub use mix::{ArtistRadio, TrackRadio};
 pub use user_data::{MediaUrl, UserData};
 pub use user_radio::UserRadio;
 
@@ -30,6 +30,7 @@
 //!         "remote_control": true
 //!     },
 //!     "URL_MEDIA": "https://media.deezer.com",
+//!     "SERVER_TIMESTAMP": 1234567000,
 //!     "GAIN": {
 //!         "TARGET": "-15"
 //!     }
@@ -63,6 +64,7 @@ impl Method for UserData {
 /// Contains all user-specific information needed for authentication
 /// and playback configuration.
 // TODO : #[serde(rename_all = "UPPERCASE")]
+#[serde_as]
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Deserialize, Redact, Hash)]
 pub struct UserData {
     /// User profile and preferences
@@ -93,6 +95,14 @@ pub struct UserData {
     #[serde(default)]
     #[serde(rename = "GAIN")]
     pub gain: Gain,
+
+    /// Server time when the response was generated
+    ///
+    /// Used to calculate token lifetimes independently of the local clock.
+    #[serde(default)]
+    #[serde(rename = "SERVER_TIMESTAMP")]
+    #[serde_as(as = "Option<TimestampSeconds<i64, Flexible>>")]
+    pub server_timestamp: Option<SystemTime>,
 }
 
 /// Media server URL wrapper.
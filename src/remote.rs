@@ -15,7 +15,11 @@
 //!
 //! Hook scripts can be configured to execute on events, receiving information
 //! through environment variables. All events include the `EVENT` variable
-//! containing the event name, plus additional variables specific to each event:
+//! containing the event name, plus additional variables specific to each event.
+//!
+//! Multiple hooks may be configured. Each hook may pass arguments, which can
+//! contain `{VARIABLE}` placeholders that are substituted with the values of
+//! the variables below:
 //!
 //! ## `playing`
 //! Emitted when playback starts
@@ -61,6 +65,13 @@
 //!
 //! No additional variables
 //!
+//! ## `error`
+//! Emitted when a non-fatal error occurs
+//!
+//! Variables:
+//! - `ERROR_KIND`: The error category (e.g. "NotFound", "DeadlineExceeded")
+//! - `ERROR_MESSAGE`: Human-readable description of the error
+//!
 //! # Protocol Details
 //!
 //! ## Connection Flow
@@ -115,14 +126,21 @@
 use std::{
     collections::{HashMap, HashSet},
     fmt::Write,
+    io::Write as _,
     ops::ControlFlow,
+    path::{Path, PathBuf},
     pin::Pin,
+    sync::LazyLock,
     time::Duration,
 };
 
-use futures_util::{SinkExt, StreamExt, stream::SplitSink};
+use futures_util::{
+    SinkExt, StreamExt,
+    stream::{SplitSink, SplitStream},
+};
 use log::Level;
 use rand::prelude::*;
+use regex_lite::Regex;
 use semver;
 use time::OffsetDateTime;
 use tokio::process::Command;
@@ -137,20 +155,26 @@ use tokio_tungstenite::{
 use uuid::Uuid;
 
 use crate::{
-    config::{Config, Credentials},
+    config::{Config, Credentials, MetadataFallbacks},
+    decrypt::KeySource,
     error::{Error, Result},
     events::Event,
     gateway::Gateway,
+    http::Client as HttpClient,
     player::Player,
     protocol::connect::{
-        Body, Channel, Contents, DeviceId, DeviceType, Headers, Ident, Message, Percentage,
-        QueueItem, RepeatMode, Status, UserId,
+        AudioQuality, Body, Channel, Contents, DeviceId, DeviceType, Headers, Ident, Message,
+        Percentage, QueueItem, RepeatMode, Status, UserId,
         queue::{self, MixType},
         stream,
     },
     proxy,
+    scrobble::{self, Scrobble},
     tokens::UserToken,
-    track::{DEFAULT_BITS_PER_SAMPLE, DEFAULT_SAMPLE_RATE, Track, TrackId},
+    track::{
+        CoverArt, DEFAULT_BITS_PER_SAMPLE, DEFAULT_SAMPLE_RATE, ImageFormat, Track, TrackId,
+        TrackType,
+    },
     util::ToF32,
 };
 
@@ -211,6 +235,15 @@ pub struct Client {
     /// Timer for sending heartbeats
     watchdog_tx: Pin<Box<tokio::time::Sleep>>,
 
+    /// Message ID and send time of the heartbeat awaiting acknowledgement
+    pending_ping: Option<(String, std::time::Instant)>,
+
+    /// Channel for publishing the liveness of the client
+    health_tx: tokio::sync::watch::Sender<Health>,
+
+    /// Channel for publishing the session statistics
+    stats_tx: tokio::sync::watch::Sender<SessionStats>,
+
     /// Current discovery state
     discovery_state: DiscoveryState,
 
@@ -221,12 +254,26 @@ pub struct Client {
     /// device rather than session since the same controllers typically reconnect multiple times.
     discovery_sessions: HashMap<DeviceId, String>,
 
+    /// When [`Client::prepare`] obtained the user token, and its time to live.
+    ///
+    /// Taken by `start`, so that it does not authenticate again.
+    prepared: Option<(tokio::time::Instant, Duration)>,
+
     /// Channel for receiving player and control events
     event_rx: tokio::sync::mpsc::UnboundedReceiver<Event>,
 
     /// Channel for sending player and control events
     event_tx: tokio::sync::mpsc::UnboundedSender<Event>,
 
+    /// Channel for receiving local playback controls
+    control_rx: tokio::sync::mpsc::UnboundedReceiver<Control>,
+
+    /// Channel for sending local playback controls
+    control_tx: tokio::sync::mpsc::UnboundedSender<Control>,
+
+    /// Channel for publishing the playback state to local integrations
+    state_tx: tokio::sync::watch::Sender<PlaybackState>,
+
     /// Volume level to set on connection and maintain until client sets below maximum.
     /// Helps work around clients that don't properly set volume levels.
     initial_volume: InitialVolume,
@@ -234,8 +281,63 @@ pub struct Client {
     /// Whether to allow connection interruptions
     interruptions: bool,
 
-    /// Optional hook script for events
-    hook: Option<String>,
+    /// Whether to decline all connections while staying discoverable
+    announce_only: bool,
+
+    /// Whether to keep plays from being reported to Deezer
+    private_listening: bool,
+
+    /// Whether stopping clears the queue and releases the output device
+    full_stop: bool,
+
+    /// Minimum time between reporting plays of the same track
+    min_report_interval: Duration,
+
+    /// Last track whose play was reported, and when
+    last_report: Option<(TrackId, tokio::time::Instant)>,
+
+    /// Whether to reject queues from unknown container types
+    reject_unknown_containers: bool,
+
+    /// Status to respond with to the skip received during the handshake
+    handshake_skip_status: Status,
+
+    /// Delay before the first start, taken on first use
+    startup_delay: Option<Duration>,
+
+    /// Timeout of each network request while starting up
+    startup_timeout: Duration,
+
+    /// Number of times to retry a network request while starting up
+    startup_retries: u32,
+
+    /// Whether to restart instead of refreshing an expired user token in place
+    restart_on_token_expiry: bool,
+
+    /// Seed for reproducible shuffle orders, random if `None`
+    shuffle_seed: Option<u64>,
+
+    /// Placeholder metadata for hook scripts
+    metadata_fallbacks: MetadataFallbacks,
+
+    /// Channel for sending event variables to the hook runner task
+    ///
+    /// `None` when no hook scripts are configured.
+    hook_tx: Option<tokio::sync::mpsc::UnboundedSender<HookVars>>,
+
+    /// Channel for sending scrobbles to the scrobbler task
+    ///
+    /// `None` when no scrobble webhook is configured.
+    scrobble_tx: Option<tokio::sync::mpsc::UnboundedSender<Scrobble>>,
+
+    /// Percentage of a track that must play before it is scrobbled
+    scrobble_percent: u8,
+
+    /// Time after which a track is scrobbled, regardless of the percentage
+    scrobble_after: Duration,
+
+    /// Track that is yet to be scrobbled, with the time it started playing
+    scrobble_candidate: Option<(TrackId, std::time::SystemTime)>,
 
     /// Audio playback manager
     player: Player,
@@ -248,6 +350,9 @@ pub struct Client {
     /// Maintains both track list and shuffle state.
     queue: Option<queue::List>,
 
+    /// Whether a queue was published since the controller connected
+    queue_published: bool,
+
     /// Position to set when queue arrives
     ///
     /// Used to handle position changes that arrive before queue.
@@ -255,6 +360,9 @@ pub struct Client {
 
     /// Whether to monitor all websocket traffic
     eavesdrop: bool,
+
+    /// File to write all protocol messages to
+    protocol_log: Option<ProtocolLog>,
 }
 
 /// Device discovery state.
@@ -325,6 +433,230 @@ enum InitialVolume {
     Disabled,
 }
 
+/// Radio that extends the queue with recommendations as it plays.
+///
+/// * `Flow` - Personalized radio of the user
+/// * `Artist` - Radio based on an artist
+/// * `Track` - Radio based on a track
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Radio {
+    /// Personalized radio of the user
+    Flow,
+    /// Radio based on the artist with this ID
+    Artist(u64),
+    /// Radio based on the track with this ID
+    Track(TrackId),
+}
+
+/// Control of playback from the local device, for example from media keys.
+///
+/// Controls are sent through [`Client::control_sender`] and handled in the
+/// event loop of the client, which reports the result to the controller.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Control {
+    /// Start or resume playback
+    Play,
+    /// Pause playback
+    Pause,
+    /// Toggle between playing and paused
+    PlayPause,
+    /// Skip to the next track in the queue
+    Next,
+    /// Skip to the previous track in the queue
+    Previous,
+    /// Seek to this position in the current track
+    Seek(Duration),
+    /// Change the volume by this many percentage points
+    ChangeVolume(i8),
+    /// Temporarily lower the output, for example for announcements
+    Duck {
+        /// Output level while ducked, as a percentage of the volume
+        percent: u8,
+        /// Duration of the fade
+        fade: Duration,
+    },
+    /// Restore the output after ducking, fading over this duration
+    Unduck(Duration),
+}
+
+/// Snapshot of the playback state for local integrations.
+///
+/// Published by the client whenever the state changes and on each
+/// progress report. See [`Client::subscribe_state`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlaybackState {
+    /// Whether playback is running
+    pub playing: bool,
+    /// ID of the current track
+    pub track_id: Option<TrackId>,
+    /// Title of the current track
+    pub title: Option<String>,
+    /// Artist of the current track
+    pub artist: Option<String>,
+    /// Album title of the current track
+    pub album: Option<String>,
+    /// Duration of the current track, unknown for livestreams
+    pub duration: Option<Duration>,
+    /// URL of the cover art of the current track
+    pub cover_url: Option<url::Url>,
+    /// Position in the current track at `updated_at`
+    pub elapsed: Duration,
+    /// When this snapshot was taken
+    pub updated_at: Option<std::time::Instant>,
+    /// Where the decryption key in use came from
+    pub key_source: KeySource,
+    /// Whether equal-loudness compensation is in effect
+    pub loudness_active: bool,
+    /// Audio quality requested for the current track
+    pub requested_quality: AudioQuality,
+    /// Audio quality of the current track, which may be lower than requested
+    pub quality: AudioQuality,
+}
+
+impl PlaybackState {
+    /// Returns the position in the current track by now.
+    ///
+    /// Extrapolates from the time the snapshot was taken while playing.
+    #[must_use]
+    pub fn position(&self) -> Duration {
+        let mut position = self.elapsed;
+        if self.playing
+            && let Some(updated_at) = self.updated_at
+        {
+            position = position.saturating_add(updated_at.elapsed());
+        }
+        match self.duration {
+            Some(duration) => position.min(duration),
+            None => position,
+        }
+    }
+}
+
+/// Liveness of the client for health checks.
+///
+/// Published whenever a heartbeat is acknowledged, a progress report is
+/// sent or the connection changes. A supervisor can restart pleezer when the
+/// last report or heartbeat grows stale while connected. See
+/// [`Client::subscribe_health`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Health {
+    /// Whether a controller is connected
+    pub connected: bool,
+    /// Round-trip time of the last acknowledged heartbeat
+    pub heartbeat_rtt: Option<Duration>,
+    /// When the last heartbeat was acknowledged
+    pub last_heartbeat: Option<std::time::Instant>,
+    /// When the last progress report was sent successfully
+    pub last_report: Option<std::time::Instant>,
+}
+
+/// Counters of session events for monitoring stability.
+///
+/// Counted since the client was created, across restarts. See
+/// [`Client::subscribe_stats`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SessionStats {
+    /// Number of times the client started, including restarts
+    pub starts: u64,
+    /// Number of controller connections
+    pub connects: u64,
+    /// Number of controller disconnections
+    pub disconnects: u64,
+    /// Number of websocket reconnections in place
+    pub websocket_reconnects: u64,
+    /// Number of user token refreshes
+    pub token_refreshes: u64,
+    /// Number of tracks marked unavailable
+    pub unavailable_tracks: u64,
+}
+
+/// File that protocol messages are written to, independent of the log level.
+///
+/// Messages are written as pretty-printed JSON, each preceded by a line with
+/// the time and direction. When the file grows beyond [`Self::MAX_SIZE`], it
+/// is rotated to a file with `.1` appended to its name, replacing any
+/// previous one.
+#[derive(Debug)]
+struct ProtocolLog {
+    /// Path of the current log file
+    path: PathBuf,
+
+    /// Open log file
+    file: std::fs::File,
+
+    /// Number of bytes written to the current log file
+    size: u64,
+}
+
+impl ProtocolLog {
+    /// Maximum size of a log file in bytes before it is rotated.
+    const MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+    /// Opens the protocol log at `path`, appending to an existing file.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the file cannot be opened.
+    fn open(path: &Path) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size,
+        })
+    }
+
+    /// Writes a raw protocol message in the given direction.
+    ///
+    /// Failures are logged and otherwise ignored, so that logging never
+    /// interrupts the connection.
+    fn write(&mut self, direction: &str, json: &str) {
+        let pretty = serde_json::from_str::<serde_json::Value>(json)
+            .and_then(|value| serde_json::to_string_pretty(&value))
+            .unwrap_or_else(|_| json.to_string());
+        let entry = format!("--- {} {direction}\n{pretty}\n", OffsetDateTime::now_utc());
+
+        if let Err(e) = self.rotate().and_then(|()| {
+            self.file.write_all(entry.as_bytes())?;
+            Ok(())
+        }) {
+            error!("failed to write protocol log: {e}");
+            return;
+        }
+
+        self.size = self
+            .size
+            .saturating_add(u64::try_from(entry.len()).unwrap_or(u64::MAX));
+    }
+
+    /// Rotates the log file when it has reached its maximum size.
+    fn rotate(&mut self) -> Result<()> {
+        if self.size < Self::MAX_SIZE {
+            return Ok(());
+        }
+
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        std::fs::rename(&self.path, rotated)?;
+        *self = Self::open(&self.path.clone())?;
+        Ok(())
+    }
+}
+
+/// Receiving half of the Deezer Connect websocket.
+type WebsocketReceiver = SplitStream<WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>>;
+
+/// Variables passed to hook scripts for a single event.
+///
+/// Pairs of variable name and value, set in the environment of the hook
+/// and available for substitution in its arguments.
+type HookVars = Vec<(&'static str, String)>;
+
 /// Calculates a future time instant by adding seconds to now.
 ///
 /// Used for scheduling timers and watchdogs. Handles overflow
@@ -357,6 +689,9 @@ impl Client {
     /// Time before network operations timeout.
     const NETWORK_TIMEOUT: Duration = Duration::from_secs(2);
 
+    /// Delay before retrying a network request while starting up.
+    const STARTUP_RETRY_DELAY: Duration = Duration::from_secs(1);
+
     /// Buffer before token refresh to prevent expiration during requests.
     const TOKEN_EXPIRATION_THRESHOLD: Duration = Duration::from_secs(60);
 
@@ -369,6 +704,13 @@ impl Client {
     /// Maximum time between sending heartbeats.
     const WATCHDOG_TX_TIMEOUT: Duration = Duration::from_secs(5);
 
+    /// How often to check the event loop for time jumps.
+    const CLOCK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Delay of the clock check beyond which the process is considered to
+    /// have been suspended, for example by SIGSTOP.
+    const TIME_JUMP_THRESHOLD: Duration = Duration::from_secs(5);
+
     /// Maximum allowed websocket frame size (payload) in bytes.
     /// Set to 32KB (message size / 4) to balance between chunking and overhead.
     const FRAME_SIZE_MAX: usize = Self::MESSAGE_SIZE_MAX / 4;
@@ -377,6 +719,11 @@ impl Client {
     /// Set to 128KB (message buffer / 2) to provide backpressure and prevent OOM.
     const MESSAGE_SIZE_MAX: usize = Self::MESSAGE_BUFFER_MAX / 2;
 
+    /// Maximum size of a received websocket message in bytes.
+    /// Set to 512KB (message size * 4), so that messages over the message size
+    /// are still received and can be rejected without being parsed.
+    const MESSAGE_RECEIVE_MAX: usize = Self::MESSAGE_SIZE_MAX * 4;
+
     /// Maximum size of the websocket write buffer in bytes.
     /// Set to 256KB to provide adequate buffering while preventing memory exhaustion.
     const MESSAGE_BUFFER_MAX: usize = 2 * 128 * 1024;
@@ -407,24 +754,38 @@ impl Client {
     ///
     /// Returns error if:
     /// * Application version in config is not valid `SemVer`
+    /// * Protocol version override is not alphanumeric
     /// * Gateway client creation fails
+    /// * Protocol log cannot be opened
     pub fn new(config: &Config, player: Player) -> Result<Self> {
-        // Construct version in the form of `Mmmppp` where:
-        // - `M` is the major version
-        // - `mm` is the minor version
-        // - `ppp` is the patch version
-        let semver = semver::Version::parse(&config.app_version)?;
-        let major = semver.major;
-        let minor = semver.minor;
-        let patch = semver.patch;
-
-        // Trim leading zeroes.
-        let version = if major > 0 {
-            format!("{major}{minor:0>2}{patch:0>3}")
-        } else if minor > 0 {
-            format!("{minor}{patch:0>3}")
-        } else {
-            format!("{patch}")
+        let version = match config.protocol_version.as_ref() {
+            Some(version) => {
+                if version.is_empty() || !version.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    return Err(Error::invalid_argument(format!(
+                        "protocol version {version} is not alphanumeric"
+                    )));
+                }
+                version.clone()
+            }
+            None => {
+                // Construct version in the form of `Mmmppp` where:
+                // - `M` is the major version
+                // - `mm` is the minor version
+                // - `ppp` is the patch version
+                let semver = semver::Version::parse(&config.app_version)?;
+                let major = semver.major;
+                let minor = semver.minor;
+                let patch = semver.patch;
+
+                // Trim leading zeroes.
+                if major > 0 {
+                    format!("{major}{minor:0>2}{patch:0>3}")
+                } else if minor > 0 {
+                    format!("{minor}{patch:0>3}")
+                } else {
+                    format!("{patch}")
+                }
+            }
         };
         trace!("remote version: {version}");
 
@@ -437,10 +798,38 @@ impl Client {
 
         let (time_to_live_tx, time_to_live_rx) = tokio::sync::mpsc::channel(1);
         let (event_tx, event_rx) = tokio::sync::mpsc::unbounded_channel::<Event>();
+        let (control_tx, control_rx) = tokio::sync::mpsc::unbounded_channel::<Control>();
+        let state_tx = tokio::sync::watch::Sender::new(PlaybackState::default());
+        let health_tx = tokio::sync::watch::Sender::new(Health::default());
+        let stats_tx = tokio::sync::watch::Sender::new(SessionStats::default());
+
+        let protocol_log = config
+            .protocol_log
+            .as_deref()
+            .map(ProtocolLog::open)
+            .transpose()?;
 
         let mut player = player;
         player.register(event_tx.clone());
 
+        // Hooks run on a separate task so slow scripts never stall the
+        // event loop. A single task runs them in the order of events.
+        let hook_tx = (!config.hooks.is_empty()).then(|| {
+            let (hook_tx, hook_rx) = tokio::sync::mpsc::unbounded_channel();
+            tokio::spawn(Self::run_hooks(
+                config.hooks.clone(),
+                config.hook_timeout,
+                hook_rx,
+            ));
+            hook_tx
+        });
+
+        let scrobble_tx = config
+            .scrobble_url
+            .clone()
+            .map(|url| HttpClient::without_cookies(config).map(|http| scrobble::spawn(http, url)))
+            .transpose()?;
+
         let initial_volume = match config.initial_volume {
             Some(volume) => InitialVolume::Active(volume),
             None => InitialVolume::Disabled,
@@ -466,24 +855,51 @@ impl Client {
             connection_state: ConnectionState::Disconnected,
             watchdog_rx: Box::pin(watchdog_rx),
             watchdog_tx: Box::pin(watchdog_tx),
+            pending_ping: None,
+            health_tx,
+            stats_tx,
 
             event_rx,
             event_tx,
 
+            control_rx,
+            control_tx,
+            state_tx,
+
             player,
             reporting_timer: Box::pin(reporting_timer),
 
             discovery_state: DiscoveryState::Available,
             discovery_sessions: HashMap::new(),
+            prepared: None,
 
             initial_volume,
             interruptions: config.interruptions,
-            hook: config.hook.clone(),
+            announce_only: config.announce_only,
+            private_listening: config.private_listening,
+            full_stop: config.full_stop,
+            min_report_interval: config.min_report_interval,
+            last_report: None,
+            reject_unknown_containers: config.reject_unknown_containers,
+            handshake_skip_status: config.handshake_skip_status,
+            hook_tx,
+            scrobble_tx,
+            scrobble_percent: config.scrobble_percent,
+            scrobble_after: config.scrobble_after,
+            scrobble_candidate: None,
+            startup_delay: Some(config.startup_delay),
+            startup_timeout: config.startup_timeout,
+            startup_retries: config.startup_retries,
+            restart_on_token_expiry: config.restart_on_token_expiry,
+            shuffle_seed: config.shuffle_seed,
+            metadata_fallbacks: config.metadata_fallbacks.clone(),
 
             queue: None,
+            queue_published: false,
             deferred_position: None,
 
             eavesdrop: config.eavesdrop,
+            protocol_log,
         })
     }
 
@@ -501,15 +917,14 @@ impl Client {
     /// # Errors
     ///
     /// Returns error if:
-    /// * Gateway request fails
+    /// * Gateway request fails or does not complete within `timeout`
     /// * Token cannot be retrieved
-    async fn user_token(&mut self) -> Result<(UserToken, Duration)> {
+    async fn user_token(&mut self, timeout: Duration) -> Result<(UserToken, Duration)> {
         // Loop until a user token is supplied that expires after the
         // threshold. If rate limiting is necessary, then that should be done
         // by the token token_provider.
         loop {
-            let token =
-                tokio::time::timeout(Self::NETWORK_TIMEOUT, self.gateway.user_token()).await??;
+            let token = tokio::time::timeout(timeout, self.gateway.user_token()).await??;
 
             let time_to_live = token
                 .time_to_live()
@@ -535,6 +950,28 @@ impl Client {
         }
     }
 
+    /// Renews the license token of the player.
+    ///
+    /// Refreshes the user data, which contains the license token, and passes
+    /// it on to the player. Failures are only logged, to prevent renewing in
+    /// a loop; the player then retries with the current license token.
+    async fn renew_license(&mut self) {
+        info!("renewing license token");
+        self.gateway.flush_user_token();
+        match tokio::time::timeout(Self::NETWORK_TIMEOUT, self.gateway.user_token()).await {
+            Ok(Ok(_)) => {
+                self.stats_tx
+                    .send_modify(|stats| stats.token_refreshes += 1);
+                self.set_player_settings();
+            }
+            Ok(Err(e)) => error!("failed to renew license token: {e}"),
+            Err(e) => error!("failed to renew license token: {e}"),
+        }
+
+        // No effect when a new license token was delivered.
+        self.player.cancel_license_renewal();
+    }
+
     /// Configures player settings from user preferences.
     ///
     /// Updates:
@@ -644,44 +1081,76 @@ impl Client {
             .saturating_sub(Self::TOKEN_EXPIRATION_THRESHOLD)
     }
 
-    /// Starts the client and handles control messages.
+    /// Prepares the client for starting.
     ///
-    /// Authentication flow:
-    /// 1. Logs in with email/password or ARL to obtain refresh token
-    /// 2. Gets user token using refresh token
-    /// 3. Renews tokens automatically before expiration
-    /// 4. Maintains persistent login across reconnects
+    /// Performs the authentication steps of [`start`](Self::start) ahead of
+    /// time: waits for the startup delay, checks the network, logs in and
+    /// obtains a user token with its user data. Front-ends can use this to
+    /// show progress and report authentication errors before the websocket
+    /// is opened. The decryption key is already obtained when creating the
+    /// player.
     ///
-    /// Connection flow:
-    /// 1. Establishes websocket with configured limits
-    /// 2. Authenticates connection
-    /// 3. Clears cached discovery sessions
-    /// 4. Begins message processing
-    ///
-    /// Processes:
-    /// * Controller discovery
-    /// * Command messages
-    /// * Playback state updates
-    /// * Connection maintenance
-    /// * Token renewals
+    /// Calling `start` afterwards skips these steps. Without preparing,
+    /// `start` performs them itself.
     ///
     /// # Errors
     ///
     /// Returns error if:
+    /// * The network is not ready
     /// * Authentication fails
-    /// * Websocket connection fails
-    /// * Message handling fails critically
-    /// * Token renewal fails
-    #[allow(clippy::too_many_lines)]
-    pub async fn start(&mut self) -> Result<()> {
-        // Purge discovery sessions from any previous session to prevent memory exhaustion.
-        self.discovery_sessions = HashMap::new();
+    /// * The user token cannot be obtained
+    pub async fn prepare(&mut self) -> Result<()> {
+        let token_ttl = self.authenticate().await?;
+        self.prepared = Some((tokio::time::Instant::now(), token_ttl));
+        Ok(())
+    }
+
+    /// Logs in and obtains the first user token.
+    ///
+    /// Returns the time to live of the user token.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the network is not ready, logging in fails, or the
+    /// user token cannot be obtained.
+    async fn authenticate(&mut self) -> Result<Duration> {
+        // Give dependent services like the network time to come up, but only once.
+        if let Some(startup_delay) = self.startup_delay.take()
+            && !startup_delay.is_zero()
+        {
+            info!(
+                "waiting {:.0}s before starting",
+                startup_delay.as_secs_f32().ceil()
+            );
+            tokio::time::sleep(startup_delay).await;
+        }
+
+        // Verify the network is ready before becoming discoverable.
+        self.preflight().await?;
 
         let arl = match self.credentials.clone() {
             Credentials::Login { email, password } => {
                 info!("logging in with email and password");
-                tokio::time::timeout(Self::NETWORK_TIMEOUT, self.gateway.oauth(&email, &password))
-                    .await??
+                let mut attempt = 0;
+                loop {
+                    let result = tokio::time::timeout(
+                        self.startup_timeout,
+                        self.gateway.oauth(&email, &password),
+                    )
+                    .await;
+                    match result.map_err(Error::from).and_then(|arl| arl) {
+                        Ok(arl) => break arl,
+                        Err(e) if e.is_transient() && attempt < self.startup_retries => {
+                            attempt += 1;
+                            warn!(
+                                "login failed: {e}; retrying (attempt {attempt} of {})",
+                                self.startup_retries
+                            );
+                            tokio::time::sleep(Self::STARTUP_RETRY_DELAY).await;
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
             }
             Credentials::Arl(arl) => {
                 info!("using ARL from secrets file");
@@ -701,21 +1170,81 @@ impl Client {
             Err(e) => warn!("jwt login timed out: {e}"),
         }
 
-        let (user_token, token_ttl) = self.user_token().await?;
+        // The first user token is fetched with the more generous startup
+        // timeout, as the network may still be warming up.
+        let mut attempt = 0;
+        let (user_token, token_ttl) = loop {
+            match self.user_token(self.startup_timeout).await {
+                Ok(token) => break token,
+                Err(e) if e.is_transient() && attempt < self.startup_retries => {
+                    attempt += 1;
+                    warn!(
+                        "failed to get user token: {e}; retrying (attempt {attempt} of {})",
+                        self.startup_retries
+                    );
+                    tokio::time::sleep(Self::STARTUP_RETRY_DELAY).await;
+                }
+                Err(e) => {
+                    self.handle_event(Event::TokenRefreshFailed {
+                        message: e.to_string(),
+                    })
+                    .await;
+                    return Err(e);
+                }
+            }
+        };
         debug!("user id: {}", user_token.user_id);
-
-        let uri = format!(
-            "{}{}?version={}",
-            Self::WEBSOCKET_URL,
-            user_token,
-            self.version
-        );
-        let mut request = ClientRequestBuilder::new(uri.parse::<http::Uri>()?);
         self.user_token = Some(user_token);
 
-        // Decorate the websocket request with the same cookies as the gateway.
-        let cookie_str = self.cookie_str();
-        request = request.with_header(http::header::COOKIE.as_str(), cookie_str);
+        if let Err(e) = self.event_tx.send(Event::LoggedIn) {
+            error!("failed to send logged in event: {e}");
+        }
+
+        Ok(token_ttl)
+    }
+
+    /// Starts the client and handles control messages.
+    ///
+    /// Authentication flow:
+    /// 1. Logs in with email/password or ARL to obtain refresh token
+    /// 2. Gets user token using refresh token
+    /// 3. Renews tokens automatically before expiration
+    /// 4. Maintains persistent login across reconnects
+    ///
+    /// The first two steps are skipped when [`prepare`](Self::prepare) ran
+    /// before.
+    ///
+    /// Connection flow:
+    /// 1. Establishes websocket with configured limits
+    /// 2. Authenticates connection
+    /// 3. Clears cached discovery sessions
+    /// 4. Begins message processing
+    ///
+    /// Processes:
+    /// * Controller discovery
+    /// * Command messages
+    /// * Playback state updates
+    /// * Connection maintenance
+    /// * Token renewals, reconnecting the websocket in place when the
+    ///   user token expires, unless configured to restart instead
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// * Authentication fails
+    /// * Websocket connection fails
+    /// * Message handling fails critically
+    /// * Token renewal fails
+    #[allow(clippy::too_many_lines)]
+    pub async fn start(&mut self) -> Result<()> {
+        // Purge discovery sessions from any previous session to prevent memory exhaustion.
+        self.discovery_sessions = HashMap::new();
+
+        // Authenticate unless `prepare` already did, accounting for the time since.
+        let token_ttl = match self.prepared.take() {
+            Some((prepared_at, token_ttl)) => token_ttl.saturating_sub(prepared_at.elapsed()),
+            None => self.authenticate().await?,
+        };
 
         // Set timer for user token expiration. Wake a short while before
         // actual expiration. This prevents API request errors when the
@@ -739,38 +1268,40 @@ impl Client {
         let jwt_expiry = tokio::time::sleep(jwt_ttl);
         tokio::pin!(jwt_expiry);
 
-        let config = Some(
-            WebSocketConfig::default()
-                .max_write_buffer_size(Self::MESSAGE_BUFFER_MAX)
-                .max_message_size(Some(Self::MESSAGE_SIZE_MAX))
-                .max_frame_size(Some(Self::FRAME_SIZE_MAX)),
-        );
-
-        let (ws_stream, _) = if let Some(proxy) = proxy::Http::from_env() {
-            info!("using proxy: {proxy}");
-            let tcp_stream = proxy.connect_async(&uri).await?;
-            tokio_tungstenite::client_async_tls_with_config(request, tcp_stream, config, None)
-                .await?
-        } else {
-            tokio_tungstenite::connect_async_with_config(request, config, false).await?
-        };
-
-        let (websocket_tx, mut websocket_rx) = ws_stream.split();
-        self.websocket_tx = Some(websocket_tx);
+        let mut websocket_rx = self.connect_websocket().await?;
+        self.stats_tx.send_modify(|stats| stats.starts += 1);
 
         self.subscribe(Ident::Stream).await?;
         self.subscribe(Ident::RemoteDiscover).await?;
 
         if self.eavesdrop {
             warn!("not discoverable: eavesdropping on websocket");
+        } else if self.announce_only {
+            info!("ready for discovery; declining connections");
         } else {
             info!("ready for discovery");
         }
 
+        // Detects when the process was suspended, so that the watchdogs that
+        // expired in the meantime do not drop the controller.
+        let mut clock_check = tokio::time::interval(Self::CLOCK_CHECK_INTERVAL);
+        clock_check.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut last_clock_check = tokio::time::Instant::now();
+
         let loop_result = loop {
             tokio::select! {
                 biased;
 
+                // Checked first, to run before any watchdog that expired during a suspension.
+                _ = clock_check.tick() => {
+                    let now = tokio::time::Instant::now();
+                    let gap = now.saturating_duration_since(last_clock_check);
+                    last_clock_check = now;
+                    if gap > Self::CLOCK_CHECK_INTERVAL + Self::TIME_JUMP_THRESHOLD {
+                        self.handle_time_jump(gap);
+                    }
+                }
+
                 () = &mut self.watchdog_tx, if self.is_connected() => {
                     if let Err(e) = self.send_ping().await {
                         error!("error sending ping: {e}");
@@ -783,7 +1314,35 @@ impl Client {
                 }
 
                 () = &mut token_expiry => {
-                    break Err(Error::deadline_exceeded("user token expired"));
+                    if self.restart_on_token_expiry {
+                        break Err(Error::deadline_exceeded("user token expired"));
+                    }
+
+                    // Try to keep the session alive with a fresh user token,
+                    // and only restart if that fails.
+                    info!("user token expired; refreshing");
+                    match self.reconnect_websocket().await {
+                        Ok((new_websocket_rx, token_ttl)) => {
+                            websocket_rx = new_websocket_rx;
+                            if let Some(deadline) = tokio::time::Instant::now().checked_add(token_ttl) {
+                                token_expiry.as_mut().reset(deadline);
+                            }
+
+                            if let Err(e) = self.event_tx.send(Event::TokenRefreshed) {
+                                error!("failed to send token refreshed event: {e}");
+                            }
+                        }
+                        Err(e) => {
+                            // Handle the event right away, as the client restarts.
+                            self.handle_event(Event::TokenRefreshFailed {
+                                message: e.to_string(),
+                            })
+                            .await;
+                            break Err(Error::deadline_exceeded(format!(
+                                "user token expired and refresh failed: {e}"
+                            )));
+                        }
+                    }
                 }
 
                 () = &mut session_expiry => {
@@ -841,7 +1400,10 @@ impl Client {
                 () = &mut self.reporting_timer, if self.is_connected() => {
                     if let Err(e) = self.report_playback_progress().await {
                         error!("error reporting playback progress: {e}");
+                        self.notify_error(&e);
                     }
+                    self.scrobble();
+                    self.publish_state();
                 }
 
                 Some(message) = websocket_rx.next() => {
@@ -852,6 +1414,7 @@ impl Client {
                             let message_size = message.len();
                             if message_size > Self::MESSAGE_SIZE_MAX {
                                 error!("ignoring oversized message with {message_size} bytes");
+                                self.handle_oversized_message(&message).await;
                                 continue;
                             }
 
@@ -875,6 +1438,10 @@ impl Client {
                 Some(event) = self.event_rx.recv() => {
                     self.handle_event(event).await;
                 }
+
+                Some(control) = self.control_rx.recv() => {
+                    self.handle_control(control).await;
+                }
             }
         };
 
@@ -882,17 +1449,153 @@ impl Client {
         loop_result
     }
 
+    /// Checks that the network is ready to serve a connection.
+    ///
+    /// Resolves the websocket host, so the device does not become
+    /// discoverable before DNS is available. Skipped when connecting
+    /// through a proxy, which resolves the host instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// * Websocket host cannot be resolved
+    /// * Resolution times out
+    async fn preflight(&self) -> Result<()> {
+        if proxy::Http::from_env().is_some() {
+            return Ok(());
+        }
+
+        let url = url::Url::parse(Self::WEBSOCKET_URL)?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| Error::invalid_argument("websocket host not available"))?;
+        let port = url.port_or_known_default().unwrap_or(443);
+
+        let mut addrs =
+            tokio::time::timeout(Self::NETWORK_TIMEOUT, tokio::net::lookup_host((host, port)))
+                .await??;
+        if addrs.next().is_none() {
+            return Err(Error::unavailable(format!("{host} did not resolve")));
+        }
+
+        trace!("preflight check passed");
+        Ok(())
+    }
+
+    /// Connects the websocket with the current user token.
+    ///
+    /// Sets the websocket sender and returns the receiving half. The
+    /// connection uses the same cookies as the gateway, and the proxy
+    /// from the environment if configured.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// * No user token is available
+    /// * Websocket connection fails
+    async fn connect_websocket(&mut self) -> Result<WebsocketReceiver> {
+        let user_token = self
+            .user_token
+            .as_ref()
+            .ok_or_else(|| Error::unauthenticated("user token unavailable"))?;
+
+        let uri = format!(
+            "{}{}?version={}",
+            Self::WEBSOCKET_URL,
+            user_token,
+            self.version
+        );
+        let mut request = ClientRequestBuilder::new(uri.parse::<http::Uri>()?);
+
+        // Decorate the websocket request with the same cookies as the gateway.
+        let cookie_str = self.cookie_str();
+        request = request.with_header(http::header::COOKIE.as_str(), cookie_str);
+
+        // Compression is not negotiated: tungstenite does not implement
+        // permessage-deflate, and requesting it anyway would have the server
+        // send compressed frames that fail on their reserved bits. Support
+        // is left until tungstenite implements the extension. The size
+        // limits below therefore apply to the messages as sent on the wire.
+        let config = Some(
+            WebSocketConfig::default()
+                .max_write_buffer_size(Self::MESSAGE_BUFFER_MAX)
+                .max_message_size(Some(Self::MESSAGE_RECEIVE_MAX))
+                .max_frame_size(Some(Self::FRAME_SIZE_MAX)),
+        );
+
+        let (ws_stream, _) = if let Some(proxy) = proxy::Http::from_env() {
+            info!("using proxy: {proxy}");
+            let tcp_stream = proxy.connect_async(&uri).await?;
+            tokio_tungstenite::client_async_tls_with_config(request, tcp_stream, config, None)
+                .await?
+        } else {
+            tokio_tungstenite::connect_async_with_config(request, config, false).await?
+        };
+
+        let (websocket_tx, websocket_rx) = ws_stream.split();
+        self.websocket_tx = Some(websocket_tx);
+
+        Ok(websocket_rx)
+    }
+
+    /// Refreshes the user token and reconnects the websocket with it.
+    ///
+    /// Restores all channel subscriptions on the new websocket, so an
+    /// active session can continue without a full restart.
+    ///
+    /// # Returns
+    ///
+    /// Tuple containing:
+    /// * Receiving half of the new websocket
+    /// * Duration - Time until the new token expires
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// * User token refresh fails
+    /// * Websocket connection fails
+    /// * Channel subscriptions cannot be restored
+    async fn reconnect_websocket(&mut self) -> Result<(WebsocketReceiver, Duration)> {
+        self.gateway.flush_user_token();
+        let (user_token, token_ttl) = self.user_token(Self::NETWORK_TIMEOUT).await?;
+        self.user_token = Some(user_token);
+
+        if let Some(mut websocket_tx) = self.websocket_tx.take() {
+            // The old connection is abandoned, so ignore any errors.
+            let _ = websocket_tx.close().await;
+        }
+
+        let websocket_rx = self.connect_websocket().await?;
+
+        // Subscriptions are bound to the websocket, so restore them.
+        let subscriptions = std::mem::take(&mut self.subscriptions);
+        for ident in subscriptions {
+            self.subscribe(ident).await?;
+        }
+
+        info!("user token refreshed");
+        self.stats_tx.send_modify(|stats| {
+            stats.websocket_reconnects += 1;
+            stats.token_refreshes += 1;
+        });
+        Ok((websocket_rx, token_ttl))
+    }
+
     /// Processes received events.
     ///
     /// Handles:
     /// * `Play` - Track started, updates stream state
     /// * `Pause` - Playback paused
     /// * `TrackChanged` - New track active, updates track info and audio parameters
+    /// * `NextTrackPreloaded` - Next track queued up for gapless playback
+    /// * `NextTrackBuffered` - Next track fully downloaded
     /// * Connected - Controller connected, configures initial settings
     /// * Disconnected - Controller disconnected, resets state
+    /// * `LoggedIn`, `TokenRefreshed`, `TokenRefreshFailed` - Authentication lifecycle
+    /// * Error - Non-fatal error occurred
     ///
     /// Also:
-    /// * Executes hook script if configured
+    /// * Executes hook scripts if configured
     /// * Reports playback progress
     /// * Manages Flow queue extension
     /// * Updates audio device settings
@@ -902,7 +1605,8 @@ impl Client {
     /// * `event` - Event to process
     #[allow(clippy::too_many_lines)]
     async fn handle_event(&mut self, event: Event) {
-        let mut command = self.hook.as_ref().map(Command::new);
+        let run_hooks = self.hook_tx.is_some();
+        let mut vars = HookVars::new();
         let track_id = self.player.track().map(Track::id);
 
         debug!("handling event: {event:?}");
@@ -920,39 +1624,41 @@ impl Client {
                     // Report the playback stream.
                     if let Err(e) = self.report_playback(track_id).await {
                         error!("error streaming {track_id}: {e}");
+                        self.notify_error(&e);
                     }
 
-                    if self.is_flow() {
-                        // Extend the queue if the player is near the end.
-                        if self
-                            .queue
-                            .as_ref()
-                            .map_or(0, |queue| queue.tracks.len())
-                            .saturating_sub(self.player.position())
-                            <= 2
-                            && let Err(e) = self.extend_queue().await
-                        {
-                            error!("error extending queue: {e}");
-                        }
-                    }
+                    self.extend_radio().await;
 
-                    if let Some(command) = command.as_mut() {
-                        command
-                            .env("EVENT", "playing")
-                            .env("TRACK_ID", track_id.to_string());
+                    if run_hooks {
+                        vars.push(("EVENT", "playing".to_string()));
+                        vars.push(("TRACK_ID", track_id.to_string()));
                     }
                 }
             }
 
             Event::Pause => {
-                if let Some(command) = command.as_mut() {
-                    command.env("EVENT", "paused");
+                if run_hooks {
+                    vars.push(("EVENT", "paused".to_string()));
                 }
             }
 
             Event::TrackChanged => {
+                if self.scrobble_tx.is_some() {
+                    self.scrobble_candidate =
+                        track_id.map(|track_id| (track_id, std::time::SystemTime::now()));
+                }
+
+                // Also extend here, because the player waits past the end of
+                // a radio queue without playing when extending failed before.
+                self.extend_radio().await;
+
+                if let Err(e) = self.publish_fallback().await {
+                    error!("error publishing fallback track: {e}");
+                    self.notify_error(&e);
+                }
+
                 if let Some(track) = self.player.track()
-                    && let Some(command) = command.as_mut()
+                    && run_hooks
                 {
                     let codec = track.codec().map_or("Unknown".to_string(), |codec| {
                         codec.to_string().to_uppercase()
@@ -988,84 +1694,652 @@ impl Client {
                             / 1000.0,
                     );
 
-                    command
-                        .env("EVENT", "track_changed")
-                        .env("TRACK_TYPE", track.typ().to_string())
-                        .env("TRACK_ID", track.id().to_string())
-                        .env("ARTIST", track.artist())
-                        .env("COVER_ID", track.cover_id())
-                        .env("FORMAT", format!("{codec}{bitrate}"))
-                        .env("DECODER", decoded);
-
-                    if let Some(title) = track.title() {
-                        command.env("TITLE", title);
-                    }
-                    if let Some(album_title) = track.album_title() {
-                        command.env("ALBUM_TITLE", album_title);
+                    let metadata = Self::metadata_vars(&track, &self.metadata_fallbacks);
+                    let cover_id = metadata
+                        .iter()
+                        .find_map(|(key, value)| (*key == "COVER_ID").then_some(value.as_str()))
+                        .unwrap_or_default();
+
+                    vars.push(("EVENT", "track_changed".to_string()));
+                    vars.push(("TRACK_TYPE", track.typ().to_string()));
+                    vars.push(("TRACK_ID", track.id().to_string()));
+                    vars.push(("EXPLICIT", track.is_explicit().to_string()));
+                    if let Some(url) = CoverArt::new(cover_id, track.typ()).and_then(|cover| {
+                        cover
+                            .url(CoverArt::DEFAULT_RESOLUTION, ImageFormat::default())
+                            .ok()
+                    }) {
+                        vars.push(("COVER_URL", url.to_string()));
                     }
+                    vars.push(("FORMAT", format!("{codec}{bitrate}")));
+                    vars.push(("DECODER", decoded));
+                    vars.extend(metadata);
+
                     if let Some(duration) = track.duration() {
-                        command.env("DURATION", duration.as_secs().to_string());
+                        vars.push(("DURATION", duration.as_secs().to_string()));
+                    }
+                    if let Some(loudness) = track.loudness {
+                        vars.push(("LOUDNESS", format!("{loudness:.1}")));
+                    }
+                    if let Some(context) = self.context() {
+                        vars.push(("CONTEXT", context));
+                    }
+                    if let Some(buffer_size) = track.buffer_size {
+                        vars.push(("BUFFER_SIZE", buffer_size.to_string()));
+                    }
+                    if let Some(storage) = track.storage {
+                        vars.push(("STORAGE", storage.to_string()));
+                    }
+                }
+            }
+
+            Event::NextTrackPreloaded => {
+                if let Some(track) = self.player.next_track()
+                    && run_hooks
+                {
+                    vars.push(("EVENT", "next_track_preloaded".to_string()));
+                    vars.push(("TRACK_ID", track.id().to_string()));
+                }
+            }
+
+            Event::NextTrackBuffered => {
+                if let Some(track) = self.player.next_track()
+                    && run_hooks
+                {
+                    vars.push(("EVENT", "next_track_buffered".to_string()));
+                    vars.push(("TRACK_ID", track.id().to_string()));
+                }
+            }
+
+            Event::PlaybackStarted => {
+                if let Some(queue) = &self.queue
+                    && run_hooks
+                {
+                    vars.push(("EVENT", "playback_started".to_string()));
+                    if let Some(context) = queue.contexts.first() {
+                        vars.push(("CONTEXT_ID", context.container.context_id.clone()));
+                    }
+                    if let Some(context) = self.context() {
+                        vars.push(("CONTEXT", context));
                     }
+                    vars.push(("QUEUE_SIZE", queue.tracks.len().to_string()));
+                    vars.push(("SHUFFLED", queue.shuffled.to_string()));
                 }
             }
 
             Event::Connected => {
-                if let Some(command) = command.as_mut() {
-                    command
-                        .env("EVENT", "connected")
-                        .env("USER_ID", self.user_id().to_string())
-                        .env("USER_NAME", self.gateway.user_name().unwrap_or_default());
+                if run_hooks {
+                    vars.push(("EVENT", "connected".to_string()));
+                    vars.push(("USER_ID", self.user_id().to_string()));
+                    vars.push((
+                        "USER_NAME",
+                        self.gateway.user_name().unwrap_or_default().to_string(),
+                    ));
                 }
             }
 
             Event::Disconnected => {
-                if let Some(command) = command.as_mut() {
-                    command.env("EVENT", "disconnected");
+                if run_hooks {
+                    vars.push(("EVENT", "disconnected".to_string()));
+                }
+            }
+
+            Event::LoggedIn => {
+                if run_hooks {
+                    vars.push(("EVENT", "logged_in".to_string()));
+                    vars.push(("USER_ID", self.user_id().to_string()));
+                }
+            }
+
+            Event::TokenRefreshed => {
+                if run_hooks {
+                    vars.push(("EVENT", "token_refreshed".to_string()));
+                }
+            }
+
+            Event::TokenRefreshFailed { message } => {
+                if run_hooks {
+                    vars.push(("EVENT", "token_refresh_failed".to_string()));
+                    vars.push(("ERROR_MESSAGE", message));
+                }
+            }
+
+            Event::LicenseForbidden => {
+                // The player cannot renew the license token itself.
+                self.renew_license().await;
+            }
+
+            Event::Error { kind, message } => {
+                if run_hooks {
+                    vars.push(("EVENT", "error".to_string()));
+                    vars.push(("ERROR_KIND", format!("{kind:?}")));
+                    vars.push(("ERROR_MESSAGE", message));
                 }
             }
         }
 
-        if let Some(command) = command.as_mut() {
-            match command.spawn() {
-                Ok(mut child) => match child.wait().await {
-                    Ok(status) => {
-                        if !status.success() {
-                            error!(
-                                "hook script exited with error {}",
-                                status.code().unwrap_or(-1)
-                            );
-                        }
+        if let Some(hook_tx) = &self.hook_tx
+            && !vars.is_empty()
+            && let Err(e) = hook_tx.send(vars)
+        {
+            error!("failed to send event to hook scripts: {e}");
+        }
+
+        self.publish_state();
+    }
+
+    /// Waits for the output device to appear, without blocking the runtime.
+    ///
+    /// Errors are left to opening the device, which reports them.
+    async fn wait_for_device(&self) {
+        if let Err(e) = self.player.wait_for_device().await {
+            debug!("output device not available: {e}");
+        }
+    }
+
+    /// Handles a playback control from the local device.
+    ///
+    /// Applies the control to the player and reports the new state to the
+    /// controller right away, like controls from the controller itself.
+    /// Skipping follows the order of the player queue, so it respects
+    /// shuffle mode.
+    async fn handle_control(&mut self, control: Control) {
+        debug!("handling control: {control:?}");
+
+        if matches!(control, Control::Play | Control::PlayPause) {
+            self.wait_for_device().await;
+        }
+
+        let result = match control {
+            Control::Play => self.player.play(),
+            Control::Pause => {
+                self.player.pause();
+                Ok(())
+            }
+            Control::PlayPause => self.player.set_playing(!self.player.is_playing()),
+            Control::Next => {
+                if self.player.next_track().is_some() {
+                    self.player
+                        .skip_to(self.player.position().saturating_add(1))
+                        .await;
+                }
+                Ok(())
+            }
+            Control::Previous => {
+                self.player
+                    .skip_to(self.player.position().saturating_sub(1))
+                    .await;
+                Ok(())
+            }
+            Control::Seek(position) => match self.player.duration() {
+                Some(duration) if !duration.is_zero() => self
+                    .player
+                    .set_progress(Percentage::from_ratio(position.div_duration_f32(duration))),
+                _ => Err(Error::unavailable("cannot seek without a duration")),
+            },
+            Control::ChangeVolume(delta) => {
+                let percent = self.player.volume().as_percent() + f32::from(delta);
+                let volume = Percentage::from_percent(percent.clamp(0.0, 100.0));
+
+                // A local volume change ends the initial volume, like one from the controller.
+                if let InitialVolume::Active(initial_volume) = self.initial_volume {
+                    self.initial_volume = InitialVolume::Inactive(initial_volume);
+                }
+                self.player.set_volume(volume);
+                Ok(())
+            }
+            Control::Duck { percent, fade } => {
+                self.player.duck(f32::from(percent) / 100.0, fade).await;
+                Ok(())
+            }
+            Control::Unduck(fade) => {
+                self.player.unduck(fade).await;
+                Ok(())
+            }
+        };
+
+        if let Err(e) = result {
+            error!("error handling {control:?}: {e}");
+            self.notify_error(&e);
+        }
+
+        if self.is_connected()
+            && let Err(e) = self.report_playback_progress().await
+        {
+            error!("error reporting playback progress: {e}");
+        }
+
+        self.publish_state();
+    }
+
+    /// Returns a sender to control playback from the local device.
+    ///
+    /// Controls are handled while the client is running.
+    #[must_use]
+    pub fn control_sender(&self) -> tokio::sync::mpsc::UnboundedSender<Control> {
+        self.control_tx.clone()
+    }
+
+    /// Returns a receiver of the playback state.
+    ///
+    /// The state is updated on playback events and progress reports.
+    #[must_use]
+    pub fn subscribe_state(&self) -> tokio::sync::watch::Receiver<PlaybackState> {
+        self.state_tx.subscribe()
+    }
+
+    /// Publishes the current playback state to subscribers.
+    fn publish_state(&self) {
+        let track = self.player.track();
+        let duration = track.and_then(Track::duration);
+        let elapsed = match (self.player.progress(), duration) {
+            (Some(progress), Some(duration)) => duration.mul_f32(progress.as_ratio()),
+            _ => Duration::ZERO,
+        };
+
+        let state = PlaybackState {
+            playing: self.player.is_playing(),
+            track_id: track.map(Track::id),
+            title: track.and_then(|track| track.title().map(ToString::to_string)),
+            artist: track.map(|track| track.artist().to_string()),
+            album: track.and_then(|track| track.album_title().map(ToString::to_string)),
+            duration,
+            cover_url: track.and_then(|track| {
+                track.cover_art().and_then(|cover| {
+                    cover
+                        .url(CoverArt::DEFAULT_RESOLUTION, ImageFormat::default())
+                        .ok()
+                })
+            }),
+            elapsed,
+            updated_at: Some(std::time::Instant::now()),
+            key_source: self.player.bf_secret_source(),
+            loudness_active: self.player.is_loudness_active(),
+            requested_quality: track.map_or(AudioQuality::Unknown, Track::requested_quality),
+            quality: track.map_or(AudioQuality::Unknown, Track::quality),
+        };
+
+        self.state_tx.send_replace(state);
+
+        let unavailable_tracks = self.player.unavailable_count();
+        self.stats_tx.send_if_modified(|stats| {
+            let modified = stats.unavailable_tracks != unavailable_tracks;
+            stats.unavailable_tracks = unavailable_tracks;
+            modified
+        });
+    }
+
+    /// Scrobbles the current track once it has played long enough.
+    ///
+    /// Each track is scrobbled at most once per time it starts playing.
+    /// Livestreams and tracks shorter than [`scrobble::MIN_DURATION`] are
+    /// never scrobbled.
+    fn scrobble(&mut self) {
+        let Some((track_id, started_at)) = self.scrobble_candidate else {
+            return;
+        };
+        let Some(track) = self.player.track().filter(|track| track.id() == track_id) else {
+            return;
+        };
+
+        let Some((duration, threshold)) = track.duration().and_then(|duration| {
+            scrobble::threshold(duration, self.scrobble_percent, self.scrobble_after)
+                .map(|threshold| (duration, threshold))
+        }) else {
+            self.scrobble_candidate = None;
+            return;
+        };
+
+        let elapsed = self.player.progress().map_or(Duration::ZERO, |progress| {
+            duration.mul_f32(progress.as_ratio())
+        });
+        if elapsed < threshold {
+            return;
+        }
+
+        let scrobble = Scrobble {
+            track_id: track_id.to_string(),
+            track_type: track.typ().to_string(),
+            artist: track.artist().to_string(),
+            title: track.title().map(ToString::to_string),
+            album: track.album_title().map(ToString::to_string),
+            duration: duration.as_secs(),
+            timestamp: started_at
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        };
+        self.scrobble_candidate = None;
+
+        if let Some(scrobble_tx) = &self.scrobble_tx
+            && let Err(e) = scrobble_tx.send(scrobble)
+        {
+            error!("failed to send scrobble: {e}");
+        }
+    }
+
+    /// Runs hook scripts for events received on `hook_rx`.
+    ///
+    /// Events are processed one at a time, and hooks in the order they
+    /// were configured, so hooks see events in the order they occurred.
+    /// Returns when the sending half of the channel is dropped.
+    async fn run_hooks(
+        hooks: Vec<String>,
+        timeout: Duration,
+        mut hook_rx: tokio::sync::mpsc::UnboundedReceiver<HookVars>,
+    ) {
+        while let Some(vars) = hook_rx.recv().await {
+            for hook in &hooks {
+                if let Some(command) = Self::hook_command(hook, &vars) {
+                    Self::run_hook(hook, command, timeout).await;
+                }
+            }
+        }
+    }
+
+    /// Executes a hook and waits for it to finish.
+    ///
+    /// The hook is killed when it is still running after `timeout`, so
+    /// hung scripts do not accumulate. The child process is always reaped.
+    async fn run_hook(hook: &str, mut command: Command, timeout: Duration) {
+        let mut child = match command.kill_on_drop(true).spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                error!("failed to spawn hook script {hook}: {e}");
+                return;
+            }
+        };
+
+        match tokio::time::timeout(timeout, child.wait()).await {
+            Ok(Ok(status)) => {
+                if !status.success() {
+                    error!(
+                        "hook script {hook} exited with error {}",
+                        status.code().unwrap_or(-1)
+                    );
+                }
+            }
+            Ok(Err(e)) => error!("failed to wait for hook script {hook}: {e}"),
+            Err(_) => {
+                warn!(
+                    "hook script {hook} did not finish within {}s; killing it",
+                    timeout.as_secs()
+                );
+                // Kills and then waits for the child, so it is reaped.
+                if let Err(e) = child.kill().await {
+                    error!("failed to kill hook script {hook}: {e}");
+                }
+            }
+        }
+    }
+
+    /// Returns the artist, cover ID, title and album title of a track as hook
+    /// variables.
+    ///
+    /// Missing or empty metadata is replaced by the configured placeholders.
+    /// Artist and cover ID are always set, if need be to an empty string.
+    /// Only songs have albums, so only they get an album title placeholder.
+    fn metadata_vars(track: &Track, fallbacks: &MetadataFallbacks) -> HookVars {
+        let with_fallback = |value: Option<&str>, fallback: Option<&str>| {
+            value
+                .filter(|value| !value.is_empty())
+                .or(fallback)
+                .map(ToString::to_string)
+        };
+
+        let mut vars = vec![
+            (
+                "ARTIST",
+                with_fallback(Some(track.artist()), fallbacks.artist.as_deref())
+                    .unwrap_or_default(),
+            ),
+            (
+                "COVER_ID",
+                with_fallback(Some(track.cover_id()), fallbacks.cover_id.as_deref())
+                    .unwrap_or_default(),
+            ),
+        ];
+
+        if let Some(title) = with_fallback(track.title(), fallbacks.title.as_deref()) {
+            vars.push(("TITLE", title));
+        }
+
+        let album_fallback = fallbacks
+            .album_title
+            .as_deref()
+            .filter(|_| track.typ() == TrackType::Song);
+        if let Some(album_title) = with_fallback(track.album_title(), album_fallback) {
+            vars.push(("ALBUM_TITLE", album_title));
+        }
+
+        vars
+    }
+
+    /// Builds the command to execute a hook.
+    ///
+    /// The hook is split on whitespace into a program and its arguments.
+    /// If the complete hook is an existing file, it is run as a program
+    /// without arguments, so paths containing spaces keep working.
+    ///
+    /// Arguments may contain `{VARIABLE}` placeholders, that are replaced by
+    /// the value of the corresponding event variable, or an empty string if
+    /// the variable is not set for this event. Placeholders must be in upper
+    /// case; other braces are passed verbatim. Since substitution happens
+    /// after splitting, values are passed as a single argument without the
+    /// need for escaping. All variables are also set in the environment.
+    ///
+    /// Returns `None` if the hook is empty.
+    fn hook_command(hook: &str, vars: &HookVars) -> Option<Command> {
+        let mut command = if std::path::Path::new(hook).is_file() {
+            Command::new(hook)
+        } else {
+            let mut words = hook.split_whitespace();
+            let mut command = Command::new(words.next()?);
+            for word in words {
+                let mut arg = String::with_capacity(word.len());
+                let mut rest = word;
+                while let Some(start) = rest.find('{')
+                    && let Some(len) = rest[start..].find('}')
+                {
+                    let name = &rest[start + 1..start + len];
+                    arg.push_str(&rest[..start]);
+                    if !name.is_empty()
+                        && name
+                            .chars()
+                            .all(|chr| chr.is_ascii_uppercase() || chr == '_')
+                    {
+                        let value = vars
+                            .iter()
+                            .find(|(key, _)| *key == name)
+                            .map_or("", |(_, value)| value.as_str());
+                        arg.push_str(value);
+                    } else {
+                        // Not a variable: keep it verbatim.
+                        arg.push_str(&rest[start..=start + len]);
                     }
-                    Err(e) => error!("failed to wait for hook script: {e}"),
-                },
-                Err(e) => error!("failed to spawn hook script: {e}"),
+                    rest = &rest[start + len + 1..];
+                }
+                arg.push_str(rest);
+                command.arg(arg);
             }
+            command
+        };
+
+        command.envs(vars.iter().map(|(key, value)| (*key, value)));
+        Some(command)
+    }
+
+    /// Emits a non-fatal error event.
+    ///
+    /// Allows library consumers and hook scripts to react to errors
+    /// that are otherwise only logged. Failures to send are ignored,
+    /// as the error has already been logged by the caller.
+    fn notify_error(&self, e: &Error) {
+        let _ = self.event_tx.send(Event::from(e));
+    }
+
+    /// Returns the radio that the current queue plays, if any.
+    ///
+    /// Examines the first queue context to identify radios by their mix type:
+    /// * User mixes are Flow
+    /// * Artist and song mixes are radios of the artist or track with the ID
+    ///   at the end of the context ID
+    ///
+    /// # Returns
+    ///
+    /// * `Some(radio)` - Queue is a radio
+    /// * `None` - Queue is not a radio or no queue exists
+    fn radio(&self) -> Option<Radio> {
+        let container = &self.queue.as_ref()?.contexts.first()?.container;
+        let id = || {
+            let digits = container
+                .context_id
+                .rsplit(|c: char| !c.is_ascii_digit())
+                .next()?;
+            digits.parse::<u64>().ok()
+        };
+
+        match container.mix.typ.enum_value_or_default() {
+            MixType::MIX_TYPE_USER => Some(Radio::Flow),
+            MixType::MIX_TYPE_ARTIST => id().map(Radio::Artist),
+            MixType::MIX_TYPE_SONG => id()
+                .and_then(|id| i64::try_from(id).ok())
+                .and_then(TrackId::new)
+                .map(Radio::Track),
+            _ => None,
+        }
+    }
+
+    /// Returns the shuffle order of the current queue.
+    ///
+    /// The element at each position of the shuffled queue, as shown by the
+    /// controller, is the position of that track in the original order, as
+    /// played by the player.
+    ///
+    /// Returns `None` when no queue is set or the queue is not shuffled.
+    #[must_use]
+    pub fn shuffle_order(&self) -> Option<&[u32]> {
+        self.queue
+            .as_ref()
+            .filter(|queue| queue.shuffled)
+            .map(|queue| queue.tracks_order.as_slice())
+    }
+
+    /// Returns the position in the shuffled queue of the track at `position`
+    /// in the original order.
+    ///
+    /// When the queue is not shuffled, both orders are the same. Tracks that
+    /// were appended to a shuffled queue are not shuffled, so they keep
+    /// their position. Returns `None` when no queue is set.
+    #[must_use]
+    pub fn shuffled_position(&self, position: usize) -> Option<usize> {
+        let queue = self.queue.as_ref()?;
+        if !queue.shuffled {
+            return Some(position);
         }
+
+        Some(
+            queue
+                .tracks_order
+                .iter()
+                .position(|original| *original as usize == position)
+                .unwrap_or(position),
+        )
+    }
+
+    /// Returns the position in the original order of the track at `position`
+    /// in the shuffled queue.
+    ///
+    /// This is the inverse of [`shuffled_position`](Self::shuffled_position).
+    /// Returns `None` when no queue is set.
+    #[must_use]
+    pub fn original_position(&self, position: usize) -> Option<usize> {
+        let queue = self.queue.as_ref()?;
+        if !queue.shuffled {
+            return Some(position);
+        }
+
+        Some(
+            queue
+                .tracks_order
+                .get(position)
+                .map_or(position, |original| *original as usize),
+        )
     }
 
-    /// Returns whether current queue is a Flow (personalized radio).
-    ///
-    /// Examines queue context to identify Flow queues by checking:
-    /// * Queue has contexts
-    /// * First context is a user mix
+    /// Returns a human-readable name of what the current queue plays from.
     ///
-    /// # Returns
+    /// Queue contexts only carry identifiers and types, so the name is
+    /// taken from the current track where possible:
+    /// * Albums use the album title
+    /// * Podcasts and live radio use the show or station name
+    /// * Other contexts are described by their type, like "Flow" or "Playlist"
     ///
-    /// * `true` - Queue is a Flow queue
-    /// * `false` - Queue is not Flow or no queue exists
+    /// Returns `None` when no queue is set or the context is unknown.
+    #[must_use]
+    pub fn context(&self) -> Option<String> {
+        use queue::ContainerType::{
+            CONTAINER_TYPE_ALBUM, CONTAINER_TYPE_LIVE, CONTAINER_TYPE_MIX, CONTAINER_TYPE_PERSONAL,
+            CONTAINER_TYPE_PLAYLIST, CONTAINER_TYPE_PODCAST, CONTAINER_TYPE_RECOMMENDED,
+            CONTAINER_TYPE_SHUFFLE_MY_MUSIC, CONTAINER_TYPE_SMART_TRACKLIST,
+            CONTAINER_TYPE_TOP_TRACKS,
+        };
+
+        let container = &self.queue.as_ref()?.contexts.first()?.container;
+        let track = self.player.track();
+
+        let name = match container.typ.enum_value_or_default() {
+            CONTAINER_TYPE_ALBUM => track
+                .and_then(Track::album_title)
+                .unwrap_or("Album")
+                .to_string(),
+            CONTAINER_TYPE_LIVE | CONTAINER_TYPE_PODCAST => track
+                .map(Track::artist)
+                .filter(|name| !name.is_empty())?
+                .to_string(),
+            CONTAINER_TYPE_MIX => match container.mix.typ.enum_value_or_default() {
+                MixType::MIX_TYPE_USER => "Flow",
+                MixType::MIX_TYPE_ARTIST => "Artist radio",
+                MixType::MIX_TYPE_SONG => "Track radio",
+                MixType::MIX_TYPE_GENRE => "Genre mix",
+                _ => "Mix",
+            }
+            .to_string(),
+            CONTAINER_TYPE_PERSONAL => "Favorite tracks".to_string(),
+            CONTAINER_TYPE_PLAYLIST => "Playlist".to_string(),
+            CONTAINER_TYPE_RECOMMENDED => "Recommendations".to_string(),
+            CONTAINER_TYPE_SHUFFLE_MY_MUSIC => "Shuffle my music".to_string(),
+            CONTAINER_TYPE_SMART_TRACKLIST => "Smart tracklist".to_string(),
+            CONTAINER_TYPE_TOP_TRACKS => "Top tracks".to_string(),
+            _ => return None,
+        };
+
+        Some(name)
+    }
+
+    /// Returns whether current queue is a radio that is extended as it plays.
     #[inline]
-    fn is_flow(&self) -> bool {
-        self.queue.as_ref().is_some_and(|queue| {
-            queue
-                .contexts
-                .first()
-                .unwrap_or_default()
-                .container
-                .mix
-                .typ
-                .enum_value_or_default()
-                == MixType::MIX_TYPE_USER
-        })
+    fn is_radio(&self) -> bool {
+        self.radio().is_some()
+    }
+
+    /// Handles the event loop resuming after the process was suspended.
+    ///
+    /// While suspended, the controller could not have been heard, so an
+    /// expired receive watchdog does not mean that the controller is gone.
+    /// Instead of disconnecting, the watchdogs are reset and a heartbeat is
+    /// sent right away, giving the controller a full timeout to respond.
+    fn handle_time_jump(&mut self, gap: Duration) {
+        warn!(
+            "event loop was suspended for {:.1}s; resuming",
+            gap.as_secs_f32()
+        );
+
+        if self.is_connected() {
+            self.reset_watchdog_rx();
+            self.watchdog_tx.as_mut().reset(tokio::time::Instant::now());
+            self.reset_reporting_timer();
+        }
     }
 
     /// Resets the receive watchdog timer.
@@ -1191,6 +2465,12 @@ impl Client {
 
     /// Reports track playback to Deezer.
     ///
+    /// Skipped in private listening mode. Progress reports to the controller
+    /// are not affected, as the controller needs them to show the state.
+    ///
+    /// Plays of the same track are reported at most once per minimum report
+    /// interval, like when repeating a track or resuming it after a pause.
+    ///
     /// # Arguments
     ///
     /// * `track_id` - ID of track being played
@@ -1201,6 +2481,19 @@ impl Client {
     /// * No active connection
     /// * Message send fails
     async fn report_playback(&mut self, track_id: TrackId) -> Result<()> {
+        if self.private_listening {
+            trace!("not reporting playback of {track_id}: private listening");
+            return Ok(());
+        }
+
+        if let Some((last_track_id, reported_at)) = self.last_report
+            && last_track_id == track_id
+            && reported_at.elapsed() < self.min_report_interval
+        {
+            trace!("not reporting playback of {track_id}: reported recently");
+            return Ok(());
+        }
+
         if let ConnectionState::Connected { session_id, .. } = &self.connection_state {
             let message = Message::StreamSend {
                 channel: self.channel(Ident::Stream),
@@ -1215,7 +2508,9 @@ impl Client {
                 },
             };
 
-            self.send_message(message).await
+            self.send_message(message).await?;
+            self.last_report = Some((track_id, tokio::time::Instant::now()));
+            Ok(())
         } else {
             Err(Error::failed_precondition(
                 "playback reporting should have an active connection".to_string(),
@@ -1223,6 +2518,24 @@ impl Client {
         }
     }
 
+    /// Stops playback, clears the queue and releases the output device.
+    ///
+    /// Unlike disconnecting, the controller stays connected, so it can
+    /// publish a new queue to play.
+    async fn stop_playback(&mut self) {
+        info!("stopping playback and clearing the queue");
+
+        self.player.shutdown().await;
+        self.player.set_queue(Vec::new());
+        self.queue = None;
+        self.deferred_position = None;
+        self.scrobble_candidate = None;
+
+        if let Err(e) = self.event_tx.send(Event::Pause) {
+            error!("failed to send pause event: {e}");
+        }
+    }
+
     /// Disconnects from the current controller.
     ///
     /// Sends a close message to the controller and resets connection state.
@@ -1238,6 +2551,113 @@ impl Client {
         result
     }
 
+    /// Prefetches the next track in the queue.
+    ///
+    /// Starts preloading the next track without waiting until the current
+    /// track is about to end, for example when the user is about to skip.
+    /// See [`Player::prefetch_next`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the next track fails to load.
+    pub async fn prefetch_next(&mut self) -> Result<()> {
+        self.player.prefetch_next().await
+    }
+
+    /// Refreshes the access tokens of the tracks in the queue.
+    ///
+    /// Track tokens expire, so after a long pause every track in the queue
+    /// may fail to download. This fetches the queue again and updates the
+    /// tokens of the tracks in place, so that playback can resume without
+    /// the controller publishing the queue again.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// * Gateway request fails or times out
+    pub async fn refresh_tokens(&mut self) -> Result<()> {
+        let Some(list) = self.queue.as_ref() else {
+            return Ok(());
+        };
+
+        let queue =
+            tokio::time::timeout(Self::NETWORK_TIMEOUT, self.gateway.list_to_queue(list)).await??;
+        let tracks: Vec<_> = queue.into_iter().map(Track::from).collect();
+
+        let refreshed = self.player.refresh_tokens(&tracks);
+        info!("refreshed tokens of {refreshed} tracks");
+
+        Ok(())
+    }
+
+    /// Starts playing a radio on local request.
+    ///
+    /// Replaces the queue with recommendations of the radio, which is
+    /// extended as it plays like Flow, and publishes it to the controller.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// * No controller is connected
+    /// * Fetching the recommendations fails
+    /// * Publishing the queue fails
+    /// * Playback cannot start
+    pub async fn play_radio(&mut self, radio: Radio) -> Result<()> {
+        if !self.is_connected() {
+            return Err(Error::failed_precondition(
+                "playing a radio requires an active connection",
+            ));
+        }
+
+        let (typ, context_id) = match radio {
+            Radio::Flow => (MixType::MIX_TYPE_USER, self.user_id().to_string()),
+            Radio::Artist(artist_id) => (MixType::MIX_TYPE_ARTIST, artist_id.to_string()),
+            Radio::Track(track_id) => (MixType::MIX_TYPE_SONG, track_id.to_string()),
+        };
+        info!("playing {radio:?}");
+
+        let mut mix = queue::Mix::new();
+        mix.typ = typ.into();
+        let mut container = queue::Container::new();
+        container.context_id = context_id;
+        container.typ = queue::ContainerType::CONTAINER_TYPE_MIX.into();
+        container.mix = Some(mix).into();
+        let mut context = queue::Context::new();
+        context.container = Some(container).into();
+
+        let mut list = queue::List::new();
+        list.id = Uuid::new_v4().to_string();
+        list.contexts.push(context);
+
+        self.queue = Some(list);
+        self.player.set_queue(Vec::new());
+        self.player.set_extendable(true);
+        self.extend_queue().await?;
+
+        self.player.set_position(0);
+        self.wait_for_device().await;
+        self.player.play()
+    }
+
+    /// Disconnects the current controller on local request.
+    ///
+    /// Sends a close message to the controller, stops playback and makes the
+    /// device available for discovery again, for example to hand control to
+    /// another device. Does nothing when no controller is connected.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// * Sending a close message fails
+    pub async fn disconnect_controller(&mut self) -> Result<()> {
+        if self.controller().is_none() {
+            return Ok(());
+        }
+
+        info!("disconnecting controller on local request");
+        self.disconnect().await
+    }
+
     /// Handles device discovery request from a controller.
     ///
     /// Creates and caches a connection offer, then sends it to the requesting controller.
@@ -1302,6 +2722,8 @@ impl Client {
     /// * Client is available for connections
     /// * Required channel subscriptions succeed
     ///
+    /// In announce-only mode, every connection is declined.
+    ///
     /// Note: Offer ID is ignored as controllers may use old offers.
     /// What matters is that the request is directed at this device.
     ///
@@ -1317,6 +2739,11 @@ impl Client {
     /// * Channel subscription fails
     /// * Message send fails
     async fn handle_connect(&mut self, from: DeviceId, _offer_id: Option<String>) -> Result<()> {
+        if self.announce_only {
+            info!("declining connection from {from}: announce only");
+            return Ok(());
+        }
+
         if self.discovery_state == DiscoveryState::Taken {
             debug!("not allowing interruptions from {from}");
 
@@ -1364,6 +2791,18 @@ impl Client {
         false
     }
 
+    /// Returns the protocol channels this client is subscribed to.
+    ///
+    /// Intended for debugging, for example to check whether the queue and
+    /// command channels were subscribed when a controller connects.
+    /// Channels are sorted by name for stable output.
+    #[must_use]
+    pub fn subscriptions(&self) -> Vec<Ident> {
+        let mut subscriptions: Vec<_> = self.subscriptions.iter().copied().collect();
+        subscriptions.sort_by_key(ToString::to_string);
+        subscriptions
+    }
+
     /// Returns ID of currently connected controller if any.
     ///
     /// Checks both active connections and pending connections:
@@ -1466,15 +2905,22 @@ impl Client {
                 };
 
                 info!("connected to {controller}");
+                self.health_tx.send_modify(|health| health.connected = true);
+                self.stats_tx.send_modify(|stats| stats.connects += 1);
                 if let Err(e) = self.event_tx.send(Event::Connected) {
                     error!("failed to send connected event: {e}");
                 }
 
                 // Refresh user token to reload configuration (normalization, audio quality)
                 // If token refresh fails, assume ARL expired and signal client restart
-                let (user_token, token_ttl) = match self.user_token().await {
+                let (user_token, token_ttl) = match self.user_token(Self::NETWORK_TIMEOUT).await {
                     Ok((token, ttl)) => (Ok(token), ttl),
-                    Err(e) => (Err(e), Duration::ZERO),
+                    Err(e) => {
+                        let _ = self.event_tx.send(Event::TokenRefreshFailed {
+                            message: e.to_string(),
+                        });
+                        (Err(e), Duration::ZERO)
+                    }
                 };
 
                 // Sending a zero duration will cause the client to restart.
@@ -1531,6 +2977,7 @@ impl Client {
     fn reset_states(&mut self) {
         if let Some(controller) = self.controller() {
             info!("disconnected from {controller}");
+            self.stats_tx.send_modify(|stats| stats.disconnects += 1);
 
             if let Err(e) = self.event_tx.send(Event::Disconnected) {
                 error!("failed to send disconnected event: {e}");
@@ -1551,6 +2998,57 @@ impl Client {
         // Reset the connection and discovery states.
         self.connection_state = ConnectionState::Disconnected;
         self.discovery_state = DiscoveryState::Available;
+        self.pending_ping = None;
+        self.queue_published = false;
+        self.health_tx.send_replace(Health::default());
+    }
+
+    /// Handles an acknowledgement from the controller.
+    ///
+    /// Acknowledgements of heartbeats serve as pongs, which measure the
+    /// round-trip time to the controller.
+    fn handle_acknowledgement(&mut self, acknowledgement_id: &str) {
+        if let Some((message_id, sent_at)) = self
+            .pending_ping
+            .take_if(|(message_id, _)| *message_id == acknowledgement_id)
+        {
+            let rtt = sent_at.elapsed();
+            trace!("heartbeat {message_id} acknowledged after {rtt:?}");
+            self.health_tx.send_modify(|health| {
+                health.heartbeat_rtt = Some(rtt);
+                health.last_heartbeat = Some(std::time::Instant::now());
+            });
+        }
+    }
+
+    /// Returns the liveness of the client.
+    #[must_use]
+    pub fn health(&self) -> Health {
+        self.health_tx.borrow().clone()
+    }
+
+    /// Returns the session statistics.
+    #[must_use]
+    pub fn stats(&self) -> SessionStats {
+        self.stats_tx.borrow().clone()
+    }
+
+    /// Returns a receiver of the session statistics.
+    ///
+    /// Can be subscribed to before the client starts, like
+    /// [`subscribe_health`](Self::subscribe_health).
+    #[must_use]
+    pub fn subscribe_stats(&self) -> tokio::sync::watch::Receiver<SessionStats> {
+        self.stats_tx.subscribe()
+    }
+
+    /// Returns a receiver of the liveness of the client.
+    ///
+    /// Can be subscribed to before the client starts, so a supervisor can
+    /// monitor it while the client runs.
+    #[must_use]
+    pub fn subscribe_health(&self) -> tokio::sync::watch::Receiver<Health> {
+        self.health_tx.subscribe()
     }
 
     /// Handles queue publication from controller.
@@ -1569,32 +3067,123 @@ impl Client {
     /// # Errors
     ///
     /// Returns error if:
+    /// * Container type is unknown and rejected by configuration
     /// * Queue resolution fails
     /// * Flow extension fails
     async fn handle_publish_queue(&mut self, list: queue::List) -> Result<()> {
         let shuffled = if list.shuffled { "(shuffled)" } else { "" };
         info!("setting queue to {} {shuffled}", list.id);
 
+        if let Some(context) = list.contexts.first() {
+            let typ = context.container.typ;
+            match typ.enum_value() {
+                Ok(
+                    queue::ContainerType::CONTAINER_TYPE_DEFAULT
+                    | queue::ContainerType::CONTAINER_TYPE_UNRECOGNIZED,
+                )
+                | Err(_) => {
+                    let value = typ.value();
+                    if self.reject_unknown_containers {
+                        return Err(Error::unimplemented(format!(
+                            "container type {value} not supported - report what you were trying to play to the developers"
+                        )));
+                    }
+                    warn!(
+                        "unknown container type {value} of {}; resolving from track list",
+                        context.container.context_id
+                    );
+                }
+                Ok(typ) => debug!("queue container type: {typ:?}"),
+            }
+        }
+
         // Await with timeout in order to prevent blocking the select loop.
         let queue = tokio::time::timeout(Self::NETWORK_TIMEOUT, self.gateway.list_to_queue(&list))
             .await??;
 
+        // Unknown containers may resolve to fewer tracks than were published.
+        if queue.len() < list.tracks.len() {
+            warn!(
+                "queue resolved to {} of {} tracks",
+                queue.len(),
+                list.tracks.len()
+            );
+        }
+
         let tracks: Vec<_> = queue.into_iter().map(Track::from).collect();
 
+        let context_id = |list: &queue::List| {
+            list.contexts
+                .first()
+                .map(|context| context.container.context_id.clone())
+        };
+        let new_context = !self.queue_published
+            || self
+                .queue
+                .as_ref()
+                .is_none_or(|queue| context_id(queue) != context_id(&list));
+        self.queue_published = true;
+
         self.queue = Some(list);
         self.player.set_queue(tracks);
+        self.player.set_extendable(self.is_radio());
 
         if let Some(position) = self.deferred_position.take() {
-            self.set_position(position);
+            self.set_position(position).await;
         }
 
-        if self.is_flow() {
+        if new_context && let Err(e) = self.event_tx.send(Event::PlaybackStarted) {
+            error!("failed to send playback started event: {e}");
+        }
+
+        if self.is_radio() {
             self.extend_queue().await?;
         }
 
         Ok(())
     }
 
+    /// Handles a message that is too large to be parsed.
+    ///
+    /// When the dropped message is a queue publication for this device,
+    /// responds with an error status so the controller knows the queue
+    /// was not accepted, instead of waiting for it to load indefinitely.
+    ///
+    /// The message is scanned for its type and identifier without
+    /// deserializing it, to prevent out of memory conditions.
+    async fn handle_oversized_message(&mut self, message: &WebsocketMessage) {
+        // The body is embedded as a JSON string, so quotes may be escaped.
+        static MESSAGE_TYPE: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r#"\\?"messageType\\?"\s*:\s*\\?"publishQueue\\?""#).unwrap()
+        });
+        static MESSAGE_ID: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r#"\\?"messageId\\?"\s*:\s*\\?"([^"\\]+)"#).unwrap());
+
+        if !self.is_connected() {
+            return;
+        }
+
+        let Ok(text) = message.to_text() else {
+            return;
+        };
+
+        if !MESSAGE_TYPE.is_match(text) || !text.contains(&self.device_id.to_string()) {
+            return;
+        }
+
+        if let Some(message_id) = MESSAGE_ID
+            .captures(text)
+            .and_then(|captures| captures.get(1))
+        {
+            warn!("queue is too large to process; reporting failure to controller");
+            if let Err(e) = self.send_status(message_id.as_str(), Status::Error).await {
+                error!("error sending queue status: {e}");
+            }
+
+            self.notify_error(&Error::resource_exhausted("queue is too large to process"));
+        }
+    }
+
     /// Sends ping message to controller.
     ///
     /// Part of connection keepalive mechanism.
@@ -1606,9 +3195,9 @@ impl Client {
     /// * Message send fails
     async fn send_ping(&mut self) -> Result<()> {
         if let Some(controller) = self.controller() {
-            let ping = Body::Ping {
-                message_id: Uuid::new_v4().to_string(),
-            };
+            let message_id = Uuid::new_v4().to_string();
+            self.pending_ping = Some((message_id.clone(), std::time::Instant::now()));
+            let ping = Body::Ping { message_id };
 
             let command = self.command(controller.clone(), ping);
             return self.send_message(command).await;
@@ -1619,10 +3208,10 @@ impl Client {
         ))
     }
 
-    /// Extends Flow queue and notifies controller.
+    /// Extends a radio queue and notifies controller.
     ///
-    /// Fetches more personalized recommendations when:
-    /// * Current queue is Flow
+    /// Fetches more recommendations when:
+    /// * Current queue is a radio
     /// * Near end of current tracks
     ///
     /// Updates both local state and remote controller by:
@@ -1637,13 +3226,29 @@ impl Client {
     /// * No active queue exists
     /// * Track fetch fails
     /// * Controller communication fails
+    #[expect(clippy::cast_possible_truncation)]
     async fn extend_queue(&mut self) -> Result<()> {
         let user_id = self.user_id();
+        let radio = self.radio();
 
         if let Some(list) = self.queue.as_mut() {
-            let new_queue =
-                tokio::time::timeout(Self::NETWORK_TIMEOUT, self.gateway.user_radio(user_id))
-                    .await??;
+            let new_queue = match radio {
+                Some(Radio::Artist(artist_id)) => {
+                    tokio::time::timeout(
+                        Self::NETWORK_TIMEOUT,
+                        self.gateway.artist_radio(artist_id),
+                    )
+                    .await??
+                }
+                Some(Radio::Track(track_id)) => {
+                    tokio::time::timeout(Self::NETWORK_TIMEOUT, self.gateway.track_radio(track_id))
+                        .await??
+                }
+                Some(Radio::Flow) | None => {
+                    tokio::time::timeout(Self::NETWORK_TIMEOUT, self.gateway.user_radio(user_id))
+                        .await??
+                }
+            };
 
             let new_tracks: Vec<_> = new_queue.into_iter().map(Track::from).collect();
 
@@ -1657,6 +3262,15 @@ impl Client {
 
             debug!("extending queue with {} tracks", new_tracks.len());
 
+            // The player queue is in the original order, so when shuffled, the new tracks map to
+            // the end of the original order. They are appended unshuffled, so the tracks that are
+            // already queued keep their positions.
+            if list.shuffled {
+                let offset = list.tracks.len();
+                list.tracks_order
+                    .extend((offset..offset + new_list.len()).map(|position| position as u32));
+            }
+
             list.tracks.extend(new_list);
             self.player.extend_queue(new_tracks);
             self.refresh_queue().await
@@ -1667,6 +3281,66 @@ impl Client {
         }
     }
 
+    /// Publishes the substitution of the current track by its fallback.
+    ///
+    /// When a fallback version of a track plays, progress is reported with
+    /// the ID of the fallback. The queue entry is updated to match, and the
+    /// queue is refreshed so the controller shows the track that is playing.
+    /// Does nothing when no fallback is playing or it was published already.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if refreshing the queue fails.
+    async fn publish_fallback(&mut self) -> Result<()> {
+        let position = self.player.position();
+        let Some(track) = self.player.track() else {
+            return Ok(());
+        };
+        let Some(original_id) = track.original_id() else {
+            return Ok(());
+        };
+        let track_id = track.id().to_string();
+
+        let Some(entry) = self
+            .queue
+            .as_mut()
+            .and_then(|queue| queue.tracks.get_mut(position))
+        else {
+            return Ok(());
+        };
+        if entry.id == track_id {
+            return Ok(());
+        }
+
+        info!("playing {track_id} instead of {original_id}");
+        entry.id = track_id;
+        if self.controller().is_some() {
+            self.refresh_queue().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Extends a radio queue when the player is near the end.
+    ///
+    /// Radio queues are extended with new recommendations rather than
+    /// wrapping around, regardless of the repeat mode. Does nothing for
+    /// other queues.
+    async fn extend_radio(&mut self) {
+        if self.is_radio()
+            && self
+                .queue
+                .as_ref()
+                .map_or(0, |queue| queue.tracks.len())
+                .saturating_sub(self.player.position())
+                <= 2
+            && let Err(e) = self.extend_queue().await
+        {
+            error!("error extending queue: {e}");
+            self.notify_error(&e);
+        }
+    }
+
     /// Publishes updated queue to controller and requests UI refresh.
     ///
     /// Called after queue modifications to:
@@ -1795,6 +3469,25 @@ impl Client {
         ))
     }
 
+    /// Returns the status to respond to a `Skip` message with right after
+    /// acknowledging it.
+    ///
+    /// The skip during the handshake is ahead of the queue publication, so
+    /// there is nothing to skip to yet. It is responded to with
+    /// `handshake_status` right away. This is decided on whether a queue was
+    /// published since connecting, and not on the loaded track: a track may
+    /// still be loaded from a previous controller, and a queue may be
+    /// published without a track being loaded yet, like when all tracks are
+    /// unavailable. Neither is it decided on the current queue, which is
+    /// cleared when stopping with `full_stop`.
+    ///
+    /// Returns `None` for other skips, which are responded to with
+    /// [`Status::OK`] after the player state is updated.
+    #[must_use]
+    fn immediate_skip_status(queue_published: bool, handshake_status: Status) -> Option<Status> {
+        (!queue_published).then_some(handshake_status)
+    }
+
     /// Handles skip command from controller.
     ///
     /// Updates player state according to skip parameters:
@@ -1812,6 +3505,12 @@ impl Client {
     /// 4. Reports playback progress
     /// 5. Sends status to controller
     ///
+    /// The first skip is received during the handshake, before the queue is
+    /// published. Its status is sent right after the acknowledgement, so that
+    /// controllers are not kept waiting on the state updates. The protocol
+    /// expects an error status here, but this is configurable for
+    /// controllers that expect otherwise.
+    ///
     /// # Arguments
     ///
     /// * `message_id` - Command ID for acknowledgement
@@ -1847,6 +3546,25 @@ impl Client {
         if self.controller().is_some() {
             self.send_acknowledgement(message_id).await?;
 
+            let immediate_status =
+                Self::immediate_skip_status(self.queue_published, self.handshake_skip_status);
+            if let Some(status) = immediate_status {
+                debug!("responding to handshake skip with {status}");
+                self.send_status(message_id, status).await?;
+            }
+
+            if should_play == Some(true) {
+                self.wait_for_device().await;
+            }
+
+            // Tokens expire during long pauses, so refresh them before resuming.
+            if should_play == Some(true)
+                && self.player.track().is_some_and(Track::is_expired)
+                && let Err(e) = self.refresh_tokens().await
+            {
+                error!("error refreshing track tokens: {e}");
+            }
+
             // Remember to refresh the queue if the shuffle mode changes.
             let refresh_queue = self.queue.as_ref().map(|queue| queue.shuffled) != set_shuffle;
 
@@ -1862,6 +3580,7 @@ impl Client {
                     set_repeat_mode,
                     set_volume,
                 )
+                .await
                 .is_err()
             {
                 // If we failed to set the player state, we disconnect.
@@ -1874,6 +3593,7 @@ impl Client {
                 && let Err(e) = self.refresh_queue().await
             {
                 error!("error refreshing queue: {e}");
+                self.notify_error(&e);
             }
 
             // Report playback progress regardless of the state setting result - it can be that
@@ -1882,15 +3602,10 @@ impl Client {
                 error!("error reporting playback progress: {e}");
             }
 
-            // The status response to the first skip, that is received during the initial handshake
-            // ahead of the queue publication, should be "1" (Error).
-            let status = if self.queue.is_some() {
-                Status::OK
-            } else {
-                Status::Error
-            };
-
-            self.send_status(message_id, status).await?;
+            // The status response to the handshake skip was already sent.
+            if immediate_status.is_none() {
+                self.send_status(message_id, Status::OK).await?;
+            }
 
             Ok(())
         } else {
@@ -1910,9 +3625,9 @@ impl Client {
     ///
     /// * `position` - Target position in the queue (in display order)
     ///
-    /// After position calculation, updates the player's actual queue position.
-    #[inline]
-    fn set_position(&mut self, position: usize) {
+    /// After position calculation, skips the player to the actual queue
+    /// position, fading through silence if enabled.
+    async fn set_position(&mut self, position: usize) {
         let mut position = position;
         if let Some(queue) = self.queue.as_ref()
             && queue.shuffled
@@ -1921,7 +3636,7 @@ impl Client {
             position = *ordered as usize;
         }
 
-        self.player.set_position(position);
+        self.player.skip_to(position).await;
     }
 
     /// Updates player state based on controller commands.
@@ -1960,7 +3675,7 @@ impl Client {
     ///
     /// Returns error if any update fails, but attempts all updates regardless
     #[expect(clippy::too_many_arguments)]
-    pub fn set_player_state(
+    pub async fn set_player_state(
         &mut self,
         queue_id: Option<&str>,
         item: Option<QueueItem>,
@@ -1984,7 +3699,7 @@ impl Client {
                 .as_ref()
                 .is_some_and(|local| queue_id.is_some_and(|remote| local.id == remote))
             {
-                self.set_position(target);
+                self.set_position(target).await;
             } else {
                 self.deferred_position = Some(target);
             }
@@ -2074,6 +3789,19 @@ impl Client {
         result
     }
 
+    /// Returns a random order of queue positions.
+    ///
+    /// With a `seed`, the order is the same for every queue of length `len`.
+    #[must_use]
+    fn shuffle_order(len: usize, seed: Option<u64>) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..len).collect();
+        match seed {
+            Some(seed) => order.shuffle(&mut StdRng::seed_from_u64(seed)),
+            None => order.shuffle(&mut rand::rng()),
+        }
+        order
+    }
+
     /// Shuffles or unshuffles the current queue.
     ///
     /// # Arguments
@@ -2099,8 +3827,7 @@ impl Client {
                     info!("shuffling queue");
 
                     let len = queue.tracks.len();
-                    let mut order: Vec<usize> = (0..len).collect();
-                    order.shuffle(&mut rand::rng());
+                    let order = Self::shuffle_order(len, self.shuffle_seed);
 
                     let mut tracks = Vec::with_capacity(len);
                     for i in &order {
@@ -2215,7 +3942,19 @@ impl Client {
                         .tracks_order
                         .iter()
                         .position(|i| *i == player_position as u32)
-                        .unwrap_or_default();
+                        .or_else(|| {
+                            // Not in the shuffle order: look the track up by ID instead.
+                            let track_id = track.id().to_string();
+                            queue.tracks.iter().position(|queued| queued.id == track_id)
+                        })
+                        .unwrap_or_else(|| {
+                            // Tracks that are appended to a shuffled queue are not shuffled,
+                            // so they keep their position.
+                            debug!(
+                                "position {player_position} not in shuffle order; reporting as is"
+                            );
+                            player_position
+                        });
                 }
 
                 let item = QueueItem {
@@ -2239,6 +3978,10 @@ impl Client {
 
                 let command = self.command(controller.clone(), progress);
                 self.send_message(command).await?;
+
+                self.health_tx.send_modify(|health| {
+                    health.last_report = Some(std::time::Instant::now());
+                });
             }
 
             Ok(())
@@ -2267,6 +4010,10 @@ impl Client {
     async fn handle_message(&mut self, message: &WebsocketMessage) -> ControlFlow<Error, ()> {
         match message {
             WebsocketMessage::Text(message) => {
+                if let Some(protocol_log) = self.protocol_log.as_mut() {
+                    protocol_log.write("received", message.as_str());
+                }
+
                 match serde_json::from_str::<Message>(message.as_str()) {
                     Ok(message) => {
                         match message.clone() {
@@ -2306,6 +4053,7 @@ impl Client {
 
                                 if let Err(e) = self.dispatch(from, contents.body).await {
                                     error!("error handling message: {e}");
+                                    self.notify_error(&e);
                                 }
                             }
 
@@ -2398,7 +4146,12 @@ impl Client {
         match body {
             // TODO - Think about maintaining a queue of message IDs to be
             // acknowledged, evictingt them one by one.
-            Body::Acknowledgement { .. } => Ok(()),
+            Body::Acknowledgement {
+                acknowledgement_id, ..
+            } => {
+                self.handle_acknowledgement(&acknowledgement_id);
+                Ok(())
+            }
 
             Body::Close { .. } => self.handle_close().await,
 
@@ -2446,7 +4199,11 @@ impl Client {
             } => self.handle_status(from, &command_id, status).await,
 
             Body::Stop { .. } => {
-                self.player.pause();
+                if self.full_stop {
+                    self.stop_playback().await;
+                } else {
+                    self.player.pause();
+                }
                 Ok(())
             }
 
@@ -2503,6 +4260,10 @@ impl Client {
         }
 
         let json = serde_json::to_string(&message)?;
+        if let Some(protocol_log) = self.protocol_log.as_mut() {
+            protocol_log.write("sent", &json);
+        }
+
         let frame = WebsocketMessage::Text(json.into());
         self.send_frame(frame).await
     }
@@ -2596,3 +4357,133 @@ impl Client {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::track::LocalSource;
+
+    #[test]
+    fn handshake_skip_is_responded_to_immediately() {
+        assert_eq!(
+            Client::immediate_skip_status(false, Status::Error),
+            Some(Status::Error)
+        );
+        assert_eq!(
+            Client::immediate_skip_status(false, Status::OK),
+            Some(Status::OK)
+        );
+    }
+
+    #[test]
+    fn skip_status_follows_connection_state() {
+        let handshake_status = Status::Error;
+
+        // Connected, the handshake skip arrives before the queue.
+        let mut queue_published = false;
+        assert_eq!(
+            Client::immediate_skip_status(queue_published, handshake_status),
+            Some(handshake_status)
+        );
+
+        // The queue is published, so skips are responded to after updating.
+        queue_published = true;
+        assert_eq!(
+            Client::immediate_skip_status(queue_published, handshake_status),
+            None
+        );
+
+        // Reconnecting resets the states, so the next skip is a handshake again.
+        queue_published = false;
+        assert_eq!(
+            Client::immediate_skip_status(queue_published, handshake_status),
+            Some(handshake_status)
+        );
+    }
+
+    #[test]
+    fn skip_with_queue_is_responded_to_after_update() {
+        assert_eq!(Client::immediate_skip_status(true, Status::Error), None);
+        assert_eq!(Client::immediate_skip_status(true, Status::OK), None);
+    }
+
+    fn missing_metadata_track(typ: TrackType) -> Track {
+        Track::local(
+            TrackId::new(1).unwrap(),
+            typ,
+            "",
+            None,
+            Duration::from_secs(1),
+            LocalSource::Buffer(Vec::<u8>::new().into()),
+        )
+    }
+
+    fn metadata_var<'a>(vars: &'a HookVars, key: &str) -> Option<&'a str> {
+        vars.iter()
+            .find_map(|(name, value)| (*name == key).then_some(value.as_str()))
+    }
+
+    #[test]
+    fn missing_metadata_gets_fallbacks() {
+        let fallbacks = MetadataFallbacks {
+            title: Some("Unknown Title".to_string()),
+            artist: Some("Unknown Artist".to_string()),
+            album_title: Some("Unknown Album".to_string()),
+            cover_id: Some("placeholder".to_string()),
+        };
+
+        let vars = Client::metadata_vars(&missing_metadata_track(TrackType::Song), &fallbacks);
+        assert_eq!(metadata_var(&vars, "TITLE"), Some("Unknown Title"));
+        assert_eq!(metadata_var(&vars, "ARTIST"), Some("Unknown Artist"));
+        assert_eq!(metadata_var(&vars, "ALBUM_TITLE"), Some("Unknown Album"));
+        assert_eq!(metadata_var(&vars, "COVER_ID"), Some("placeholder"));
+
+        // Episodes have no album, so they get no album title placeholder.
+        let vars = Client::metadata_vars(&missing_metadata_track(TrackType::Episode), &fallbacks);
+        assert_eq!(metadata_var(&vars, "ALBUM_TITLE"), None);
+    }
+
+    #[test]
+    fn missing_metadata_without_fallbacks() {
+        let vars = Client::metadata_vars(
+            &missing_metadata_track(TrackType::Song),
+            &MetadataFallbacks::default(),
+        );
+        assert_eq!(metadata_var(&vars, "TITLE"), None);
+        assert_eq!(metadata_var(&vars, "ALBUM_TITLE"), None);
+        assert_eq!(metadata_var(&vars, "ARTIST"), Some(""));
+        assert_eq!(metadata_var(&vars, "COVER_ID"), Some(""));
+    }
+
+    #[test]
+    fn present_metadata_is_kept() {
+        let track = Track::local(
+            TrackId::new(1).unwrap(),
+            TrackType::Song,
+            "Artist",
+            Some("Title".to_string()),
+            Duration::from_secs(1),
+            LocalSource::Buffer(Vec::<u8>::new().into()),
+        );
+        let fallbacks = MetadataFallbacks {
+            title: Some("Unknown Title".to_string()),
+            artist: Some("Unknown Artist".to_string()),
+            ..MetadataFallbacks::default()
+        };
+
+        let vars = Client::metadata_vars(&track, &fallbacks);
+        assert_eq!(metadata_var(&vars, "TITLE"), Some("Title"));
+        assert_eq!(metadata_var(&vars, "ARTIST"), Some("Artist"));
+    }
+
+    #[test]
+    fn seeded_shuffle_order_is_deterministic() {
+        let order = Client::shuffle_order(20, Some(42));
+        assert_eq!(order, Client::shuffle_order(20, Some(42)));
+        assert_ne!(order, Client::shuffle_order(20, Some(43)));
+
+        let mut positions = order.clone();
+        positions.sort_unstable();
+        assert_eq!(positions, (0..20).collect::<Vec<_>>());
+    }
+}
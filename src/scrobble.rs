@@ -0,0 +1,189 @@
+//! Scrobbling of played tracks to a webhook.
+//!
+//! Reports tracks that were played for long enough to a configured URL, so
+//! they can be forwarded to Last.fm, ListenBrainz or a similar service
+//! without writing a hook script.
+//!
+//! # Threshold
+//!
+//! Following the conventions of Last.fm, a track is scrobbled once it has
+//! played for a percentage of its duration or a maximum time, whichever
+//! comes first. Tracks shorter than [`MIN_DURATION`] and livestreams are
+//! never scrobbled.
+//!
+//! # Delivery
+//!
+//! Scrobbles are POSTed one at a time as JSON, in the order they were
+//! played:
+//!
+//! ```json
+//! {
+//!   "track_id": "3135556",
+//!   "track_type": "song",
+//!   "artist": "Daft Punk",
+//!   "title": "Harder, Better, Faster, Stronger",
+//!   "album": "Discovery",
+//!   "duration": 224,
+//!   "timestamp": 1700000000
+//! }
+//! ```
+//!
+//! Scrobbles that cannot be delivered, for example when offline, are kept
+//! and retried later, up to [`MAX_PENDING`]. Scrobbles that the webhook
+//! rejects with a client error are dropped.
+
+use std::{collections::VecDeque, time::Duration};
+
+use reqwest::Url;
+use serde::Serialize;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+use crate::{
+    error::{ErrorKind, Result},
+    http::Client as HttpClient,
+};
+
+/// Minimum duration of a track to be scrobbled.
+pub const MIN_DURATION: Duration = Duration::from_secs(30);
+
+/// Maximum number of scrobbles to keep while they cannot be delivered.
+///
+/// The oldest scrobbles are dropped when more tracks are played.
+pub const MAX_PENDING: usize = 1000;
+
+/// Delay before retrying to deliver pending scrobbles.
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// A track that was played for long enough to be scrobbled.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Scrobble {
+    /// Deezer ID of the track
+    pub track_id: String,
+
+    /// Type of the track: "song" or "episode"
+    pub track_type: String,
+
+    /// Artist name or podcast title
+    pub artist: String,
+
+    /// Track or episode title, if known
+    pub title: Option<String>,
+
+    /// Album title, if known
+    pub album: Option<String>,
+
+    /// Duration of the track in seconds
+    pub duration: u64,
+
+    /// Time at which playback of the track started, in seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+/// Returns how long a track of `duration` must play to be scrobbled.
+///
+/// That is `percent` of the duration, but no longer than `max`. Returns
+/// `None` if the track is too short to be scrobbled.
+#[must_use]
+pub fn threshold(duration: Duration, percent: u8, max: Duration) -> Option<Duration> {
+    (duration >= MIN_DURATION).then(|| {
+        duration
+            .mul_f32(f32::from(percent.min(100)) / 100.0)
+            .min(max)
+    })
+}
+
+/// Spawns a task that delivers scrobbles to `url`.
+///
+/// Returns the sender to submit scrobbles to. The task stops when the
+/// sender is dropped.
+#[must_use]
+pub fn spawn(http_client: HttpClient, url: Url) -> UnboundedSender<Scrobble> {
+    let (scrobble_tx, scrobble_rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(run(http_client, url, scrobble_rx));
+    scrobble_tx
+}
+
+/// Delivers scrobbles received on `scrobble_rx` in order.
+///
+/// Returns when the sending half of the channel is dropped.
+async fn run(http_client: HttpClient, url: Url, mut scrobble_rx: UnboundedReceiver<Scrobble>) {
+    let mut pending = VecDeque::new();
+    let mut retry_at = None;
+
+    loop {
+        let retry = async {
+            match retry_at {
+                Some(deadline) => tokio::time::sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            scrobble = scrobble_rx.recv() => {
+                let Some(scrobble) = scrobble else {
+                    break;
+                };
+
+                if pending.len() >= MAX_PENDING {
+                    warn!("too many pending scrobbles; dropping the oldest");
+                    pending.pop_front();
+                }
+                pending.push_back(scrobble);
+
+                // Keep waiting for the retry while offline.
+                if retry_at.is_some() {
+                    continue;
+                }
+            }
+
+            () = retry => retry_at = None,
+        }
+
+        while let Some(scrobble) = pending.front() {
+            match submit(&http_client, &url, scrobble).await {
+                Ok(()) => {
+                    debug!("scrobbled track {}", scrobble.track_id);
+                    pending.pop_front();
+                }
+                Err(e)
+                    if matches!(
+                        e.kind,
+                        ErrorKind::InvalidArgument
+                            | ErrorKind::NotFound
+                            | ErrorKind::PermissionDenied
+                            | ErrorKind::Unauthenticated
+                    ) =>
+                {
+                    error!("scrobble of track {} rejected: {e}", scrobble.track_id);
+                    pending.pop_front();
+                }
+                Err(e) => {
+                    warn!(
+                        "failed to scrobble track {}: {e}; retrying {} pending scrobbles in {}s",
+                        scrobble.track_id,
+                        pending.len(),
+                        RETRY_DELAY.as_secs()
+                    );
+                    retry_at = tokio::time::Instant::now().checked_add(RETRY_DELAY);
+                    break;
+                }
+            }
+        }
+    }
+
+    if !pending.is_empty() {
+        warn!("dropping {} pending scrobbles", pending.len());
+    }
+}
+
+/// Posts a scrobble to `url`.
+///
+/// # Errors
+///
+/// Returns error if the request fails or the webhook does not respond
+/// with a successful status code.
+async fn submit(http_client: &HttpClient, url: &Url, scrobble: &Scrobble) -> Result<()> {
+    let body = serde_json::to_string(scrobble)?;
+    let request = http_client.json(url.clone(), body);
+    http_client.execute(request).await.map(|_| ())
+}
@@ -0,0 +1,142 @@
+//! Download bandwidth limiting.
+//!
+//! Wraps a download stream to limit the rate at which it is read, so that
+//! downloads do not saturate a shared connection. The limit is applied by
+//! delaying the next read until the bytes received so far are within the
+//! rate, after an initial burst that fills the prefetch buffer at full
+//! speed.
+//!
+//! Seeking and reconnecting restart the accounting, as the data is needed
+//! right away.
+
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::Duration,
+};
+
+use futures_util::Stream;
+use stream_download::source::SourceStream;
+
+/// Download stream with an optional rate limit.
+#[derive(Debug)]
+pub struct Throttled<S> {
+    /// The underlying download stream
+    inner: S,
+
+    /// Maximum rate in bytes per second, or `None` for no limit
+    bytes_per_second: Option<u64>,
+
+    /// Number of bytes that are read before the limit applies
+    burst: u64,
+
+    /// Start of the current accounting period
+    started: tokio::time::Instant,
+
+    /// Number of bytes received in the current accounting period
+    received: u64,
+
+    /// Delay before the next read, if over the rate
+    delay: Option<Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl<S> Throttled<S> {
+    /// Wraps `inner` to read at most `bytes_per_second` after `burst` bytes.
+    ///
+    /// No limit applies when `bytes_per_second` is `None`.
+    #[must_use]
+    pub fn new(inner: S, bytes_per_second: Option<u64>, burst: u64) -> Self {
+        Self {
+            inner,
+            bytes_per_second,
+            burst,
+            started: tokio::time::Instant::now(),
+            received: 0,
+            delay: None,
+        }
+    }
+
+    /// Returns the maximum rate in bytes per second, if limited.
+    #[must_use]
+    #[inline]
+    pub fn bytes_per_second(&self) -> Option<u64> {
+        self.bytes_per_second
+    }
+
+    /// Restarts the accounting, allowing another burst.
+    fn restart(&mut self) {
+        self.started = tokio::time::Instant::now();
+        self.received = 0;
+        self.delay = None;
+    }
+}
+
+impl<S> Stream for Throttled<S>
+where
+    S: SourceStream,
+{
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        if let Some(delay) = this.delay.as_mut() {
+            ready!(delay.as_mut().poll(cx));
+            this.delay = None;
+        }
+
+        let item = ready!(Pin::new(&mut this.inner).poll_next(cx));
+        if let (Some(Ok(bytes)), Some(bytes_per_second)) = (&item, this.bytes_per_second) {
+            this.received = this.received.saturating_add(bytes.len() as u64);
+
+            // Bytes beyond the burst are due at the rate since the start.
+            let throttled = this.received.saturating_sub(this.burst);
+            if throttled > 0 && bytes_per_second > 0 {
+                #[expect(clippy::cast_precision_loss)]
+                let due = Duration::from_secs_f64(throttled as f64 / bytes_per_second as f64);
+                if let Some(deadline) = this.started.checked_add(due)
+                    && deadline > tokio::time::Instant::now()
+                {
+                    this.delay = Some(Box::pin(tokio::time::sleep_until(deadline)));
+                }
+            }
+        }
+
+        Poll::Ready(item)
+    }
+}
+
+impl<S> SourceStream for Throttled<S>
+where
+    S: SourceStream,
+{
+    type Params = S::Params;
+    type StreamCreationError = S::StreamCreationError;
+    type StreamError = S::StreamError;
+
+    /// Creates an unlimited stream.
+    async fn create(params: Self::Params) -> Result<Self, Self::StreamCreationError> {
+        S::create(params)
+            .await
+            .map(|inner| Self::new(inner, None, 0))
+    }
+
+    fn content_length(&self) -> Option<u64> {
+        self.inner.content_length()
+    }
+
+    async fn seek_range(&mut self, start: u64, end: Option<u64>) -> io::Result<()> {
+        self.restart();
+        self.inner.seek_range(start, end).await
+    }
+
+    async fn reconnect(&mut self, current_position: u64) -> io::Result<()> {
+        self.restart();
+        self.inner.reconnect(current_position).await
+    }
+
+    fn supports_seek(&self) -> bool {
+        self.inner.supports_seek()
+    }
+}
@@ -39,12 +39,12 @@
 //!
 //! ```rust
 //! use pleezer::tokens::UserToken;
-//! use std::time::{SystemTime, Duration};
+//! use std::time::{Duration, Instant};
 //!
 //! let token = UserToken {
 //!     user_id: 123456789,
 //!     token: "secret_token".to_string(),
-//!     expires_at: SystemTime::now() + Duration::from_secs(3600),
+//!     expires_at: Instant::now() + Duration::from_secs(3600),
 //! };
 //!
 //! if !token.is_expired() {
@@ -54,7 +54,7 @@
 
 use std::{
     fmt,
-    time::{Duration, SystemTime},
+    time::{Duration, Instant},
 };
 
 use crate::protocol::connect::UserId;
@@ -76,12 +76,12 @@ use crate::protocol::connect::UserId;
 ///
 /// ```rust
 /// use pleezer::tokens::UserToken;
-/// use std::time::{SystemTime, Duration};
+/// use std::time::{Duration, Instant};
 ///
 /// let token = UserToken {
 ///     user_id: 123456789,
 ///     token: "secret_token".to_string(),
-///     expires_at: SystemTime::now() + Duration::from_secs(3600),
+///     expires_at: Instant::now() + Duration::from_secs(3600),
 /// };
 ///
 /// // Check if token needs refresh
@@ -99,15 +99,18 @@ pub struct UserToken {
     /// Authentication token string.
     pub token: String,
 
-    /// Timestamp when this token expires.
-    pub expires_at: SystemTime,
+    /// Instant when this token expires.
+    ///
+    /// Measured on the monotonic clock, so it is robust to a skewed or
+    /// adjusted system clock.
+    pub expires_at: Instant,
 }
 
 impl UserToken {
     /// Returns the remaining validity time of the token.
     ///
     /// Calculates how long until the token expires by comparing the expiration
-    /// timestamp with the current system time.
+    /// instant with the current monotonic time.
     ///
     /// # Returns
     ///
@@ -118,12 +121,12 @@ impl UserToken {
     ///
     /// ```rust
     /// use pleezer::tokens::UserToken;
-    /// use std::time::{SystemTime, Duration};
+    /// use std::time::{Duration, Instant};
     ///
     /// let token = UserToken {
     ///     user_id: 123456789,
     ///     token: "secret_token".to_string(),
-    ///     expires_at: SystemTime::now() + Duration::from_secs(3600),
+    ///     expires_at: Instant::now() + Duration::from_secs(3600),
     /// };
     ///
     /// println!("Token valid for: {:?}", token.time_to_live());
@@ -131,15 +134,13 @@ impl UserToken {
     #[must_use]
     #[inline]
     pub fn time_to_live(&self) -> Duration {
-        self.expires_at
-            .duration_since(SystemTime::now())
-            .unwrap_or(Duration::ZERO)
+        self.expires_at.saturating_duration_since(Instant::now())
     }
 
     /// Checks if the token has expired.
     ///
-    /// A token is considered expired if the current system time is equal to
-    /// or later than the expiration timestamp.
+    /// A token is considered expired if the current monotonic time is equal
+    /// to or later than the expiration instant.
     ///
     /// # Returns
     ///
@@ -150,12 +151,12 @@ impl UserToken {
     ///
     /// ```rust
     /// use pleezer::tokens::UserToken;
-    /// use std::time::{SystemTime, Duration};
+    /// use std::time::{Duration, Instant};
     ///
     /// let token = UserToken {
     ///     user_id: 123456789,
     ///     token: "secret_token".to_string(),
-    ///     expires_at: SystemTime::now() + Duration::from_secs(3600),
+    ///     expires_at: Instant::now() + Duration::from_secs(3600),
     /// };
     ///
     /// if token.is_expired() {
@@ -165,7 +166,7 @@ impl UserToken {
     #[must_use]
     #[inline]
     pub fn is_expired(&self) -> bool {
-        self.expires_at <= SystemTime::now()
+        self.expires_at <= Instant::now()
     }
 }
 
@@ -178,12 +179,12 @@ impl UserToken {
 ///
 /// ```rust
 /// use pleezer::tokens::UserToken;
-/// use std::time::{SystemTime, Duration};
+/// use std::time::{Duration, Instant};
 ///
 /// let token = UserToken {
 ///     user_id: 123456789,
 ///     token: "secret_token".to_string(),
-///     expires_at: SystemTime::now() + Duration::from_secs(3600),
+///     expires_at: Instant::now() + Duration::from_secs(3600),
 /// };
 ///
 /// assert_eq!(token.to_string(), "secret_token");
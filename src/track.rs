@@ -52,6 +52,27 @@
 //!    * Manages buffer state
 //!    * Enables seeking within buffered data
 //!
+//! # Local Tracks
+//!
+//! Tracks can also be created from a [`LocalSource`], like a file or an
+//! in-memory buffer. These skip media source resolution and downloading, so
+//! the decoding, processing and output chain can be exercised without
+//! network access, for example in integration tests:
+//!
+//! ```rust
+//! use pleezer::track::{LocalSource, Track, TrackType};
+//!
+//! let track = Track::local(
+//!     track_id,
+//!     TrackType::Song,
+//!     "Artist",
+//!     Some("Title".to_string()),
+//!     Duration::from_secs(30),
+//!     LocalSource::File("test.flac".into()),
+//! );
+//! player.set_queue(vec![track]);
+//! ```
+//!
 //! # Quality Fallback
 //!
 //! When requested quality isn't available, the system attempts fallback in order:
@@ -87,11 +108,17 @@
 //! ```
 
 use std::{
+    collections::BTreeMap,
     fmt,
+    io::Cursor,
     num::NonZeroI64,
     ops::Deref,
+    path::PathBuf,
     str::FromStr,
-    sync::{Arc, Mutex, PoisonError},
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
     time::{Duration, SystemTime},
 };
 
@@ -114,6 +141,7 @@ use crate::{
         gateway::{self, LivestreamUrls},
         media::{self, Cipher, CipherFormat, Data, Format, Medium},
     },
+    throttle::Throttled,
     util::ToF32,
 };
 
@@ -131,7 +159,7 @@ pub const DEFAULT_BITS_PER_SAMPLE: u32 = 16;
 pub type TrackId = NonZeroI64;
 
 /// Type of track content.
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
 #[expect(clippy::module_name_repetitions)]
 pub enum TrackType {
     /// Regular music track from Deezer catalog or user upload
@@ -165,6 +193,128 @@ impl TrackType {
     }
 }
 
+/// Storage backing a track download.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Storage {
+    /// Downloaded into memory
+    Memory,
+    /// Downloaded into a temporary file
+    TempFile,
+}
+
+impl fmt::Display for Storage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Memory => write!(f, "memory"),
+            Self::TempFile => write!(f, "tempfile"),
+        }
+    }
+}
+
+/// Image format of cover art.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ImageFormat {
+    /// JPEG: smaller files
+    #[default]
+    Jpg,
+    /// PNG: higher quality
+    Png,
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Jpg => write!(f, "jpg"),
+            Self::Png => write!(f, "png"),
+        }
+    }
+}
+
+/// Cover art of a track.
+///
+/// Builds image URLs on the Deezer CDN:
+/// * Album art for songs and station logos for livestreams, under `cover`
+/// * Show art for episodes, under `talk`
+///
+/// Images are square, with a resolution of up to [`CoverArt::MAX_RESOLUTION`]
+/// pixels.
+///
+/// # Example
+///
+/// ```rust
+/// use pleezer::track::{CoverArt, ImageFormat, TrackType};
+///
+/// let cover = CoverArt::new("f286f9e7dc818e181c37b944e2461101", TrackType::Song).unwrap();
+/// let url = cover.url(1000, ImageFormat::Png)?;
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct CoverArt {
+    /// Image identifier
+    id: String,
+
+    /// Type of track the image belongs to
+    typ: TrackType,
+}
+
+impl CoverArt {
+    /// Base URL of images on the Deezer CDN.
+    const BASE_URL: &'static str = "https://cdn-images.dzcdn.net/images";
+
+    /// Maximum resolution in pixels.
+    pub const MAX_RESOLUTION: u16 = 1920;
+
+    /// Resolution in pixels that Deezer uses by default.
+    pub const DEFAULT_RESOLUTION: u16 = 500;
+
+    /// Creates cover art from an image identifier.
+    ///
+    /// Returns `None` if the identifier is empty, or contains anything but
+    /// ASCII alphanumeric characters.
+    #[must_use]
+    pub fn new(id: impl Into<String>, typ: TrackType) -> Option<Self> {
+        let id = id.into();
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+
+        Some(Self { id, typ })
+    }
+
+    /// Returns the image identifier.
+    #[must_use]
+    #[inline]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the URL of the image at a resolution in pixels and format.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the resolution is 0 or more than
+    /// [`CoverArt::MAX_RESOLUTION`].
+    pub fn url(&self, resolution: u16, format: ImageFormat) -> Result<Url> {
+        if resolution == 0 || resolution > Self::MAX_RESOLUTION {
+            return Err(Error::out_of_range(format!(
+                "cover resolution must be between 1 and {} pixels",
+                Self::MAX_RESOLUTION
+            )));
+        }
+
+        let kind = match self.typ {
+            TrackType::Episode => "talk",
+            TrackType::Song | TrackType::Livestream => "cover",
+        };
+
+        let url = format!(
+            "{}/{kind}/{}/{resolution}x{resolution}.{format}",
+            Self::BASE_URL,
+            self.id
+        );
+        Ok(Url::parse(&url)?)
+    }
+}
+
 /// External streaming URL configuration.
 ///
 /// Handles streaming URLs for non-standard content:
@@ -206,6 +356,28 @@ impl FromStr for TrackType {
     }
 }
 
+/// Audio data that is read locally instead of downloaded.
+///
+/// Local sources are never encrypted. Their codec is probed from the data.
+#[derive(Clone)]
+pub enum LocalSource {
+    /// Audio file on the local filesystem
+    File(PathBuf),
+
+    /// Audio data in memory
+    Buffer(Arc<[u8]>),
+}
+
+/// Formats the source without dumping buffer contents.
+impl fmt::Debug for LocalSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => f.debug_tuple("File").field(path).finish(),
+            Self::Buffer(data) => write!(f, "Buffer({} bytes)", data.len()),
+        }
+    }
+}
+
 /// Represents a Deezer track with metadata and download/buffering state.
 ///
 /// Combines track metadata (title, artist, etc) with:
@@ -247,10 +419,18 @@ pub struct Track {
     /// Whether content is served from external source
     external: bool,
 
+    /// Whether the song has explicit lyrics.
+    /// Always `false` for episodes and livestreams.
+    explicit: bool,
+
     /// External URL for direct streaming.
     /// Used by episodes and livestreams.
     external_url: Option<ExternalUrl>,
 
+    /// Host that the content is downloaded from.
+    /// Set when the download starts.
+    source_host: Option<String>,
+
     /// Title of the content.
     /// None for livestreams which only have station name.
     title: Option<String>,
@@ -284,6 +464,10 @@ pub struct Track {
     /// May be lower than requested if any higher quality was unavailable.
     quality: AudioQuality,
 
+    /// Audio quality that was requested when getting the medium.
+    /// Compare with `quality` to detect a fallback to lower quality.
+    requested_quality: AudioQuality,
+
     /// Total duration of the track.
     /// Not available for livestreams.
     duration: Option<Duration>,
@@ -292,6 +476,18 @@ pub struct Track {
     /// Protected by mutex for concurrent access from download task.
     buffered: Arc<Mutex<Option<Duration>>>,
 
+    /// Whether the download completed with fewer bytes than expected.
+    /// Set from the download task when the download completes.
+    truncated: Arc<AtomicBool>,
+
+    /// Position of the decoder in microseconds of media time.
+    /// Set from the decoder as packets are decoded, ahead of the output.
+    decoded: Arc<AtomicU64>,
+
+    /// Number of samples that clipped at the output.
+    /// Set from the audio thread when clip detection is enabled.
+    clipped: Arc<AtomicU64>,
+
     /// Total size of the audio file in bytes.
     /// Available only after download begins.
     /// Not available for livestreams.
@@ -305,6 +501,14 @@ pub struct Track {
     /// None if download hasn't started or was reset.
     handle: Option<StreamHandle>,
 
+    /// Local audio data to play instead of downloading.
+    /// None for tracks from the Deezer catalog.
+    local: Option<LocalSource>,
+
+    /// Whether the local source was opened for playback.
+    /// Local tracks have no download handle.
+    local_open: bool,
+
     /// Whether the track is available for download.
     /// Only available for podcasts and episodes.
     /// Songs have this always set to `true`.
@@ -335,12 +539,29 @@ pub struct Track {
     /// Set by player after decoder initialization.
     pub channels: Option<u16>,
 
+    /// Estimated integrated loudness of the track in LUFS.
+    /// Set by player after decoder initialization, from the Deezer gain or
+    /// `ReplayGain` metadata, whichever is available.
+    pub loudness: Option<f32>,
+
+    /// Buffer size in bytes chosen for the download.
+    /// Set by player when the download starts.
+    pub buffer_size: Option<usize>,
+
+    /// Storage used for the download.
+    /// Set by player when the download starts.
+    pub storage: Option<Storage>,
+
     /// Fallback track to use when primary track is unavailable.
     /// * Contains complete track metadata
     /// * Used for alternative versions of same song
     /// * Swapped with primary track when fallback is needed
     /// * Reset when switching to preserve download state
     fallback: Option<Box<Self>>,
+
+    /// ID of the originally requested track, when its fallback is playing
+    /// instead. Prevents swapping back when the download is restarted.
+    original_id: Option<TrackId>,
 }
 
 /// Internal stream state for content download.
@@ -414,6 +635,13 @@ impl Track {
     /// * Reasonable startup latency
     pub const PREFETCH_DURATION: Duration = Duration::from_secs(3);
 
+    /// Duration up to which a track is considered very short.
+    ///
+    /// Such tracks, like jingles or sound effects, end before the usual
+    /// prefetch and preload windows would apply. They are downloaded in
+    /// full before playback and the next track is preloaded right away.
+    pub const SHORT_DURATION: Duration = Self::PREFETCH_DURATION.saturating_mul(2);
+
     /// Default prefetch size in bytes when bitrate is unknown.
     ///
     /// Used when:
@@ -424,6 +652,25 @@ impl Track {
     /// Value of 60KB matches official client behavior.
     const PREFETCH_DEFAULT: usize = 60 * 1024;
 
+    /// Delay before requesting a source again that responded with 0 bytes.
+    const ZERO_BYTE_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+    /// Multiple of the bitrate that downloads are never limited below.
+    ///
+    /// Leaves headroom for network jitter, so a limited download still
+    /// keeps ahead of playback.
+    const DOWNLOAD_RATE_HEADROOM: usize = 2;
+
+    /// Returns the ID of the originally requested track, if a fallback
+    /// version of it is playing instead.
+    ///
+    /// In that case, `id()` returns the ID of the fallback that is playing.
+    #[must_use]
+    #[inline]
+    pub fn original_id(&self) -> Option<TrackId> {
+        self.original_id
+    }
+
     /// Returns the track's unique identifier.
     #[must_use]
     #[inline]
@@ -451,6 +698,15 @@ impl Track {
         self.available
     }
 
+    /// Returns whether the song has explicit lyrics.
+    ///
+    /// Always false for episodes and livestreams.
+    #[must_use]
+    #[inline]
+    pub fn is_explicit(&self) -> bool {
+        self.explicit
+    }
+
     /// Returns the track type.
     #[must_use]
     #[inline]
@@ -502,13 +758,19 @@ impl Track {
     /// * `resolution` is the desired size in pixels (up to 1920)
     /// * `format` is either `jpg` (smaller) or `png` (higher quality)
     ///
-    /// Deezer's default is 500x500.jpg
+    /// Deezer's default is 500x500.jpg. See `cover_art()` to build these URLs.
     #[must_use]
     #[inline]
     pub fn cover_id(&self) -> &str {
         &self.cover_id
     }
 
+    /// Returns the cover art of this track, if any.
+    #[must_use]
+    pub fn cover_art(&self) -> Option<CoverArt> {
+        CoverArt::new(self.cover_id.as_str(), self.typ)
+    }
+
     /// Returns the track's expiration time.
     ///
     /// After this time, the track becomes unavailable for download
@@ -519,6 +781,46 @@ impl Track {
         self.expiry
     }
 
+    /// Returns whether the track's access token has expired.
+    ///
+    /// Tracks without an expiry time, like livestreams, never expire.
+    #[must_use]
+    pub fn is_expired(&self) -> bool {
+        self.expiry
+            .is_some_and(|expiry| expiry <= SystemTime::now())
+    }
+
+    /// Refreshes the access tokens of this track from a freshly fetched copy.
+    ///
+    /// Only the tokens and their expiry times are updated, so that download
+    /// state and metadata are kept. When this track has fallen back to its
+    /// fallback track, the tokens are matched to the swapped tracks.
+    pub fn refresh_tokens(&mut self, fresh: &Track) {
+        let (primary, fallback) = if self.original_id.is_some() {
+            (fresh.fallback.as_deref(), Some(fresh))
+        } else {
+            (Some(fresh), fresh.fallback.as_deref())
+        };
+
+        if let Some(primary) = primary {
+            self.copy_token(primary);
+        }
+
+        if let Some(own) = self.fallback.as_deref_mut()
+            && let Some(fallback) = fallback
+        {
+            own.copy_token(fallback);
+        }
+    }
+
+    /// Copies the access token and expiry time from a track with the same ID.
+    fn copy_token(&mut self, other: &Track) {
+        if self.id == other.id {
+            self.token.clone_from(&other.token);
+            self.expiry = other.expiry;
+        }
+    }
+
     /// Returns whether this is a livestream.
     ///
     /// Livestreams have different behaviors:
@@ -555,12 +857,24 @@ impl Track {
     }
 
     /// Returns the track's audio quality.
+    ///
+    /// This is the quality that was negotiated when the download started,
+    /// which may be lower than the [requested quality](Self::requested_quality).
     #[must_use]
     #[inline]
     pub fn quality(&self) -> AudioQuality {
         self.quality
     }
 
+    /// Returns the audio quality that was requested for this track.
+    ///
+    /// `AudioQuality::Unknown` until media was requested.
+    #[must_use]
+    #[inline]
+    pub fn requested_quality(&self) -> AudioQuality {
+        self.requested_quality
+    }
+
     /// Returns the encryption cipher used for this track.
     #[must_use]
     #[inline]
@@ -660,7 +974,12 @@ impl Track {
     /// API endpoint for retrieving media sources.
     const MEDIA_ENDPOINT: &'static str = "v1/get_url";
 
-    fn get_external_medium(&self, quality: AudioQuality) -> Result<MediumType> {
+    fn get_external_medium(
+        &self,
+        quality: AudioQuality,
+        preferred_codec: Codec,
+        max_kbps: Option<usize>,
+    ) -> Result<MediumType> {
         let external_url = self.external_url.as_ref().ok_or_else(|| {
             Error::unavailable(format!("external {} {self} has no urls", self.typ))
         })?;
@@ -673,12 +992,20 @@ impl Track {
                 }]
             }
             ExternalUrl::WithQuality(codec_urls) => {
-                // Filter out sources that are of higher quality than requested.
+                // Filter out sources that are of higher quality than requested
+                // or than the configured maximum.
                 let mut urls = Vec::new();
                 for (bitrate, codec_url) in codec_urls.sort_by_bitrate().into_iter().rev() {
-                    if quality.bitrate().is_none_or(|kbps| bitrate <= kbps) {
-                        // Prefer AAC over MP3 if both are available for the same bitrate.
-                        if let Some(url) = codec_url.aac.or(codec_url.mp3) {
+                    if quality.bitrate().is_none_or(|kbps| bitrate <= kbps)
+                        && max_kbps.is_none_or(|kbps| bitrate <= kbps)
+                    {
+                        // Prefer the configured codec if both are available for the same bitrate.
+                        let url = if preferred_codec == Codec::MP3 {
+                            codec_url.mp3.or(codec_url.aac)
+                        } else {
+                            codec_url.aac.or(codec_url.mp3)
+                        };
+                        if let Some(url) = url {
                             urls.push(media::Source {
                                 url,
                                 provider: String::default(),
@@ -719,6 +1046,9 @@ impl Track {
     /// * `client` - HTTP client for API requests
     /// * `media_url` - Base URL for media content
     /// * `quality` - Preferred audio quality
+    /// * `quality_overrides` - Preferred audio quality per content type
+    /// * `livestream_codec` - Preferred codec for livestreams
+    /// * `max_livestream_kbps` - Maximum bitrate for livestreams
     /// * `license_token` - Token authorizing media access
     ///
     /// # Errors
@@ -744,13 +1074,20 @@ impl Track {
     /// exists and has available media, returns `MediumType::Fallback`. The
     /// track's metadata will be swapped with the fallback version when
     /// playback begins.
+    #[expect(clippy::too_many_arguments)]
     pub async fn get_medium(
-        &self,
+        &mut self,
         client: &http::Client,
         media_url: &Url,
         quality: AudioQuality,
+        quality_overrides: &BTreeMap<TrackType, AudioQuality>,
+        livestream_codec: Codec,
+        max_livestream_kbps: Option<usize>,
         license_token: impl Into<String>,
     ) -> Result<MediumType> {
+        // Content types may be configured to use a different quality.
+        let quality = quality_overrides.get(&self.typ).copied().unwrap_or(quality);
+
         if !self.available() {
             return Err(Error::unavailable(format!(
                 "{} {self} is not available for download",
@@ -758,6 +1095,8 @@ impl Track {
             )));
         }
 
+        self.requested_quality = quality;
+
         if let Some(expiry) = self.expiry
             && expiry <= SystemTime::now()
         {
@@ -769,7 +1108,7 @@ impl Track {
         }
 
         if self.external {
-            return self.get_external_medium(quality);
+            return self.get_external_medium(quality, livestream_codec, max_livestream_kbps);
         }
 
         let track_token = self.token.as_ref().ok_or_else(|| {
@@ -887,12 +1226,15 @@ impl Track {
     ///
     /// Returns error if:
     /// * No valid source found
+    /// * No source is on a trusted host
     /// * Track unavailable
     /// * Network error occurs
-    /// * HTTP response status is not successful (not 2xx)
+    /// * HTTP response status is not successful (not 2xx); `Unauthenticated`
+    ///   when a source responded with 403 Forbidden
     /// * Download cannot start
     async fn open_stream(&self, client: &http::Client, medium: &Medium) -> Result<StreamUrl> {
         let now = SystemTime::now();
+        let mut forbidden = false;
 
         // Deezer usually returns multiple sources for a track. The official
         // client seems to always use the first one. We start with the first
@@ -904,6 +1246,16 @@ impl Track {
                 continue;
             };
 
+            // Guard against downloads from arbitrary hosts. Redirects to
+            // untrusted hosts are refused by the media client.
+            if !client.is_trusted_host(host_str) {
+                warn!(
+                    "skipping source with untrusted host {host_str} for {} {self}",
+                    self.typ
+                );
+                continue;
+            }
+
             // Check if the track is in a timeframe where it can be downloaded.
             // If not, it can be that the download link expired and needs to be
             // refreshed, that the track is not available yet, or that the track is
@@ -930,7 +1282,12 @@ impl Track {
             }
 
             // Perform the request and stream the response.
-            match HttpStream::new(client.unlimited.clone(), source.url.clone()).await {
+            match HttpStream::new(
+                client.media_client_for(&source.url).clone(),
+                source.url.clone(),
+            )
+            .await
+            {
                 Ok(stream) => {
                     debug!("starting download of {} {self} from {host_str}", self.typ);
                     return Ok(StreamUrl {
@@ -939,6 +1296,13 @@ impl Track {
                     });
                 }
                 Err(err) => {
+                    // A forbidden response usually means that the license token is stale.
+                    if let stream_download::http::HttpStreamError::ResponseFailure(response) = &err
+                        && response.status() == reqwest::StatusCode::FORBIDDEN
+                    {
+                        forbidden = true;
+                    }
+
                     warn!(
                         "failed to start download of {} {self} from {host_str}: {err}",
                         self.typ
@@ -947,6 +1311,13 @@ impl Track {
             }
         }
 
+        if forbidden {
+            return Err(Error::unauthenticated(format!(
+                "download of {} {self} forbidden; license token may be stale",
+                self.typ
+            )));
+        }
+
         Err(Error::unavailable(format!(
             "no valid sources found for {} {self}",
             self.typ
@@ -954,6 +1325,8 @@ impl Track {
     }
 
     fn init_download(&mut self, url: &Url) {
+        self.source_host = url.host_str().map(ToString::to_string);
+
         // Determine the codec and bitrate of the track.
         if let Some(ExternalUrl::WithQuality(urls)) = &self.external_url {
             // Livestreams specify the codec and bitrate with the URL.
@@ -1023,6 +1396,8 @@ impl Track {
     /// * `client` - HTTP client for download
     /// * `medium` - Media source information
     /// * `storage` - Storage provider with prefetch buffer
+    /// * `zero_byte_retries` - Times to request the source again when it
+    ///   responds with an empty body
     ///
     /// # Returns
     ///
@@ -1048,6 +1423,7 @@ impl Track {
     /// * Track unavailable
     /// * Network error occurs
     /// * Download cannot start
+    /// * Source still responds with 0 bytes after all retries
     ///
     /// # Progress Tracking
     ///
@@ -1065,6 +1441,8 @@ impl Track {
         client: &http::Client,
         medium: &MediumType,
         storage: P,
+        zero_byte_retries: u8,
+        max_download_kbps: Option<usize>,
     ) -> Result<AudioFile>
     where
         P: StorageProvider + Sync + 'static,
@@ -1073,8 +1451,11 @@ impl Track {
         let medium = match medium {
             MediumType::Primary(medium) => medium,
             MediumType::Fallback(medium) => {
-                if let Some(fallback) = &mut self.fallback {
+                if self.original_id.is_none()
+                    && let Some(fallback) = &mut self.fallback
+                {
                     warn!("falling back {} {} to {fallback}", self.typ, self.id);
+                    self.original_id = Some(self.id);
                     std::mem::swap(&mut self.id, &mut fallback.id);
                     std::mem::swap(&mut self.artist, &mut fallback.artist);
                     std::mem::swap(&mut self.album_title, &mut fallback.album_title);
@@ -1089,7 +1470,26 @@ impl Track {
             }
         };
 
-        let stream_url = self.open_stream(client, medium).await?;
+        // Some CDN edges transiently respond with an empty body, so request
+        // the source again before giving up.
+        let mut attempt = 0;
+        let stream_url = loop {
+            let stream_url = self.open_stream(client, medium).await?;
+            if stream_url.stream.content_length() != Some(0) {
+                break stream_url;
+            }
+
+            if attempt >= zero_byte_retries {
+                return Err(Error::data_loss(format!("{} is 0 bytes", self.typ)));
+            }
+
+            attempt += 1;
+            warn!(
+                "{} {self} is 0 bytes; retrying (attempt {attempt} of {zero_byte_retries})",
+                self.typ
+            );
+            tokio::time::sleep(Self::ZERO_BYTE_RETRY_DELAY).await;
+        };
         let stream = stream_url.stream;
         let url = stream_url.url;
 
@@ -1099,12 +1499,8 @@ impl Track {
 
         // Set the file size if known. This is used to calculate the prefetch size.
         if let Some(file_size) = stream.content_length() {
-            if file_size > 0 {
-                info!("downloading {file_size} bytes for {} {self}", self.typ);
-                self.file_size = Some(file_size);
-            } else {
-                return Err(Error::data_loss(format!("{} is 0 bytes", self.typ)));
-            }
+            info!("downloading {file_size} bytes for {} {self}", self.typ);
+            self.file_size = Some(file_size);
         } else {
             info!("downloading {} {self} with unknown file size", self.typ);
         }
@@ -1118,17 +1514,51 @@ impl Track {
             self.typ
         );
 
+        // Limit the download rate, but never below what is needed to keep up
+        // with real-time playback.
+        let bytes_per_second = max_download_kbps.map(|max_kbps| {
+            let min_kbps = self
+                .bitrate()
+                .map_or(0, |kbps| kbps.saturating_mul(Self::DOWNLOAD_RATE_HEADROOM));
+            let kbps = max_kbps.max(min_kbps);
+            if kbps > max_kbps {
+                debug!(
+                    "raising download limit of {} {self} to {kbps} kbps to keep up with playback",
+                    self.typ
+                );
+            }
+            u64::try_from(kbps).unwrap_or(u64::MAX).saturating_mul(1000) / 8
+        });
+        let stream = Throttled::new(stream, bytes_per_second, prefetch_size);
+
         // A progress callback that logs the download progress.
         let track_str = self.to_string();
         let track_typ = self.typ.to_string();
         let duration = self.duration;
         let buffered = Arc::clone(&self.buffered);
+        let truncated = Arc::clone(&self.truncated);
         let file_size = self.file_size;
-        let callback = move |_: &HttpStream<_>,
+        let callback = move |_: &Throttled<HttpStream<_>>,
                              stream: StreamState,
                              _: &tokio_util::sync::CancellationToken| {
             match stream.phase {
                 StreamPhase::Complete => {
+                    // Verify that the download has the length that the server announced.
+                    // The decrypted stream has the same length as the encrypted one.
+                    if let Some(file_size) = file_size
+                        && stream.current_position != file_size
+                    {
+                        error!(
+                            "download of {track_typ} {track_str} is {} bytes, expected {file_size}",
+                            stream.current_position
+                        );
+
+                        // Keep the buffered duration as-is, so the track is not
+                        // considered complete.
+                        truncated.store(true, Ordering::Relaxed);
+                        return;
+                    }
+
                     info!("completed download of {track_typ} {track_str}");
 
                     // Prevent rounding errors and set the buffered duration
@@ -1182,6 +1612,130 @@ impl Track {
         AudioFile::try_from_download(self, download)
     }
 
+    /// Creates a track that plays from a local source instead of downloading.
+    ///
+    /// Local tracks bypass media source resolution and downloads, so they can
+    /// be played without network access. They are available, unencrypted and
+    /// never expire.
+    #[must_use]
+    pub fn local(
+        id: TrackId,
+        typ: TrackType,
+        artist: impl Into<String>,
+        title: Option<String>,
+        duration: Duration,
+        source: LocalSource,
+    ) -> Self {
+        Self {
+            typ,
+            id,
+            token: None,
+            title,
+            artist: artist.into(),
+            album_title: None,
+            cover_id: String::new(),
+            duration: Some(duration),
+            gain: None,
+            expiry: None,
+            quality: AudioQuality::Unknown,
+            requested_quality: AudioQuality::Unknown,
+            buffered: Arc::new(Mutex::new(None)),
+            truncated: Arc::new(AtomicBool::new(false)),
+            decoded: Arc::new(AtomicU64::new(0)),
+            clipped: Arc::new(AtomicU64::new(0)),
+            file_size: None,
+            cipher: Cipher::NONE,
+            handle: None,
+            local: Some(source),
+            local_open: false,
+            available: true,
+            external: false,
+            explicit: false,
+            external_url: None,
+            source_host: None,
+            bitrate: None,
+            codec: None,
+            sample_rate: None,
+            bits_per_sample: None,
+            channels: None,
+            loudness: None,
+            buffer_size: None,
+            storage: None,
+            fallback: None,
+            original_id: None,
+        }
+    }
+
+    /// Sets the replay gain of the track in decibels.
+    ///
+    /// Without it, volume normalization falls back to `ReplayGain` metadata
+    /// in the audio data.
+    #[must_use]
+    pub fn with_gain(mut self, gain: f32) -> Self {
+        self.gain = Some(gain);
+        self
+    }
+
+    /// Returns whether this track plays from a local source.
+    #[must_use]
+    #[inline]
+    pub fn is_local(&self) -> bool {
+        self.local.is_some()
+    }
+
+    /// Opens the local source of this track for playback.
+    ///
+    /// The track is completely buffered right away, as all of its audio data
+    /// is available.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// * The track has no local source
+    /// * The local file cannot be opened
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffered lock is poisoned.
+    pub fn open_local(&mut self) -> Result<AudioFile> {
+        let (file, byte_len) = match &self.local {
+            Some(LocalSource::File(path)) => {
+                let file = std::fs::File::open(path)?;
+                let byte_len = file.metadata()?.len();
+                (AudioFile::from_reader(file, byte_len), byte_len)
+            }
+            Some(LocalSource::Buffer(data)) => {
+                let byte_len = data.len().try_into()?;
+                (
+                    AudioFile::from_reader(Cursor::new(Arc::clone(data)), byte_len),
+                    byte_len,
+                )
+            }
+            None => {
+                return Err(Error::failed_precondition(format!(
+                    "{} {self} has no local source",
+                    self.typ
+                )));
+            }
+        };
+
+        info!("opening {byte_len} bytes for local {} {self}", self.typ);
+        self.file_size = Some(byte_len);
+        self.local_open = true;
+        *self.buffered.lock().unwrap() = self.duration;
+
+        Ok(file)
+    }
+
+    /// Returns whether the track was opened for playback.
+    ///
+    /// That is when its download has started, or its local source was opened.
+    #[must_use]
+    #[inline]
+    pub fn is_opened(&self) -> bool {
+        self.handle.is_some() || self.local_open
+    }
+
     /// Returns the current download handle if active.
     ///
     /// Returns None if:
@@ -1209,6 +1763,7 @@ impl Track {
     /// * Download handle
     /// * File size information
     /// * Buffer progress
+    /// * Integrity check result
     ///
     /// For livestreams, this will clear any accumulated playback duration
     /// since they don't have a traditional buffer concept.
@@ -1220,8 +1775,60 @@ impl Track {
     /// Panics if the buffered lock is poisoned.
     pub fn reset_download(&mut self) {
         self.handle = None;
+        self.local_open = false;
         self.file_size = None;
+        self.buffer_size = None;
+        self.storage = None;
         *self.buffered.lock().unwrap() = None;
+        self.truncated.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns whether the download completed with fewer or more bytes
+    /// than the server announced.
+    ///
+    /// Deezer does not provide checksums, so the download is verified
+    /// against its expected length. A truncated download indicates
+    /// corrupted data from the CDN rather than a wrong decryption key.
+    #[must_use]
+    #[inline]
+    pub fn is_truncated(&self) -> bool {
+        self.truncated.load(Ordering::Relaxed)
+    }
+
+    /// Returns how far the track has been decoded, in media time.
+    ///
+    /// This runs ahead of the audible position by however much audio is
+    /// queued in the output, but is not subject to the rounding of sample
+    /// counting after resampling. Compare with the position of the sink to
+    /// measure drift.
+    #[must_use]
+    #[inline]
+    pub fn decoded(&self) -> Duration {
+        Duration::from_micros(self.decoded.load(Ordering::Relaxed))
+    }
+
+    /// Returns the number of samples that clipped at the output.
+    ///
+    /// Only counted when clip detection is enabled. Counts from the last
+    /// time the track was loaded.
+    #[must_use]
+    #[inline]
+    pub fn clipped_samples(&self) -> u64 {
+        self.clipped.load(Ordering::Relaxed)
+    }
+
+    /// Returns a handle to count clipped samples from the audio thread.
+    #[must_use]
+    #[inline]
+    pub(crate) fn clipped_handle(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.clipped)
+    }
+
+    /// Returns a handle to update the decoder position from the decoder.
+    #[must_use]
+    #[inline]
+    pub(crate) fn decoded_handle(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.decoded)
     }
 
     /// Returns the total file size if known.
@@ -1259,6 +1866,44 @@ impl Track {
         self.bitrate
     }
 
+    /// Returns the host that the content is downloaded from.
+    ///
+    /// Only the host is kept, as the full URL may contain access tokens.
+    /// Returns `None` before the download has started.
+    #[must_use]
+    #[inline]
+    pub fn source_host(&self) -> Option<&str> {
+        self.source_host.as_deref()
+    }
+
+    /// Describes the external sources of the content, for diagnostics.
+    ///
+    /// Lists the host of each external URL, with the codec and bitrate for
+    /// livestreams, from the highest bitrate to the lowest. Paths and query
+    /// strings are left out, as they may contain access tokens.
+    ///
+    /// Empty for content without external URLs, like songs.
+    #[must_use]
+    pub fn external_sources(&self) -> Vec<String> {
+        let host = |url: &Url| url.host_str().unwrap_or("unknown host").to_string();
+        match &self.external_url {
+            Some(ExternalUrl::Direct(url)) => vec![host(url)],
+            Some(ExternalUrl::WithQuality(urls)) => urls
+                .sort_by_bitrate()
+                .into_iter()
+                .rev()
+                .flat_map(|(bitrate, codec_url)| {
+                    [(Codec::ADTS, codec_url.aac), (Codec::MP3, codec_url.mp3)]
+                        .into_iter()
+                        .filter_map(move |(codec, url)| {
+                            url.map(|url| format!("{} ({codec} {bitrate} kbps)", host(&url)))
+                        })
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
     /// Returns the audio codec used for this content.
     ///
     /// Possible codecs:
@@ -1273,6 +1918,17 @@ impl Track {
         self.codec
     }
 
+    /// Returns whether the track is very short.
+    ///
+    /// See [`SHORT_DURATION`](Self::SHORT_DURATION). Livestreams and tracks
+    /// of unknown duration are never short.
+    #[must_use]
+    #[inline]
+    pub fn is_short(&self) -> bool {
+        self.duration
+            .is_some_and(|duration| duration <= Self::SHORT_DURATION)
+    }
+
     /// Returns the size of audio data to prefetch before playback.
     ///
     /// The prefetch size is calculated based on:
@@ -1290,6 +1946,9 @@ impl Track {
     /// For unknown bitrates:
     /// * Uses default size of 60KB
     ///
+    /// For [very short](Self::is_short) tracks with a known file size, the
+    /// whole file is prefetched.
+    ///
     /// # Examples
     ///
     /// * 320kbps MP3: ~120KB prefetch
@@ -1301,6 +1960,12 @@ impl Track {
     /// * Configuring storage buffer size
     #[must_use]
     pub fn prefetch_size(&self) -> usize {
+        if self.is_short()
+            && let Some(file_size) = self.file_size
+        {
+            return file_size.try_into().unwrap_or(usize::MAX);
+        }
+
         let mut prefetch_size = Self::PREFETCH_DEFAULT;
         if let Some(kbps) = self.bitrate {
             prefetch_size = (kbps * 1000 / 8)
@@ -1327,13 +1992,16 @@ impl Track {
 /// * Livestreams - Uses station metadata and quality streams
 impl From<gateway::ListData> for Track {
     fn from(item: gateway::ListData) -> Self {
-        let (gain, album_title) = if let gateway::ListData::Song {
-            gain, album_title, ..
+        let (gain, album_title, explicit) = if let gateway::ListData::Song {
+            gain,
+            album_title,
+            explicit,
+            ..
         } = &item
         {
-            (gain.as_ref(), Some(album_title))
+            (gain.as_ref(), Some(album_title), *explicit)
         } else {
-            (None, None)
+            (None, None, false)
         };
 
         let (available, external, external_url, fallback) = match &item {
@@ -1375,19 +2043,31 @@ impl From<gateway::ListData> for Track {
             gain: gain.map(|gain| gain.to_f32_lossy()),
             expiry: item.expiry(),
             quality: AudioQuality::Unknown,
+            requested_quality: AudioQuality::Unknown,
             buffered: Arc::new(Mutex::new(None)),
+            truncated: Arc::new(AtomicBool::new(false)),
+            decoded: Arc::new(AtomicU64::new(0)),
+            clipped: Arc::new(AtomicU64::new(0)),
             file_size: None,
             cipher: Cipher::BF_CBC_STRIPE,
             handle: None,
+            local: None,
+            local_open: false,
             available,
             external,
+            explicit,
             external_url,
+            source_host: None,
             bitrate: None,
             codec: None,
             sample_rate: None,
             bits_per_sample: None,
             channels: None,
+            loudness: None,
+            buffer_size: None,
+            storage: None,
             fallback: fallback.map(|boxed| Box::new((*boxed).into())),
+            original_id: None,
         }
     }
 }
@@ -1439,3 +2119,38 @@ fn find_codec_bitrate(haystack: &LivestreamUrls, needle: &Url) -> Option<(Codec,
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local_track(id: i64, duration: Duration, len: usize) -> Track {
+        Track::local(
+            TrackId::new(id).unwrap(),
+            TrackType::Song,
+            "Artist",
+            None,
+            duration,
+            LocalSource::Buffer(vec![0; len].into()),
+        )
+    }
+
+    #[test]
+    fn short_track_in_queue_is_prefetched_in_full() {
+        let mut queue = vec![
+            local_track(1, Duration::from_secs(180), 1_000_000),
+            local_track(2, Duration::from_secs(1), 20_000),
+            local_track(3, Duration::from_secs(180), 1_000_000),
+        ];
+        for track in &mut queue {
+            track.open_local().unwrap();
+        }
+
+        let short: Vec<bool> = queue.iter().map(Track::is_short).collect();
+        assert_eq!(short, [false, true, false]);
+
+        assert_eq!(queue[1].prefetch_size(), 20_000);
+        assert_eq!(queue[0].prefetch_size(), Track::PREFETCH_DEFAULT);
+        assert_eq!(queue[2].prefetch_size(), Track::PREFETCH_DEFAULT);
+    }
+}